@@ -1,3 +1,5 @@
+use crate::keymap::Context;
+
 
 #[derive(PartialEq)]
 pub enum Action {
@@ -10,4 +12,14 @@ pub enum Action {
     Window2Request,
     Window3Request,
     HistoryRequest,
+    StopStream,
+    WebSocketCommandRequest,
+    WebSocketSaveRequest,
+    GraphQLIntrospectionRequest,
+    GrpcLoadRequest,
+    GrpcTemplateRequest,
+    CollectionRunRequest,
+    StopCollectionRun,
+    VariablesSaveRequest,
+    HelpRequest(Context),
 }
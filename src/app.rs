@@ -1,16 +1,27 @@
-use std::{io::{self}, str::FromStr};
+use std::{io::{self}, sync::mpsc::{Receiver, TryRecvError}, time::Duration};
+use crossterm::event::{Event, KeyEventKind};
+use prost_reflect::DescriptorPool;
 use ratatui::{layout::{Constraint, Direction, Layout}, Frame};
-use tui_textarea::{Input, Key};
+use serde_json::Value;
+use tui_textarea::Input;
 use crate::{
-    action::Action, components::{
+    action::Action, config::Config, crash_report, components::{
+        help::Help,
         history::History,
         parameters::Parameters,
         response::Response,
-        url::Url, Component
+        runner::Runner,
+        url::Url,
+        variables::VariableList,
+        websocket::WebSocket, Component
     },
-    lazycurl_file::LazyCurlFile, tui, utils::curl_service::curl_call,http_method::HTTPMethod
+    http_file::HttpFile, keymap::{Command, Context}, lazycurl_file::{LazyCurlFile, SavedResponse}, script::{self, run_pre_request}, tui, variables::Variables, utils::{curl_service::{curl_in_background, curl_stream, CurlRequest, CurlResponse, RequestHandle, StreamHandle, StreamMessage}, graphql::{add_json_content_type, introspect_in_background, GraphQLBody, Schema}, grpc_service::{grpc_in_background, load_descriptors_in_background, GrpcEndpoint, GrpcProtocol, GrpcResponse}, runner::{run_in_background, RunnerHandle, RunnerMessage}, websocket_service::{websocket_connect, WebSocketCommand, WebSocketEvent, WebSocketHandle}},http_method::HTTPMethod,
 };
 
+/// How long the event loop waits for input before checking requests,
+/// streamed responses, WebSocket sessions and collection runs for new data
+const TICK_RATE: Duration = Duration::from_millis(100);
+
 #[derive(PartialEq)]
 pub enum SelectedComponent {
     Main,
@@ -18,16 +29,59 @@ pub enum SelectedComponent {
     Response,
     History,
     Parameters,
+    Runner,
+    Variables,
+}
+
+/// A request sent in the background, saved to history with its response
+pub struct PendingRequest {
+    handle: RequestHandle,
+    lazycurl_file: LazyCurlFile,
+    charset: Option<String>,
+}
+
+/// A gRPC call sent in the background, saved to history with its response
+pub struct PendingGrpcCall {
+    receiver: Receiver<Result<GrpcResponse, String>>,
+    lazycurl_file: LazyCurlFile,
+}
+
+/// gRPC services loading in the background, `call` is the call to make
+/// once they are loaded when the load was started by sending the request
+pub struct PendingGrpcLoad {
+    receiver: Receiver<Result<DescriptorPool, String>>,
+    call: Option<GrpcProtocol>,
+}
+
+/// A GraphQL schema fetched in the background, cached for `url`
+pub struct PendingIntrospection {
+    receiver: Receiver<Result<Schema, String>>,
+    url: String,
 }
 
 pub struct App<'a> {
     pub exit: bool,
     pub url_component: Url<'a>,
-    pub response_component: Response,
+    pub response_component: Response<'a>,
+    pub websocket_component: WebSocket<'a>,
     pub history_component: History,
     pub parameters_component: Parameters<'a>,
+    pub runner_component: Runner,
+    pub variables_component: VariableList<'a>,
+    pub help_component: Help,
     pub selected_component: SelectedComponent,
-    pub response: Vec<u8>,
+    pub response: Option<CurlResponse>,
+    pub request: Option<PendingRequest>,
+    pub grpc_call: Option<PendingGrpcCall>,
+    pub grpc_load: Option<PendingGrpcLoad>,
+    pub introspection: Option<PendingIntrospection>,
+    pub stream: Option<StreamHandle>,
+    pub websocket: Option<WebSocketHandle>,
+    pub runner: Option<RunnerHandle>,
+    pub variables: Variables,
+    /// Index in the open `.http` file of the request in the windows, None
+    /// when it is not from the file
+    pub open_request: Option<usize>,
 }
 
 impl<'a> App<'a> {
@@ -36,41 +90,112 @@ impl<'a> App<'a> {
             exit: false,
             url_component: Url::new(),
             response_component: Response::new(),
+            websocket_component: WebSocket::new(),
             history_component: History::new(),
             selected_component: SelectedComponent::Main,
             parameters_component: Parameters::new(),
-            response: Vec::new(),
+            runner_component: Runner::new(),
+            variables_component: VariableList::new(),
+            help_component: Help::new(),
+            response: None,
+            request: None,
+            grpc_call: None,
+            grpc_load: None,
+            introspection: None,
+            stream: None,
+            websocket: None,
+            runner: None,
+            variables: Variables::load().unwrap_or_default(),
+            open_request: None,
         }
     }
 
+    /// List the requests of the file in place of the history, its variables
+    /// are used for the requests sent from now on
+    pub fn open_http_file(&mut self, http_file: HttpFile) {
+        self.variables.use_file_variables(&http_file.variables());
+        self.history_component.set_http_file(http_file);
+    }
+
+    /// The event loop: key presses go to the selected component, the
+    /// background tasks are checked every tick and the screen is only drawn
+    /// again when one of them changed something. It ends on quit or on a
+    /// termination signal.
     pub fn run(&mut self, terminal: &mut tui::Tui) -> io::Result<()> {
+        let mut is_changed = true;
         while !self.exit {
-            terminal.draw(|frame| self.render_frame(frame))?;
-            if let Some(action) = self.handle_key_events() {
-                self.handle_action(action);
-            };
+            if tui::received_signal().is_some() {
+                return Ok(());
+            }
+            if self.handle_background_messages() {
+                is_changed = true;
+            }
+            if is_changed {
+                terminal.draw(|frame| self.render_frame(frame))?;
+            }
+            is_changed = crossterm::event::poll(TICK_RATE)? && self.handle_event(crossterm::event::read()?);
         }
         Ok(())
     }
 
+    /// True when the screen has to be drawn again
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if let Some(action) = self.handle_key_events(key.into()) {
+                    self.handle_action(action);
+                }
+                true
+            },
+            Event::Resize(_, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Take what the background tasks sent since the last tick, true when
+    /// anything arrived
+    fn handle_background_messages(&mut self) -> bool {
+        let request = self.handle_request_result();
+        let stream = self.handle_stream_messages();
+        let websocket = self.handle_websocket_events();
+        let runner = self.handle_runner_messages();
+        let grpc_load = self.handle_grpc_load_result();
+        let grpc_call = self.handle_grpc_call_result();
+        let introspection = self.handle_introspection_result();
+        request || stream || websocket || runner || grpc_load || grpc_call || introspection
+    }
+
     pub fn handle_lazcurlfile_load_request(&mut self) {
         if let Some(selected_file) = self.history_component.take_selected_file() {
+            self.open_request = self.history_component.take_selected_request();
+            let schema = Schema::load_cached(&selected_file.url);
+            self.parameters_component = Parameters::new_with_lazycurl_file(&selected_file);
             self.url_component = Url::new_withurl_and_httpmethod(selected_file.url, selected_file.http_method);
-            self.parameters_component = Parameters::new_with_headers(selected_file.headers);
+            if let Some(schema) = schema {
+                self.parameters_component.body_component.set_schema(schema);
+            }
+            self.websocket_component = WebSocket::new_with_frames(selected_file.websocket_frames);
+            crash_report::set_request(self.current_lazycurl_file());
         }
         self.reset_selected_component()
     }
 
-    pub fn handle_key_events(&mut self) -> Option<Action> {
+    pub fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.help_component.is_open() {
+            return self.help_component.handle_key_events(input)
+        }
         match self.selected_component {
             SelectedComponent::Main => {
-                let _ = self.handle_component_selection();
+                self.handle_component_selection(input);
                 None
             }
-            SelectedComponent::Url => self.url_component.handle_key_events(),
-            SelectedComponent::Response => self.response_component.handle_key_events(),
-            SelectedComponent::History => self.history_component.handle_key_events(),
-            SelectedComponent::Parameters => self.parameters_component.handle_key_events(),
+            SelectedComponent::Url => self.url_component.handle_key_events(input),
+            SelectedComponent::Response if self.is_websocket() => self.websocket_component.handle_key_events(input),
+            SelectedComponent::Response => self.response_component.handle_key_events(input),
+            SelectedComponent::History => self.history_component.handle_key_events(input),
+            SelectedComponent::Parameters => self.parameters_component.handle_key_events(input),
+            SelectedComponent::Runner => self.runner_component.handle_key_events(input),
+            SelectedComponent::Variables => self.variables_component.handle_key_events(input),
         }
     }
 
@@ -97,88 +222,586 @@ impl<'a> App<'a> {
                 self.parameters_component.handle_select();
                 self.selected_component = SelectedComponent::Parameters
             }
-            Action::Window3Request => {
-                self.response_component.handle_select();
-                self.selected_component = SelectedComponent::Response
-            }
+            Action::Window3Request => self.select_response_component(),
             Action::HistoryRequest => {
                 self.history_component.handle_select();
                 self.selected_component = SelectedComponent::History;
             },
+            Action::StopStream => self.handle_stop_stream(),
+            Action::WebSocketCommandRequest => {
+                if let (Some(websocket), Some(command)) = (&self.websocket, self.websocket_component.take_command()) {
+                    websocket.send(command);
+                }
+            },
+            Action::WebSocketSaveRequest => self.save_websocket_session(),
+            Action::GraphQLIntrospectionRequest => self.handle_graphql_introspection(),
+            Action::GrpcLoadRequest => self.handle_grpc_load(None),
+            Action::GrpcTemplateRequest => (),
+            Action::CollectionRunRequest => self.handle_collection_run(),
+            Action::StopCollectionRun => {
+                if let Some(runner) = &self.runner {
+                    runner.stop();
+                }
+            },
+            Action::VariablesSaveRequest => {
+                if let Some(variables) = self.variables_component.take_variables() {
+                    self.variables = variables;
+                    if let Err(e) = self.variables.save().and_then(|()| self.variables.save_active_environment()) {
+                        self.variables_component.notice = Some(format!("Could not save variables: {}", e));
+                    }
+                }
+            },
+            Action::HelpRequest(context) => self.help_component.context = Some(context),
         }
     }
 
-    fn handle_component_selection(&mut self) -> io::Result<()> {
-        match crossterm::event::read()?.into() {
-            Input { key: Key::Char('q'), .. } => self.handle_exit(),
-            Input { key: Key::Char('h'), .. } => {
+    fn handle_component_selection(&mut self, input: Input) {
+        match Config::get().keys.command(Context::Main, &input) {
+            Some(Command::Quit) => self.handle_exit(),
+            Some(Command::History) => {
                 self.history_component.handle_select();
                 self.selected_component = SelectedComponent::History;
             },
-            Input { key: Key::Char('1'), .. } => {
+            Some(Command::Url) => {
                 self.url_component.handle_select();
                 self.selected_component = SelectedComponent::Url;
             }
-            Input { key: Key::Char('2'), .. } => {
+            Some(Command::Parameters) => {
                 self.parameters_component.handle_select();
                 self.selected_component = SelectedComponent::Parameters
             },
-            Input { key: Key::Char('3'), .. } => {
-                self.response_component.handle_select();
-                self.selected_component = SelectedComponent::Response
-            }
+            Some(Command::Response) => self.select_response_component(),
+            Some(Command::Stop) => self.handle_stop_stream(),
+            Some(Command::Runner) => {
+                self.runner_component.handle_select();
+                self.selected_component = SelectedComponent::Runner;
+            },
+            Some(Command::SaveToFile) => self.handle_save_to_http_file(),
+            Some(Command::Variables) => {
+                self.variables_component.set_variables(self.variables.clone());
+                self.variables_component.handle_select();
+                self.selected_component = SelectedComponent::Variables;
+            },
+            Some(Command::Help) => self.help_component.context = Some(Context::Main),
             _ => ()
         }
+    }
 
-        Ok(())
+    /// Write the request in the windows back to the open `.http` file, a
+    /// request that is not from the file is added to it
+    fn handle_save_to_http_file(&mut self) {
+        let lazycurl_file = self.current_lazycurl_file();
+        let Some(http_file) = &mut self.history_component.http_file else {
+            self.response_component.notice = Some(String::from("No .http file open, start with lazy_curl FILE.http"));
+            return;
+        };
+        self.response_component.notice = Some(match http_file.save_request(self.open_request, &lazycurl_file) {
+            Ok(index) => {
+                self.open_request = Some(index);
+                format!("Saved to {}", http_file.path.display())
+            },
+            Err(e) => format!("Could not save to {}: {}", http_file.path.display(), e),
+        });
     }
 
     fn render_frame(&mut self, frame: &mut Frame) {
+        let layout = &Config::get().layout;
         let app_layout = Layout::new(
             Direction::Horizontal,
             [
-                Constraint::Percentage(20),
-                Constraint::Percentage(80),
+                Constraint::Percentage(layout.history_width),
+                Constraint::Percentage(100 - layout.history_width),
             ]
         ).split(frame.size());
 
         let main_layout = Layout::new(
             Direction::Vertical,
             [
-                Constraint::Percentage(10),
-                Constraint::Percentage(30),
-                Constraint::Percentage(60),
+                Constraint::Percentage(layout.url_height),
+                Constraint::Percentage(layout.parameters_height),
+                Constraint::Percentage(100 - layout.url_height - layout.parameters_height),
             ],
         ).split(app_layout[1]);
 
-        let _  = self.response_component.render_frame(frame, main_layout[2]);
+        if self.is_websocket() {
+            let _ = self.websocket_component.render_frame(frame, main_layout[2]);
+        } else {
+            let _  = self.response_component.render_frame(frame, main_layout[2]);
+        }
         let _  = self.parameters_component.render_frame(frame, main_layout[1]);
         let _  = self.url_component.render_frame(frame, main_layout[0]);
 
         let _ = self.history_component.render_frame(frame, app_layout[0]);
+
+        if self.selected_component == SelectedComponent::Runner {
+            let _ = self.runner_component.render_frame(frame, frame.size());
+        }
+        if self.selected_component == SelectedComponent::Variables {
+            let _ = self.variables_component.render_frame(frame, frame.size());
+        }
+        let _ = self.help_component.render_frame(frame, frame.size());
+    }
+
+    /// The response window shows the WebSocket session when WS is selected as method
+    fn is_websocket(&self) -> bool {
+        self.url_component.get_method() == HTTPMethod::WS
+    }
+
+    fn select_response_component(&mut self) {
+        if self.is_websocket() {
+            self.websocket_component.handle_select();
+        } else {
+            self.response_component.handle_select();
+        }
+        self.selected_component = SelectedComponent::Response
     }
 
     fn handle_curl_request(&mut self) {
         self.reset_selected_component();
+        crash_report::set_request(self.current_lazycurl_file());
+        if self.is_websocket() {
+            return self.handle_websocket_connect();
+        }
+        if let Err(e) = self.parameters_component.get_assertions() {
+            self.response_component.notice = Some(format!("Tests: {}", e));
+            return;
+        }
+        if let Err(e) = self.parameters_component.get_extractions() {
+            self.response_component.notice = Some(format!("Extract: {}", e));
+            return;
+        }
+        for (tab, script) in [("Pre-request", self.parameters_component.get_pre_request_script()), ("Post-response", self.parameters_component.get_post_response_script())] {
+            if let Err(e) = script::check(&script) {
+                self.response_component.notice = Some(format!("{}: {}", tab, e));
+                return;
+            }
+        }
+        self.response_component.clear_console();
+        // File variables may use variables set since the file was opened
+        if let Some(http_file) = &self.history_component.http_file {
+            self.variables.use_file_variables(&http_file.variables());
+        }
+        let request_name = format!("{} {}", self.url_component.get_method(), self.url_component.get_url());
+        self.response_component.filter_bar.set_request(request_name);
+        // A request still waiting is replaced, its response is ignored
+        self.request = None;
+        self.grpc_call = None;
+        if let Some(protocol) = GrpcProtocol::from_method(self.url_component.get_method()) {
+            return self.handle_grpc_call(protocol);
+        }
+
+        let mut lazycurl_file = self.current_lazycurl_file();
+        let Some(prepared_file) = self.prepared_request(&lazycurl_file) else {
+            return;
+        };
+        let request = match prepared_file.curl_request(&self.parameters_component.settings_component.global_settings) {
+            Ok(request) => request,
+            Err(e) => {
+                self.response_component.notice = Some(e);
+                return;
+            },
+        };
+
+        if lazycurl_file.graphql.is_some() && self.parameters_component.body_component.schema.is_none() {
+            if let Some(schema) = Schema::load_cached(&request.url) {
+                self.parameters_component.body_component.set_schema(schema);
+            }
+        }
 
-        let mut headers = curl::easy::List::new();
-        let component_headers = self.parameters_component.get_headers();
-        component_headers
-            .iter()
-            .for_each(|f| headers.append(f).unwrap());
-        self.response = Vec::new();
-        let url = self.url_component.get_url().to_owned();
-        let method = self.url_component.get_method();
+        self.handle_stop_stream();
+        if request.settings.stream() {
+            self.response_component.start_stream(request.settings.charset.clone());
+            self.response = None;
+            self.stream = Some(curl_stream(request));
+            self.notify_missing_variables(&lazycurl_file);
+            let _ = lazycurl_file.save();
+            return;
+        }
 
-        curl_call(url.as_str(), &mut self.response, headers, self.parameters_component.get_body(), method);
-        let response_string = String::from_utf8(self.response.clone()).unwrap();
-        self.response_component.update_response_value(response_string.clone());
-        save_request(url.as_str(), component_headers, method)
+        self.response_component.notice = Some(String::from("Sending request..."));
+        let charset = request.settings.charset.clone();
+        self.request = Some(PendingRequest { handle: curl_in_background(request), lazycurl_file, charset });
     }
 
-}
+    /// Show the response of the request sent in the background and save
+    /// the request to history, true when it arrived
+    fn handle_request_result(&mut self) -> bool {
+        let Some(request) = self.request.take() else {
+            return false;
+        };
+        let result = match request.handle.receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => {
+                self.request = Some(request);
+                return false;
+            },
+            // The thread panicked, its panic hook wrote a crash report
+            Err(TryRecvError::Disconnected) => {
+                self.response_component.update_error(&"The request thread panicked, a crash report was written to crashes/");
+                return true;
+            },
+        };
+
+        let mut lazycurl_file = request.lazycurl_file;
+        self.response = match result {
+            Ok(response) => {
+                self.response_component.update_response(&response, request.charset);
+                self.handle_response_rules(&response, None);
+                lazycurl_file.response = Some(SavedResponse::new(&response));
+                Some(response)
+            },
+            Err(error) => {
+                self.response_component.update_error(&error);
+                None
+            },
+        };
+        self.notify_missing_variables(&lazycurl_file);
+        let _ = lazycurl_file.save();
+        true
+    }
+
+    /// The request as it is sent, changed by the pre-request script and with
+    /// the variables filled in. None when the script failed.
+    fn prepared_request(&mut self, lazycurl_file: &LazyCurlFile) -> Option<LazyCurlFile> {
+        let mut prepared_file = lazycurl_file.clone();
+        let variables = self.variables.clone();
+        let script_output = run_pre_request(&lazycurl_file.pre_request_script, &mut prepared_file, &mut self.variables);
+        if script_output.error.is_some() {
+            self.response_component.update_error(&"The pre-request script failed, the request was not sent");
+        }
+        self.response_component.add_console(script_output.console);
+        self.save_changed_variables(&variables);
+        script_output.error.is_none().then(|| prepared_file.with_variables(&self.variables))
+    }
+
+    /// Run the Tests and Extract tabs and the post-response script against
+    /// the response, variables that were set are saved right away so the
+    /// next request can use them
+    fn handle_response_rules(&mut self, response: &CurlResponse, json: Option<Value>) {
+        let variables = self.variables.clone();
+        let (test_results, extraction_results, script_output) = self.parameters_component.evaluate_response(response, json, &mut self.variables);
+        self.response_component.update_rule_results(test_results, extraction_results);
+        self.response_component.add_console(script_output.console);
+        self.save_changed_variables(&variables);
+    }
+
+    fn save_changed_variables(&mut self, previous: &Variables) {
+        if self.variables != *previous {
+            if let Err(e) = self.variables.save() {
+                self.response_component.notice = Some(format!("Could not save variables: {}", e));
+            }
+        }
+    }
+
+    /// The request is sent anyway, the placeholders are left as they are
+    fn notify_missing_variables(&mut self, lazycurl_file: &LazyCurlFile) {
+        let missing = lazycurl_file.missing_variables(&self.variables);
+        if !missing.is_empty() {
+            self.response_component.notice = Some(format!("No value for {}", missing.iter().map(|name| format!("{{{{{}}}}}", name)).collect::<Vec<_>>().join(", ")));
+        }
+    }
+
+    /// Fetch the schema of the GraphQL endpoint in the URL window, it is
+    /// cached per URL so completion works without fetching it again
+    fn handle_graphql_introspection(&mut self) {
+        let url = self.variables.substitute(self.url_component.get_url());
+        let mut headers = self.substituted_headers();
+        add_json_content_type(&mut headers);
+        let request = CurlRequest {
+            url: url.clone(),
+            method: HTTPMethod::POST,
+            headers,
+            body: GraphQLBody::introspection().envelope().unwrap_or_default().into_bytes(),
+            settings: self.parameters_component.get_effective_settings(),
+        };
+
+        self.parameters_component.body_component.notice = Some(String::from("Fetching the schema..."));
+        self.introspection = Some(PendingIntrospection { receiver: introspect_in_background(request), url });
+    }
+
+    /// Set and cache the schema once it is fetched, true when it arrived
+    fn handle_introspection_result(&mut self) -> bool {
+        let Some(introspection) = self.introspection.take() else {
+            return false;
+        };
+        let schema = match introspection.receiver.try_recv() {
+            Ok(schema) => schema,
+            Err(TryRecvError::Empty) => {
+                self.introspection = Some(introspection);
+                return false;
+            },
+            Err(TryRecvError::Disconnected) => Err(String::from("The introspection thread panicked, a crash report was written to crashes/")),
+        };
+
+        let body_component = &mut self.parameters_component.body_component;
+        match schema {
+            Ok(schema) => {
+                let cache_result = schema.save_cache(&introspection.url);
+                body_component.set_schema(schema);
+                if let Err(e) = cache_result {
+                    body_component.notice = Some(format!("Schema loaded, not cached: {}", e));
+                }
+            },
+            Err(e) => body_component.notice = Some(e),
+        }
+        true
+    }
+
+    fn substituted_headers(&mut self) -> Vec<String> {
+        self.parameters_component.get_headers().iter()
+            .map(|header| self.variables.substitute(header))
+            .collect()
+    }
+
+    fn grpc_endpoint(&mut self, protocol: GrpcProtocol) -> GrpcEndpoint {
+        GrpcEndpoint {
+            url: self.variables.substitute(self.url_component.get_url()),
+            headers: self.substituted_headers(),
+            settings: self.parameters_component.get_effective_settings(),
+            protocol,
+        }
+    }
+
+    /// Load the gRPC services from the proto files of the gRPC tab or, when
+    /// there are none, from the server in the URL window through reflection.
+    /// The call is made once they are loaded when `call` is set.
+    fn handle_grpc_load(&mut self, call: Option<GrpcProtocol>) {
+        let protocol = GrpcProtocol::from_method(self.url_component.get_method()).unwrap_or(GrpcProtocol::Grpc);
+        let endpoint = self.grpc_endpoint(protocol);
+        let grpc_component = &mut self.parameters_component.grpc_component;
+        grpc_component.notice = Some(String::from("Loading services..."));
+        self.grpc_load = Some(PendingGrpcLoad { receiver: load_descriptors_in_background(grpc_component.request.clone(), endpoint), call });
+    }
+
+    /// Hand the loaded services to the gRPC tab and make the call waiting
+    /// for them, true when they arrived
+    fn handle_grpc_load_result(&mut self) -> bool {
+        let Some(load) = self.grpc_load.take() else {
+            return false;
+        };
+        let pool = match load.receiver.try_recv() {
+            Ok(pool) => pool,
+            Err(TryRecvError::Empty) => {
+                self.grpc_load = Some(load);
+                return false;
+            },
+            Err(TryRecvError::Disconnected) => Err(String::from("The gRPC thread panicked, a crash report was written to crashes/")),
+        };
+
+        let grpc_component = &mut self.parameters_component.grpc_component;
+        match pool {
+            Ok(pool) => {
+                grpc_component.set_pool(pool);
+                if let Some(protocol) = load.call {
+                    self.handle_grpc_call(protocol);
+                }
+            },
+            Err(e) => {
+                grpc_component.notice = Some(e);
+                if load.call.is_some() {
+                    self.response_component.update_error(&"Could not load the gRPC services, see the gRPC tab");
+                }
+            },
+        }
+        true
+    }
+
+    fn handle_grpc_call(&mut self, protocol: GrpcProtocol) {
+        if self.parameters_component.grpc_component.pool.is_none() {
+            self.response_component.notice = Some(String::from("Loading the gRPC services..."));
+            return self.handle_grpc_load(Some(protocol));
+        }
+        let Some(method) = self.parameters_component.grpc_component.selected_method() else {
+            self.response_component.update_error(&"Pick a gRPC method in the gRPC tab");
+            return;
+        };
+
+        let lazycurl_file = self.current_lazycurl_file();
+        let Some(prepared_file) = self.prepared_request(&lazycurl_file) else {
+            return;
+        };
+        let endpoint = GrpcEndpoint {
+            url: prepared_file.url,
+            headers: prepared_file.headers,
+            settings: self.parameters_component.get_effective_settings(),
+            protocol,
+        };
+        self.response_component.notice = Some(String::from("Sending request..."));
+        self.grpc_call = Some(PendingGrpcCall { receiver: grpc_in_background(endpoint, method, prepared_file.body), lazycurl_file });
+    }
+
+    /// Show the response of the gRPC call and save the request to history,
+    /// true when it arrived
+    fn handle_grpc_call_result(&mut self) -> bool {
+        let Some(call) = self.grpc_call.take() else {
+            return false;
+        };
+        let result = match call.receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => {
+                self.grpc_call = Some(call);
+                return false;
+            },
+            Err(TryRecvError::Disconnected) => Err(String::from("The gRPC thread panicked, a crash report was written to crashes/")),
+        };
+
+        let mut lazycurl_file = call.lazycurl_file;
+        match result {
+            Ok(response) => {
+                self.response_component.update_grpc_response(&response);
+                self.handle_response_rules(&response.response, Some(response.messages_value()));
+                lazycurl_file.response = Some(SavedResponse::new(&response.response));
+            },
+            Err(e) => self.response_component.update_error(&e),
+        }
+        self.response = None;
+        let _ = lazycurl_file.save();
+        true
+    }
+
+    /// The request as shown in the windows, as saved to history
+    fn current_lazycurl_file(&mut self) -> LazyCurlFile {
+        let mut lazycurl_file = LazyCurlFile::new(
+            self.url_component.get_url().to_owned(),
+            self.parameters_component.get_headers(),
+            self.url_component.get_method());
+        lazycurl_file.body = self.parameters_component.get_raw_body();
+        lazycurl_file.graphql = self.parameters_component.get_graphql();
+        lazycurl_file.settings = self.parameters_component.get_settings();
+        lazycurl_file.assertions = self.parameters_component.get_assertions().unwrap_or_default();
+        lazycurl_file.extractions = self.parameters_component.get_extractions().unwrap_or_default();
+        lazycurl_file.pre_request_script = self.parameters_component.get_pre_request_script();
+        lazycurl_file.post_response_script = self.parameters_component.get_post_response_script();
+        if GrpcProtocol::from_method(lazycurl_file.http_method).is_some() {
+            lazycurl_file.grpc = Some(self.parameters_component.get_grpc());
+        }
+        lazycurl_file
+    }
+
+    fn handle_websocket_connect(&mut self) {
+        if let Some(websocket) = &self.websocket {
+            websocket.send(WebSocketCommand::Close);
+        }
+        let url = self.variables.substitute(self.url_component.get_url());
+        let headers = self.substituted_headers();
+        self.websocket_component.start_session();
+        self.websocket = Some(websocket_connect(url, headers));
+    }
+
+    /// Hand frames of the WebSocket session to the WebSocket component, the
+    /// session is saved to history once the connection is gone. True when
+    /// anything arrived.
+    fn handle_websocket_events(&mut self) -> bool {
+        let Some(websocket) = &self.websocket else {
+            return false;
+        };
+
+        let mut received = false;
+        loop {
+            match websocket.events.try_recv() {
+                Ok(WebSocketEvent::Frame(frame)) => self.websocket_component.add_frame(frame),
+                Ok(WebSocketEvent::Closed) | Err(TryRecvError::Disconnected) => {
+                    self.websocket_component.end_session();
+                    self.websocket = None;
+                    self.save_websocket_session();
+                    return true;
+                },
+                Err(TryRecvError::Empty) => return received,
+            }
+            received = true;
+        }
+    }
+
+    fn save_websocket_session(&mut self) {
+        let mut lazycurl_file = self.current_lazycurl_file();
+        lazycurl_file.websocket_frames = self.websocket_component.frames.clone();
+        self.websocket_component.notice = Some(match lazycurl_file.save() {
+            Ok(()) => String::from("Session saved to history"),
+            Err(e) => format!("Could not save session: {}", e),
+        });
+    }
+
+    fn handle_collection_run(&mut self) {
+        if let Some((files, options)) = self.runner_component.take_run() {
+            let global_settings = self.parameters_component.settings_component.global_settings.clone();
+            self.runner = Some(run_in_background(files, options, global_settings, self.variables.clone()));
+        }
+    }
+
+    /// Hand finished requests of the collection run to the runner
+    /// component, true when anything arrived
+    fn handle_runner_messages(&mut self) -> bool {
+        let Some(runner) = &self.runner else {
+            return false;
+        };
+
+        let mut received = false;
+        loop {
+            match runner.receiver.try_recv() {
+                Ok(RunnerMessage::Finished(index, result)) => self.runner_component.add_result(index, result),
+                Ok(RunnerMessage::Done(changes)) => {
+                    self.runner_component.finish_run();
+                    self.runner = None;
+                    // Variables edited while the run was going keep their value
+                    // unless the run set them as well
+                    self.variables.apply_changes(changes);
+                    if let Err(e) = self.variables.save() {
+                        self.runner_component.notice = Some(format!("Could not save variables: {}", e));
+                    }
+                    return true;
+                },
+                Err(TryRecvError::Disconnected) => {
+                    self.runner_component.finish_run();
+                    self.runner = None;
+                    return true;
+                },
+                Err(TryRecvError::Empty) => return received,
+            }
+            received = true;
+        }
+    }
+
+    fn handle_stop_stream(&mut self) {
+        if let Some(stream) = &self.stream {
+            stream.stop();
+        }
+    }
+
+    /// Hand everything received from the streamed response to the response
+    /// component, true when anything arrived
+    fn handle_stream_messages(&mut self) -> bool {
+        let Some(stream) = &self.stream else {
+            return false;
+        };
+
+        let mut received = false;
+        loop {
+            match stream.receiver.try_recv() {
+                Ok(StreamMessage::Headers(headers)) => self.response_component.update_stream_headers(&headers),
+                Ok(StreamMessage::Chunk(chunk)) => self.response_component.update_stream_chunk(&chunk),
+                Ok(StreamMessage::Finished(result)) => {
+                    let result = *result;
+                    self.stream = None;
+                    self.response_component.finish_stream(&result);
+                    if let Ok(response) = &result {
+                        self.handle_response_rules(response, None);
+                    }
+                    return true;
+                },
+                Err(TryRecvError::Empty) => return received,
+                Err(TryRecvError::Disconnected) => {
+                    self.stream = None;
+                    return true;
+                },
+            }
+            received = true;
+        }
+    }
 
-fn save_request(url: &str, headers: Vec<String>, http_method: HTTPMethod) {
-    let _ = LazyCurlFile::new(String::from_str(url).unwrap(), headers, http_method).save();
 }
 
+impl<'a> Default for App<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,248 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use strum::{Display, EnumString};
+
+use crate::utils::{curl_service::CurlResponse, jsonpath};
+
+#[derive(Display, EnumString, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    String,
+    Number,
+    Boolean,
+    Array,
+    Object,
+    Null,
+}
+
+impl JsonType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => JsonType::String,
+            Value::Number(_) => JsonType::Number,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+            Value::Null => JsonType::Null,
+        }
+    }
+}
+
+/// A check of the response. Saved as JSON with the request and written one
+/// per line in the Tests tab, e.g. `status == 200` or `json $.id is number`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    StatusEquals { status: u32 },
+    /// Both ends included
+    StatusInRange { min: u32, max: u32 },
+    HeaderExists { name: String },
+    HeaderMatches { name: String, pattern: String },
+    JsonPathEquals { path: String, value: Value },
+    JsonPathMatches { path: String, pattern: String },
+    JsonPathType { path: String, json_type: JsonType },
+    ResponseTimeUnder { ms: u64 },
+    BodyContains { text: String },
+    /// A check made with `test(name, passed)` in a post-response script,
+    /// never parsed from the Tests tab
+    Script { name: String },
+}
+
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// What the response had instead, for failed assertions
+    pub actual: String,
+}
+
+impl fmt::Display for AssertionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed {
+            write!(f, "✓ {}", self.assertion)
+        } else {
+            write!(f, "✗ {} (got {})", self.assertion, self.actual)
+        }
+    }
+}
+
+impl Assertion {
+    pub fn is_status(&self) -> bool {
+        matches!(self, Assertion::StatusEquals { .. } | Assertion::StatusInRange { .. })
+    }
+
+    /// `json` is the body parsed as JSON, or the messages of a gRPC call
+    pub fn evaluate(&self, response: &CurlResponse, json: Option<&Value>) -> AssertionResult {
+        let (passed, actual) = match self {
+            Assertion::StatusEquals { status } => (response.status == *status, response.status.to_string()),
+            Assertion::StatusInRange { min, max } => ((*min..=*max).contains(&response.status), response.status.to_string()),
+            Assertion::HeaderExists { name } => (response.header(name).is_some(), String::from("no such header")),
+            Assertion::HeaderMatches { name, pattern } => {
+                let values = response.header_values(name);
+                match Regex::new(pattern) {
+                    Ok(_) if values.is_empty() => (false, String::from("no such header")),
+                    Ok(regex) => (values.iter().any(|value| regex.is_match(value)), values.join(", ")),
+                    Err(_) => (false, String::from("an invalid pattern")),
+                }
+            },
+            Assertion::JsonPathEquals { path, value } => match select(path, json) {
+                Ok(selected) => (json_equals(&selected, value), selected.to_string()),
+                Err(actual) => (false, actual),
+            },
+            Assertion::JsonPathMatches { path, pattern } => match (select(path, json), Regex::new(pattern)) {
+                (Ok(selected), Ok(regex)) => {
+                    let text = jsonpath::value_text(&selected);
+                    (regex.is_match(&text), text)
+                },
+                (Err(actual), _) => (false, actual),
+                (_, Err(_)) => (false, String::from("an invalid pattern")),
+            },
+            Assertion::JsonPathType { path, json_type } => match select(path, json) {
+                Ok(selected) => (JsonType::of(&selected) == *json_type, JsonType::of(&selected).to_string()),
+                Err(actual) => (false, actual),
+            },
+            Assertion::ResponseTimeUnder { ms } => {
+                let time = response.total_time.as_millis();
+                (time < u128::from(*ms), format!("{} ms", time))
+            },
+            Assertion::BodyContains { text } => (
+                String::from_utf8_lossy(&response.body).contains(text.as_str()),
+                String::from("a body without it")),
+            Assertion::Script { .. } => (false, String::from("a check only a script can make")),
+        };
+        AssertionResult { assertion: self.clone(), passed, actual }
+    }
+}
+
+pub fn evaluate(assertions: &[Assertion], response: &CurlResponse, json: Option<&Value>) -> Vec<AssertionResult> {
+    assertions.iter()
+        .map(|assertion| assertion.evaluate(response, json))
+        .collect()
+}
+
+/// The body parsed as JSON, for JSONPath assertions
+pub fn response_json(response: &CurlResponse) -> Option<Value> {
+    serde_json::from_slice(&response.body).ok()
+}
+
+/// Parse the lines of the Tests tab, skipping blank lines and `#` comments.
+/// Errors carry the line number.
+pub fn parse_assertions(text: &str) -> Result<Vec<Assertion>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(|(index, line)| line.parse().map_err(|e| format!("Line {}: {}", index + 1, e)))
+        .collect()
+}
+
+fn select(path: &str, json: Option<&Value>) -> Result<Value, String> {
+    let json = json.ok_or_else(|| String::from("a body that is not JSON"))?;
+    jsonpath::query(path, json)?.ok_or_else(|| String::from("nothing at the path"))
+}
+
+/// Numbers are equal by value, `1` equals `1.0`
+fn json_equals(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Number(actual), Value::Number(expected)) => jsonpath::compare_numbers(actual, expected) == Some(Ordering::Equal),
+        _ => actual == expected,
+    }
+}
+
+/// The first word and the trimmed rest of `text`
+pub fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+fn parse_number<T: FromStr>(text: &str) -> Result<T, String> {
+    text.trim().parse().map_err(|_| format!("{} is not a number", text.trim()))
+}
+
+pub fn parse_pattern(pattern: &str) -> Result<String, String> {
+    Regex::new(pattern).map_err(|e| {
+        // Regex errors point at the position over several lines, keep the reason
+        let e = e.to_string();
+        let reason = e.lines().last().unwrap_or_default().trim_start_matches("error: ").to_string();
+        format!("Invalid pattern {}: {}", pattern, reason)
+    })?;
+    Ok(pattern.to_string())
+}
+
+impl FromStr for Assertion {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (subject, rest) = split_word(line);
+        match subject {
+            "status" => match split_word(rest) {
+                ("==", status) => Ok(Assertion::StatusEquals { status: parse_number(status)? }),
+                ("in", range) => {
+                    let (min, max) = range.split_once("..").ok_or("Expected a range like 200..299")?;
+                    Ok(Assertion::StatusInRange { min: parse_number(min)?, max: parse_number(max)? })
+                },
+                _ => Err(String::from("Expected status == <code> or status in <min>..<max>")),
+            },
+            "header" => {
+                let (name, rest) = split_word(rest);
+                match split_word(rest) {
+                    ("exists", "") if !name.is_empty() => Ok(Assertion::HeaderExists { name: name.to_string() }),
+                    ("matches", pattern) if !pattern.is_empty() => Ok(Assertion::HeaderMatches {
+                        name: name.to_string(),
+                        pattern: parse_pattern(pattern)?,
+                    }),
+                    _ => Err(String::from("Expected header <name> exists or header <name> matches <regex>")),
+                }
+            },
+            "json" => {
+                let (path, rest) = jsonpath::split_path(rest);
+                jsonpath::select(path, &Value::Null)?;
+                let path = path.to_string();
+                match split_word(rest) {
+                    ("==", value) if !value.is_empty() => Ok(Assertion::JsonPathEquals {
+                        path,
+                        // Bare words are strings
+                        value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+                    }),
+                    ("matches", pattern) if !pattern.is_empty() => Ok(Assertion::JsonPathMatches { path, pattern: parse_pattern(pattern)? }),
+                    ("is", json_type) => Ok(Assertion::JsonPathType {
+                        path,
+                        json_type: json_type.parse().map_err(|_| format!("Unknown type {}, expected string, number, boolean, array, object or null", json_type))?,
+                    }),
+                    _ => Err(String::from("Expected json <path> == <value>, matches <regex> or is <type>")),
+                }
+            },
+            "time" => match split_word(rest) {
+                ("<", ms) => Ok(Assertion::ResponseTimeUnder { ms: parse_number(ms.trim_end_matches("ms"))? }),
+                _ => Err(String::from("Expected time < <ms>")),
+            },
+            "body" => match split_word(rest) {
+                ("contains", text) if !text.is_empty() => Ok(Assertion::BodyContains { text: text.to_string() }),
+                _ => Err(String::from("Expected body contains <text>")),
+            },
+            _ => Err(format!("Unknown check {}, expected status, header, json, time or body", subject)),
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::StatusEquals { status } => write!(f, "status == {}", status),
+            Assertion::StatusInRange { min, max } => write!(f, "status in {}..{}", min, max),
+            Assertion::HeaderExists { name } => write!(f, "header {} exists", name),
+            Assertion::HeaderMatches { name, pattern } => write!(f, "header {} matches {}", name, pattern),
+            Assertion::JsonPathEquals { path, value } => write!(f, "json {} == {}", path, value),
+            Assertion::JsonPathMatches { path, pattern } => write!(f, "json {} matches {}", path, pattern),
+            Assertion::JsonPathType { path, json_type } => write!(f, "json {} is {}", path, json_type),
+            Assertion::ResponseTimeUnder { ms } => write!(f, "time < {}", ms),
+            Assertion::BodyContains { text } => write!(f, "body contains {}", text),
+            Assertion::Script { name } => write!(f, "script {}", name),
+        }
+    }
+}
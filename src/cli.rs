@@ -0,0 +1,546 @@
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}, process::ExitCode, sync::{atomic::AtomicBool, Mutex, PoisonError}, time::Instant};
+
+use clap::{builder::RangedU64ValueParser, Parser, Subcommand, ValueEnum};
+use serde_json::json;
+
+use crate::{
+    http_file::HttpFile,
+    import::{self, har::HarImportOptions},
+    lazycurl_file::LazyCurlFile,
+    request_settings::RequestSettings,
+    variables::Variables,
+    utils::{
+        binary,
+        charset::{decode, detect_charset},
+        curl_service::CurlResponse,
+        directory::{workspace_location, Directory},
+        grpc_service::GrpcResponse,
+        runner::{assertions_json, extractions_json, json_report, junit_report, request_files, run_files, Outcome, RequestResult, RunOptions, Summary},
+    },
+};
+
+/// Exit code when a request got a status that was not expected
+const EXIT_UNEXPECTED_STATUS: u8 = 1;
+/// Exit code when a request could not be loaded or sent
+pub const EXIT_FAILURE: u8 = 2;
+
+/// Terminal UI for curl. Without a command the TUI is started.
+#[derive(Parser)]
+#[command(name = "lazy_curl", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// A .http or .rest file to open in the TUI, it is created by the first save
+    pub file: Option<PathBuf>,
+    /// Directory of the collection, history and variables [env: LAZYCURL_DATA_DIR]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Directory of settings.json [env: LAZYCURL_CONFIG_DIR]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a saved request, or every request below a collection directory
+    Run {
+        /// Request file or directory
+        path: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Expected status codes, comma separated. Any 2xx or 3xx by default
+        #[arg(short = 's', long, value_delimiter = ',')]
+        expect_status: Vec<u32>,
+        /// Number of requests run at the same time
+        #[arg(short, long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        parallel: usize,
+        /// Skip the remaining requests after the first failure
+        #[arg(long)]
+        stop_on_failure: bool,
+        /// Write a JUnit XML report to the file
+        #[arg(long, value_name = "FILE")]
+        junit: Option<PathBuf>,
+        /// Write a JSON report to the file
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// Environment to use instead of the active one
+        #[arg(short, long, value_name = "NAME")]
+        env: Option<String>,
+    },
+    /// List the requests of the collection directory
+    List {
+        /// Directory to list instead of the collection directory
+        path: Option<PathBuf>,
+    },
+    /// List the most recent requests of the history
+    History {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Convert requests of another tool into the collection directory
+    Import {
+        #[arg(value_enum)]
+        format: ImportFormat,
+        /// File exported by the tool
+        path: PathBuf,
+        /// List the entries of a HAR file with their numbers, without importing
+        #[arg(long)]
+        list: bool,
+        /// HAR entries to import, e.g. 1,3-5. All entries by default
+        #[arg(long, value_name = "NUMBERS")]
+        entries: Option<String>,
+        /// Only import HAR entries whose URL contains the text
+        #[arg(long = "match", value_name = "TEXT")]
+        url_filter: Option<String>,
+        /// Import HAR entries into history with their responses, instead of the collection
+        #[arg(long)]
+        history: bool,
+    },
+    /// Make the current git repository, or else the current directory, a
+    /// workspace with its own collection, history, variables and environments
+    /// in .lazycurl/
+    Init,
+    /// Print the data and config directories in use
+    Paths,
+    /// Export requests and their responses for other tools
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+        /// History entries, request files or directories. The most recent history entries by default
+        paths: Vec<PathBuf>,
+        /// Number of history entries exported by default
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// Write to the file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    /// Postman v2.0 or v2.1 collection, or Postman environment
+    Postman,
+    /// OpenAPI 3 or Swagger 2 document, YAML or JSON
+    Openapi,
+    /// HAR 1.2 file, e.g. saved from the network tab of browser devtools
+    Har,
+    /// Insomnia v4 export, YAML or JSON
+    Insomnia,
+    /// Bruno collection directory, or its bruno.json
+    Bruno,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// HAR 1.2 with the responses kept in history
+    Har,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Status line, headers and body
+    Text,
+    /// One JSON object per request
+    Json,
+    /// The response body only
+    Body,
+}
+
+pub fn run(command: Command) -> ExitCode {
+    match command {
+        Command::Run { path, format, expect_status, parallel, stop_on_failure, junit, report, env } => {
+            let options = RunOptions { parallel, stop_on_failure, expect_status };
+            run_requests(&data_path(path), format, &options, junit.as_deref(), report.as_deref(), env)
+        },
+        Command::List { path } => list(&path.map_or_else(|| PathBuf::from(Directory::Collection.path()), data_path)),
+        Command::History { limit } => history(limit),
+        Command::Import { format, path, list, entries, url_filter, history } => {
+            if format != ImportFormat::Har && (list || entries.is_some() || url_filter.is_some() || history) {
+                eprintln!("--list, --entries, --match and --history only apply to HAR files");
+                return ExitCode::from(EXIT_FAILURE);
+            }
+            if list {
+                return list_har_entries(&path);
+            }
+            let entries = match entries.as_deref().map(import::har::parse_entries).transpose() {
+                Ok(entries) => entries.unwrap_or_default(),
+                Err(e) => {
+                    eprintln!("--entries: {}", e);
+                    return ExitCode::from(EXIT_FAILURE);
+                },
+            };
+            import_file(format, &path, &HarImportOptions { entries, url_filter, into_history: history })
+        },
+        Command::Init => init_workspace(),
+        Command::Paths => {
+            println!("data   {}", Directory::Root.path());
+            println!("config {}", Directory::Config.path());
+            ExitCode::SUCCESS
+        },
+        Command::Export { format: ExportFormat::Har, paths, limit, output } => {
+            let paths = paths.into_iter().map(data_path).collect::<Vec<_>>();
+            export_har(&paths, limit, output.as_deref())
+        },
+    }
+}
+
+/// Relative paths that are not found in the current directory are looked up
+/// in the data directory, so `run collection/users` works from anywhere
+fn data_path(path: PathBuf) -> PathBuf {
+    if path.is_absolute() || path.exists() || HttpFile::split_request_path(&path).is_some_and(|(file, _)| file.exists()) {
+        return path;
+    }
+    let data_path = Path::new(&Directory::Root.path()).join(&path);
+    let is_data_path = data_path.exists() || HttpFile::split_request_path(&data_path).is_some_and(|(file, _)| file.exists());
+    if is_data_path { data_path } else { path }
+}
+
+fn init_workspace() -> ExitCode {
+    let workspace = match workspace_location() {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    if workspace.is_dir() {
+        println!("{} is a workspace already", workspace.display());
+        return ExitCode::SUCCESS;
+    }
+    match fs::create_dir_all(&workspace) {
+        Ok(()) => {
+            println!("Created workspace {}", workspace.display());
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}: {}", workspace.display(), e);
+            ExitCode::from(EXIT_FAILURE)
+        },
+    }
+}
+
+fn run_requests(path: &Path, format: OutputFormat, options: &RunOptions, junit: Option<&Path>, report: Option<&Path>, env: Option<String>) -> ExitCode {
+    let files = match request_files(path) {
+        Ok(files) if !files.is_empty() => files,
+        Ok(_) => {
+            eprintln!("No requests found in {}", path.display());
+            return ExitCode::from(EXIT_FAILURE);
+        },
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    let global_settings = RequestSettings::load_global().unwrap_or_else(|e| {
+        eprintln!("settings.json: {}", e);
+        RequestSettings::default()
+    });
+
+    let mut variables = Variables::load().unwrap_or_else(|e| {
+        eprintln!("variables.json: {}", e);
+        Variables::default()
+    });
+    if env.is_some() {
+        if let Err(e) = variables.use_environment(env) {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    }
+
+    let started = Instant::now();
+    let loaded_variables = variables.clone();
+    let variables = Mutex::new(variables);
+    let results = Mutex::new(Vec::new());
+    run_files(&files, options, &global_settings, &variables, &AtomicBool::new(false), |_, result| {
+        print_result(&result, format, files.len() > 1);
+        results.lock().unwrap_or_else(PoisonError::into_inner).push(result);
+    });
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    let variables = variables.into_inner().unwrap_or_else(PoisonError::into_inner);
+    if variables != loaded_variables {
+        if let Err(e) = variables.save() {
+            eprintln!("variables.json: {}", e);
+        }
+    }
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    let results = results.iter().collect::<Vec<_>>();
+
+    let summary = Summary::new(results.iter().copied());
+    if files.len() > 1 {
+        eprintln!("{}, finished in {} ms", summary, started.elapsed().as_millis());
+    }
+    let root = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    for (report_path, contents) in [(junit, junit_report(&results, root)), (report, json_report(&results, root))] {
+        if let Some(report_path) = report_path {
+            if let Err(e) = fs::write(report_path, contents) {
+                eprintln!("{}: {}", report_path.display(), e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    }
+
+    if summary.errors > 0 {
+        ExitCode::from(EXIT_FAILURE)
+    } else if summary.failed > 0 {
+        ExitCode::from(EXIT_UNEXPECTED_STATUS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Print the whole output of a request at once, requests run in parallel finish in any order
+fn print_result(result: &RequestResult, format: OutputFormat, is_collection: bool) {
+    let path = &result.path;
+    let mut output = Vec::new();
+    match (result.outcome, result.http_response()) {
+        (Outcome::Skipped, _) => {
+            eprintln!("{}: skipped, {}", path.display(), result.message.as_deref().unwrap_or_default());
+            return;
+        },
+        (_, Some(response)) => {
+            if is_collection && format == OutputFormat::Text {
+                let _ = writeln!(output, "### {} {} {} ms", path.display(), result.outcome.label(), result.time.as_millis());
+            }
+            write_response(&mut output, format, result, response);
+            if is_collection && format == OutputFormat::Text {
+                let _ = writeln!(output);
+            }
+        },
+        (_, None) => {
+            let error = result.message.as_deref().unwrap_or_default();
+            match format {
+                OutputFormat::Json => {
+                    let _ = writeln!(output, "{}", json!({ "file": path.display().to_string(), "error": error }));
+                },
+                _ => eprintln!("{}: {}", path.display(), error),
+            }
+        },
+    }
+    let _ = io::stdout().lock().write_all(&output);
+}
+
+fn write_response(output: &mut Vec<u8>, format: OutputFormat, result: &RequestResult, response: &CurlResponse) {
+    let grpc_response = result.grpc_response.as_ref();
+    let body = match grpc_response {
+        Some(grpc_response) => Some(grpc_messages(grpc_response)),
+        None => body_text(response),
+    };
+    // gRPC-Web sends its trailers in the body
+    let trailers = grpc_response.map_or(&response.trailers, |grpc_response| &grpc_response.trailers);
+
+    let _ = match format {
+        OutputFormat::Text => write_text(output, response, body.as_deref(), grpc_response, trailers)
+            .and_then(|()| write_rule_results(output, result)),
+        OutputFormat::Json => {
+            let mut json_output = json!({
+                "file": result.path.display().to_string(),
+                "url": response.url,
+                "status": response.status,
+                "http_version": response.http_version,
+                "headers": response.headers,
+                "trailers": trailers,
+                "body": body,
+                "body_size": response.body.len(),
+                "time_ms": response.total_time.as_millis() as u64,
+                "attempts": response.attempts,
+            });
+            if !result.assertion_results.is_empty() {
+                json_output["assertions"] = json!(assertions_json(&result.assertion_results));
+            }
+            if !result.extraction_results.is_empty() {
+                json_output["extractions"] = json!(extractions_json(&result.extraction_results));
+            }
+            if !result.console.is_empty() {
+                json_output["console"] = json!(result.console.iter().map(ToString::to_string).collect::<Vec<_>>());
+            }
+            if let Some(grpc_response) = grpc_response {
+                json_output["grpc_status"] = json!(grpc_response.status);
+                json_output["grpc_message"] = json!(grpc_response.status_message);
+            }
+            writeln!(output, "{}", json_output)
+        },
+        OutputFormat::Body => match &body {
+            Some(body) if grpc_response.is_some() => writeln!(output, "{}", body),
+            _ => output.write_all(&response.body),
+        },
+    };
+}
+
+fn write_text(output: &mut Vec<u8>, response: &CurlResponse, body: Option<&str>, grpc_response: Option<&GrpcResponse>, trailers: &[String]) -> io::Result<()> {
+    for header in &response.headers {
+        writeln!(output, "{}", header)?;
+    }
+    writeln!(output)?;
+    match body {
+        Some(body) => writeln!(output, "{}", body)?,
+        None => writeln!(output, "<binary body, {} bytes>", response.body.len())?,
+    }
+    if let Some(grpc_response) = grpc_response {
+        writeln!(output, "\ngrpc-status: {}", grpc_response.status_description())?;
+        if !grpc_response.status_message.is_empty() {
+            writeln!(output, "grpc-message: {}", grpc_response.status_message)?;
+        }
+    }
+    for trailer in trailers {
+        writeln!(output, "{}", trailer)?;
+    }
+    Ok(())
+}
+
+/// Tests, extracted variables and script output below the response
+fn write_rule_results(output: &mut Vec<u8>, result: &RequestResult) -> io::Result<()> {
+    write_section(output, "Tests", &result.assertion_results)?;
+    write_section(output, "Variables", &result.extraction_results)?;
+    write_section(output, "Console", &result.console)
+}
+
+fn write_section(output: &mut Vec<u8>, title: &str, lines: &[impl std::fmt::Display]) -> io::Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    writeln!(output, "\n{}:", title)?;
+    for line in lines {
+        writeln!(output, "  {}", line)?;
+    }
+    Ok(())
+}
+
+/// The decoded body, None for binary bodies
+fn body_text(response: &CurlResponse) -> Option<String> {
+    if binary::is_binary(response.content_type(), &response.body) {
+        return None;
+    }
+    let (encoding, _) = detect_charset(None, response.content_type(), &response.body);
+    Some(decode(&response.body, encoding))
+}
+
+fn grpc_messages(grpc_response: &GrpcResponse) -> String {
+    serde_json::to_string_pretty(&grpc_response.messages_value()).unwrap_or_default()
+}
+
+fn list(path: &Path) -> ExitCode {
+    let files = match request_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+
+    for file in files {
+        let name = file.strip_prefix(path).unwrap_or(&file).display().to_string();
+        match LazyCurlFile::from_path(&file) {
+            Ok(lazycurl_file) => println!("{:<40} {:<8} {}", name, lazycurl_file.http_method, lazycurl_file.url),
+            Err(e) => println!("{:<40} invalid: {}", name, e),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn history(limit: usize) -> ExitCode {
+    let mut files = match request_files(Path::new(&Directory::History.path())) {
+        Ok(files) => files,
+        Err(_) => {
+            eprintln!("No history found");
+            return ExitCode::SUCCESS;
+        },
+    };
+    // File names carry the time they were saved at
+    files.reverse();
+
+    for file in files.into_iter().take(limit) {
+        match LazyCurlFile::from_path(&file) {
+            Ok(lazycurl_file) => println!("{:<60} {:<8} {}", file.display(), lazycurl_file.http_method, lazycurl_file.url),
+            Err(e) => println!("{:<60} invalid: {}", file.display(), e),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn import_file(format: ImportFormat, path: &Path, har_options: &HarImportOptions) -> ExitCode {
+    let result = match format {
+        ImportFormat::Postman => import::postman::import(path),
+        ImportFormat::Openapi => import::openapi::import(path),
+        ImportFormat::Har => import::har::import(path, har_options),
+        ImportFormat::Insomnia => import::insomnia::import(path),
+        ImportFormat::Bruno => import::bruno::import(path),
+    };
+    match result {
+        Ok(report) => {
+            print!("{}", report);
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            ExitCode::from(EXIT_FAILURE)
+        },
+    }
+}
+
+fn list_har_entries(path: &Path) -> ExitCode {
+    match import::har::list(path) {
+        Ok(entries) => {
+            for entry in entries {
+                println!("{}", entry);
+            }
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            ExitCode::from(EXIT_FAILURE)
+        },
+    }
+}
+
+fn export_har(paths: &[PathBuf], limit: usize, output: Option<&Path>) -> ExitCode {
+    let mut files = Vec::new();
+    if paths.is_empty() {
+        let history = request_files(Path::new(&Directory::History.path())).unwrap_or_default();
+        // File names carry the time they were saved at
+        files.extend(history.iter().skip(history.len().saturating_sub(limit)).cloned());
+    }
+    for path in paths {
+        match request_files(path) {
+            Ok(path_files) => files.extend(path_files),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        }
+    }
+    if files.is_empty() {
+        eprintln!("No requests to export");
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    let mut lazycurl_files = Vec::new();
+    for file in files {
+        match LazyCurlFile::from_path(&file) {
+            Ok(lazycurl_file) => lazycurl_files.push((file, lazycurl_file)),
+            Err(e) => eprintln!("{}: {}, skipped", file.display(), e),
+        }
+    }
+    let variables = Variables::load().unwrap_or_else(|e| {
+        eprintln!("variables.json: {}", e);
+        Variables::default()
+    });
+
+    let (har, count) = import::har::export(&lazycurl_files, &variables);
+    match output {
+        Some(output) => match fs::write(output, har) {
+            Ok(()) => {
+                eprintln!("Exported {} request(s) to {}", count, output.display());
+                ExitCode::SUCCESS
+            },
+            Err(e) => {
+                eprintln!("{}: {}", output.display(), e);
+                ExitCode::from(EXIT_FAILURE)
+            },
+        },
+        None => {
+            println!("{}", har);
+            ExitCode::SUCCESS
+        },
+    }
+}
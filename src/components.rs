@@ -1,5 +1,6 @@
 use std::io;
 use ratatui::{layout::Rect, Frame};
+use tui_textarea::Input;
 
 use crate::action::Action;
 
@@ -10,6 +11,14 @@ pub mod header;
 pub mod history;
 pub mod parameters;
 pub mod body;
+pub mod settings;
+pub mod websocket;
+pub mod grpc;
+pub mod runner;
+pub mod rules;
+pub mod variables;
+pub mod filter_bar;
+pub mod help;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 /// Implementors of this trait can be registered with the main application loop and will be able to receive events,
@@ -27,12 +36,12 @@ pub trait Component {
   ///
   /// # Arguments
   ///
-  /// * `key` - A key event to be processed.
+  /// * `input` - A key press, read by the event loop of `App`.
   ///
   /// # Returns
   ///
   /// * `Result<Option<Action>>` - An action to be processed or none.
-  fn handle_key_events(&mut self) -> Option<Action>;
+  fn handle_key_events(&mut self, input: Input) -> Option<Action>;
 
   /// Render the component on the screen. (REQUIRED)
   ///
@@ -1,32 +1,91 @@
-use ratatui::{layout::{Constraint, Direction, Layout, Rect}, widgets::{Block, Borders}};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::{Line, Span}, widgets::{Block, Borders, Paragraph}};
+use strum::Display;
 use tui_textarea::{Input, Key, TextArea};
 
-use crate::action::Action;
+use crate::{action::Action, config::Config, keymap::{Command, Context}, utils::graphql::{analyze, complete, operation_names, GraphQLBody, Schema}};
 
 use super::Component;
 
+#[derive(Display, Copy, Clone, PartialEq)]
+pub enum BodyMode {
+    Raw,
+    GraphQL,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GraphQLEditor {
+    Query,
+    Variables,
+}
+
 pub struct Body<'a> {
     pub selected: bool,
     pub body_textarea: TextArea<'a>,
     pub is_edit_mode: bool,
+    pub mode: BodyMode,
+    pub query_textarea: TextArea<'a>,
+    pub variables_textarea: TextArea<'a>,
+    pub selected_editor: GraphQLEditor,
+    pub operation_name: Option<String>,
+    pub schema: Option<Schema>,
+    pub notice: Option<String>,
 }
 
 impl<'a> Body<'a> {
     pub fn new() -> Self {
-        let text_area = TextArea::default();
-        Block::default()
-            .borders(Borders::ALL)
-            .title("");
+        Self::new_with_body(String::new(), None)
+    }
+
+    pub fn new_with_body(body: String, graphql: Option<GraphQLBody>) -> Self {
+        let mode = if graphql.is_some() { BodyMode::GraphQL } else { BodyMode::Raw };
+        let graphql = graphql.unwrap_or_default();
 
         Self {
             selected: false,
-            body_textarea: text_area,
+            body_textarea: textarea_with_text(&body),
             is_edit_mode: false,
+            mode,
+            query_textarea: textarea_with_text(&graphql.query),
+            variables_textarea: textarea_with_text(&graphql.variables),
+            selected_editor: GraphQLEditor::Query,
+            operation_name: graphql.operation_name,
+            schema: None,
+            notice: None,
+        }
+    }
+
+    /// The body sent with the request, the JSON envelope in GraphQL mode
+    pub fn get_body_text(&self) -> Result<String, String> {
+        match self.get_graphql() {
+            Some(graphql) => graphql.envelope(),
+            None => Ok(self.body_textarea.lines().join("\n")),
+        }
+    }
+
+    pub fn get_raw_body(&self) -> String {
+        self.body_textarea.lines().join("\n")
+    }
+
+    /// Replace the raw body, e.g. with a request template
+    pub fn set_raw_body(&mut self, body: &str) {
+        self.body_textarea = textarea_with_text(body);
+        self.mode = BodyMode::Raw;
+    }
+
+    pub fn get_graphql(&self) -> Option<GraphQLBody> {
+        if self.mode != BodyMode::GraphQL {
+            return None;
         }
+        Some(GraphQLBody {
+            query: self.query_textarea.lines().join("\n"),
+            variables: self.variables_textarea.lines().join("\n"),
+            operation_name: self.operation_name.clone(),
+        })
     }
 
-    pub fn get_body_text(&mut self) -> &str {
-        self.body_textarea.lines()[0].as_str()
+    pub fn set_schema(&mut self, schema: Schema) {
+        self.notice = Some(format!("Schema loaded, {} types", schema.types.len()));
+        self.schema = Some(schema);
     }
 
     pub fn handle_edit_mode(&mut self) -> Option<Action> {
@@ -39,57 +98,181 @@ impl<'a> Body<'a> {
         None
     }
 
-    pub fn handle_edit_mode_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_exit_edit_mode(),
-                    input => {
-                        self.body_textarea.input(input);
-                        None
-                    }
-                }
+    fn handle_toggle_mode(&mut self) -> Option<Action> {
+        self.mode = match self.mode {
+            BodyMode::Raw => BodyMode::GraphQL,
+            BodyMode::GraphQL => BodyMode::Raw,
+        };
+        None
+    }
+
+    fn handle_switch_editor(&mut self) -> Option<Action> {
+        self.selected_editor = match self.selected_editor {
+            GraphQLEditor::Query => GraphQLEditor::Variables,
+            GraphQLEditor::Variables => GraphQLEditor::Query,
+        };
+        None
+    }
+
+    /// Cycle through the operations of the query, then back to none
+    fn handle_next_operation(&mut self) -> Option<Action> {
+        let names = operation_names(&self.query_textarea.lines().join("\n"));
+        let next_index = match &self.operation_name {
+            Some(name) => names.iter().position(|n| n == name).map_or(0, |index| index + 1),
+            None => 0,
+        };
+        self.operation_name = names.get(next_index).cloned();
+        None
+    }
+
+    fn selected_textarea(&mut self) -> &mut TextArea<'a> {
+        match (self.mode, self.selected_editor) {
+            (BodyMode::Raw, _) => &mut self.body_textarea,
+            (BodyMode::GraphQL, GraphQLEditor::Query) => &mut self.query_textarea,
+            (BodyMode::GraphQL, GraphQLEditor::Variables) => &mut self.variables_textarea,
+        }
+    }
+
+    /// Query text up to the cursor, which is what completion looks at
+    fn query_before_cursor(&self) -> String {
+        let (row, col) = self.query_textarea.cursor();
+        let lines = self.query_textarea.lines();
+        let mut text = lines[..row].iter().map(|line| format!("{}\n", line)).collect::<String>();
+        text.extend(lines[row].chars().take(col));
+        text
+    }
+
+    fn completions(&self) -> (String, Vec<String>) {
+        match &self.schema {
+            Some(schema) => complete(&self.query_before_cursor(), schema),
+            None => (String::new(), Vec::new()),
+        }
+    }
+
+    fn handle_complete(&mut self) -> Option<Action> {
+        let (partial, suggestions) = self.completions();
+        if let Some(suggestion) = suggestions.first() {
+            self.query_textarea.insert_str(&suggestion[partial.len()..]);
+        }
+        None
+    }
+
+    pub fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        let is_query_editor = self.mode == BodyMode::GraphQL && self.selected_editor == GraphQLEditor::Query;
+        match input {
+            Input { key: Key::Esc, .. } => self.handle_exit_edit_mode(),
+            Input { key: Key::Tab, .. } if is_query_editor => self.handle_complete(),
+            input => {
+                self.selected_textarea().input(input);
+                None
             }
-            Err(_) => Some(Action::Suspend)
         }
     }
+
+    fn status_line(&self) -> Line<'_> {
+        let operation = match &self.operation_name {
+            Some(name) => name.as_str(),
+            None => "(none)",
+        };
+        let schema = match &self.schema {
+            Some(schema) => format!("{} types", schema.types.len()),
+            None => format!("none, press {} to fetch", Config::get().keys.keys(Context::Body, Command::Introspect)),
+        };
+        let mut spans = vec![Span::raw(format!("operation: {} | schema: {} | ", operation, schema))];
+
+        let is_completing = self.is_edit_mode && self.selected_editor == GraphQLEditor::Query;
+        let (_, suggestions) = self.completions();
+        let query = self.query_textarea.lines().join("\n");
+        let error = self.schema.as_ref()
+            .and_then(|schema| analyze(&query, schema).errors.into_iter().next())
+            .or_else(|| self.get_graphql().and_then(|graphql| graphql.envelope().err()));
+
+        if is_completing && !suggestions.is_empty() {
+            spans.push(Span::styled(format!("Tab: {}", suggestions.join(" ")), Style::default().fg(Color::Cyan)));
+        } else if let Some(error) = error {
+            spans.push(Span::styled(error, Style::default().fg(Color::Red)));
+        } else if let Some(notice) = &self.notice {
+            spans.push(Span::raw(notice.as_str()));
+        }
+        Line::from(spans)
+    }
+
+    fn render_graphql(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+        let editor_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(layout[0]);
+
+        let editor_block = |title: &'static str, editor: GraphQLEditor| {
+            let border_style = match (self.selected_editor == editor, self.is_edit_mode) {
+                (true, true) => Style::default().fg(Color::Green),
+                (true, false) => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
+            };
+            Block::default().borders(Borders::ALL).title(title).border_style(border_style)
+        };
+        let query_block = editor_block(" Query ", GraphQLEditor::Query);
+        let variables_block = editor_block(" Variables ", GraphQLEditor::Variables);
+        self.query_textarea.set_block(query_block);
+        self.variables_textarea.set_block(variables_block);
+
+        frame.render_widget(self.query_textarea.widget(), editor_layout[0]);
+        frame.render_widget(self.variables_textarea.widget(), editor_layout[1]);
+        frame.render_widget(Paragraph::new(self.status_line()), layout[1]);
+    }
+}
+
+pub fn textarea_with_text<'a>(text: &str) -> TextArea<'a> {
+    if text.is_empty() {
+        return TextArea::default();
+    }
+    TextArea::new(text.lines().map(String::from).collect())
+}
+
+impl<'a> Default for Body<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> Component for Body<'a> {
-    fn handle_key_events(&mut self) -> Option<Action> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
         if self.is_edit_mode {
-            return self.handle_edit_mode_key_events()
+            return self.handle_edit_mode_key_events(input)
         }
 
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Char('['), .. } => Some(Action::TabLeft),
-                    Input { key: Key::Char(']'), .. } => Some(Action::TabRight),
-                    Input { key: Key::Char('e'), .. } => self.handle_edit_mode(),
-                    Input { key: Key::Char('h'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::HistoryRequest)
-                    },
-                    Input { key: Key::Char('1'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window1Request)
-                    },
-                    Input { key: Key::Char('2'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window2Request)
-                    },
-                    Input { key: Key::Char('3'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window3Request)
-                    },
-                    _ => None
-                }
-            }
-            Err(_) => Some(Action::Suspend)
+        let is_graphql = self.mode == BodyMode::GraphQL;
+        match Config::get().keys.command(Context::Body, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::PreviousTab) => Some(Action::TabLeft),
+            Some(Command::NextTab) => Some(Action::TabRight),
+            Some(Command::Edit) => self.handle_edit_mode(),
+            Some(Command::ToggleMode) => self.handle_toggle_mode(),
+            Some(Command::SwitchEditor) if is_graphql => self.handle_switch_editor(),
+            Some(Command::NextOperation) if is_graphql => self.handle_next_operation(),
+            Some(Command::Introspect) if is_graphql => Some(Action::GraphQLIntrospectionRequest),
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Body)),
+            _ => None
         }
     }
 
@@ -107,9 +290,27 @@ impl<'a> Component for Body<'a> {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
-            .constraints([Constraint::Percentage(100)])
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
             .split(area);
-        frame.render_widget(self.body_textarea.widget(), layout[0]);
+
+        let keys = &Config::get().keys;
+        let key = |command| keys.keys(Context::Body, command);
+        let help = match self.mode {
+            BodyMode::Raw => format!(" - {} GraphQL mode, {} edit", key(Command::ToggleMode), key(Command::Edit)),
+            BodyMode::GraphQL => format!(" - {} raw mode, {} edit, {} editor, {} operation, {} fetch schema, Tab in query completes",
+                key(Command::ToggleMode), key(Command::Edit), key(Command::SwitchEditor), key(Command::NextOperation), key(Command::Introspect)),
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(format!("Mode: {}", self.mode), Style::default().fg(Color::Yellow)),
+                Span::styled(help, Style::default().fg(Color::DarkGray)),
+            ])),
+            layout[0]);
+
+        match self.mode {
+            BodyMode::Raw => frame.render_widget(self.body_textarea.widget(), layout[1]),
+            BodyMode::GraphQL => self.render_graphql(frame, layout[1]),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,144 @@
+use ratatui::{layout::Rect, style::{Color, Style}, text::Line, widgets::{Block, Borders}, Frame};
+use tui_textarea::{CursorMove, Input, Key, TextArea};
+
+use crate::config::Config;
+use crate::utils::json_filter::RecentFilters;
+
+/// What a key press in the filter bar did
+pub enum FilterEvent {
+    /// The expression changed, filter again
+    Changed,
+    /// The bar was closed
+    Closed,
+    None,
+}
+
+/// JSONPath or jq filter of the response body, applied while typing
+pub struct FilterBar<'a> {
+    pub textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+    /// Expression before the bar was opened, restored on Esc
+    previous_expression: String,
+    /// Why the expression cannot be applied, shown below it
+    pub error: Option<String>,
+    recent_filters: RecentFilters,
+    /// Method and URL of the request, recent filters are kept per request
+    request: String,
+    /// Position while browsing the recent filters with Up and Down
+    recent_index: Option<usize>,
+}
+
+impl<'a> FilterBar<'a> {
+    pub fn new() -> Self {
+        Self {
+            textarea: TextArea::default(),
+            is_edit_mode: false,
+            previous_expression: String::new(),
+            error: None,
+            recent_filters: RecentFilters::load(),
+            request: String::new(),
+            recent_index: None,
+        }
+    }
+
+    pub fn expression(&self) -> String {
+        self.textarea.lines().join("").trim().to_string()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_edit_mode || !self.expression().is_empty()
+    }
+
+    /// The filter is kept while the same request is sent again
+    pub fn set_request(&mut self, request: String) {
+        if request != self.request {
+            self.set_expression("");
+            self.error = None;
+        }
+        self.request = request;
+    }
+
+    pub fn open(&mut self) {
+        self.previous_expression = self.expression();
+        self.recent_index = None;
+        self.is_edit_mode = true;
+    }
+
+    fn set_expression(&mut self, expression: &str) {
+        self.textarea = TextArea::new(vec![expression.to_string()]);
+        self.textarea.move_cursor(CursorMove::End);
+    }
+
+    /// Step through the recent filters of the request, newest first
+    fn browse_recent(&mut self, older: bool) -> FilterEvent {
+        let recent = self.recent_filters.get(&self.request);
+        if recent.is_empty() {
+            return FilterEvent::None;
+        }
+        let index = match (self.recent_index, older) {
+            (None, true) => Some(0),
+            (None, false) => return FilterEvent::None,
+            (Some(index), true) => Some((index + 1).min(recent.len() - 1)),
+            (Some(0), false) => None,
+            (Some(index), false) => Some(index - 1),
+        };
+        let expression = index.map_or_else(|| self.previous_expression.clone(), |index| recent[index].clone());
+        self.recent_index = index;
+        self.set_expression(&expression);
+        FilterEvent::Changed
+    }
+
+    /// Keep the filter applied and remember it for the request
+    fn submit(&mut self) -> FilterEvent {
+        self.is_edit_mode = false;
+        let expression = self.expression();
+        if !expression.is_empty() && self.error.is_none() {
+            self.recent_filters.add(&self.request, &expression);
+            let _ = self.recent_filters.save();
+        }
+        FilterEvent::Closed
+    }
+
+    pub fn handle_input(&mut self, input: Input) -> FilterEvent {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                let expression = self.previous_expression.clone();
+                self.set_expression(&expression);
+                self.is_edit_mode = false;
+                FilterEvent::Changed
+            },
+            Input { key: Key::Enter, .. } => self.submit(),
+            Input { key: Key::Up, .. } => self.browse_recent(true),
+            Input { key: Key::Down, .. } => self.browse_recent(false),
+            input => {
+                if self.textarea.input(input) {
+                    FilterEvent::Changed
+                } else {
+                    FilterEvent::None
+                }
+            },
+        }
+    }
+
+    pub fn render_frame(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let (status, color) = match &self.error {
+            Some(error) => (error.clone(), Color::Red),
+            None if self.is_edit_mode => (String::from("Enter keep, Esc cancel, Up/Down recent"), Color::DarkGray),
+            None => (String::from("/ edit, empty to clear"), Color::DarkGray),
+        };
+        let border_color = if self.is_edit_mode { Config::get().theme.selected_border } else { Color::Yellow };
+        self.textarea.set_cursor_style(if self.is_edit_mode { Style::default().bg(Color::White) } else { Style::default() });
+        self.textarea.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(" Filter ($ JSONPath or . jq) ")
+            .title_bottom(Line::styled(format!(" {} ", status), Style::default().fg(color)))
+            .border_style(Style::default().fg(border_color)));
+        frame.render_widget(self.textarea.widget(), area);
+    }
+}
+
+impl<'a> Default for FilterBar<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,307 @@
+use prost_reflect::{DescriptorPool, MethodDescriptor};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, Paragraph}};
+use strum::{EnumIter, FromRepr, IntoEnumIterator};
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, config::Config, keymap::{Command, Context}, grpc::GrpcRequest, utils::{grpc_service::service_names, tui_frame_util::centered_rect}};
+
+use super::Component;
+
+#[derive(EnumIter, FromRepr, Clone, Copy, PartialEq)]
+pub enum GrpcField {
+    ProtoFiles,
+    ImportPaths,
+    Service,
+    Method,
+}
+
+impl GrpcField {
+    fn label(self) -> &'static str {
+        match self {
+            GrpcField::ProtoFiles => "Proto files",
+            GrpcField::ImportPaths => "Import paths",
+            GrpcField::Service => "Service",
+            GrpcField::Method => "Method",
+        }
+    }
+
+    fn value(self, request: &GrpcRequest) -> String {
+        match self {
+            GrpcField::ProtoFiles => request.proto_files.join(", "),
+            GrpcField::ImportPaths => request.import_paths.join(", "),
+            GrpcField::Service => request.service.clone(),
+            GrpcField::Method => request.method.clone(),
+        }
+    }
+
+    fn set_value(self, request: &mut GrpcRequest, value: &str) {
+        let list = || value.split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+        match self {
+            GrpcField::ProtoFiles => request.proto_files = list(),
+            GrpcField::ImportPaths => request.import_paths = list(),
+            GrpcField::Service => request.service = value.trim().to_string(),
+            GrpcField::Method => request.method = value.trim().to_string(),
+        }
+    }
+
+    /// Shown when the field is empty
+    fn placeholder(self) -> String {
+        let key = |command| Config::get().keys.keys(Context::Grpc, command);
+        match self {
+            GrpcField::ProtoFiles => String::from("(server reflection)"),
+            GrpcField::ImportPaths => String::from("(directories of the proto files)"),
+            GrpcField::Service | GrpcField::Method => format!("(press {} to load, {} to pick)", key(Command::LoadServices), key(Command::Cycle)),
+        }
+    }
+}
+
+pub struct Grpc<'a> {
+    pub request: GrpcRequest,
+    /// Service definitions loaded from the proto files or the server
+    pub pool: Option<DescriptorPool>,
+    pub selected_field_index: usize,
+    pub value_textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+    pub notice: Option<String>,
+}
+
+impl<'a> Grpc<'a> {
+    pub fn new() -> Self {
+        Self::new_with_request(GrpcRequest::default())
+    }
+
+    pub fn new_with_request(request: GrpcRequest) -> Self {
+        Self {
+            request,
+            pool: None,
+            selected_field_index: 0,
+            value_textarea: TextArea::default(),
+            is_edit_mode: false,
+            notice: None,
+        }
+    }
+
+    /// Use newly loaded definitions, keeping the selected method when it still exists
+    pub fn set_pool(&mut self, pool: DescriptorPool) {
+        let services = service_names(&pool);
+        self.notice = Some(format!("Loaded {} service(s)", services.len()));
+        if !services.contains(&self.request.service) {
+            self.request.service = services.first().cloned().unwrap_or_default();
+        }
+        self.pool = Some(pool);
+        if self.selected_method().is_none() {
+            self.request.method = self.method_names().first().cloned().unwrap_or_default();
+        }
+    }
+
+    pub fn selected_method(&self) -> Option<MethodDescriptor> {
+        self.pool.as_ref()?
+            .get_service_by_name(&self.request.service)?
+            .methods()
+            .find(|method| method.name() == self.request.method)
+    }
+
+    fn method_names(&self) -> Vec<String> {
+        self.pool.as_ref()
+            .and_then(|pool| pool.get_service_by_name(&self.request.service))
+            .map(|service| service.methods().map(|method| method.name().to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn selected_field(&self) -> GrpcField {
+        GrpcField::from_repr(self.selected_field_index).unwrap_or(GrpcField::ProtoFiles)
+    }
+
+    fn handle_traverse_up_request(&mut self) -> Option<Action> {
+        self.selected_field_index = self.selected_field_index.saturating_sub(1);
+        None
+    }
+
+    fn handle_traverse_down_request(&mut self) -> Option<Action> {
+        if self.selected_field_index < GrpcField::iter().count() - 1 {
+            self.selected_field_index += 1;
+        }
+        None
+    }
+
+    fn handle_edit_mode(&mut self) -> Option<Action> {
+        let field = self.selected_field();
+        let title = match field {
+            GrpcField::ProtoFiles | GrpcField::ImportPaths => format!(" {} (comma separated) ", field.label()),
+            _ => format!(" {} ", field.label()),
+        };
+        let mut text_area = TextArea::default();
+        text_area.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(Config::get().theme.selected_border)));
+        text_area.insert_str(field.value(&self.request));
+
+        self.value_textarea = text_area;
+        self.is_edit_mode = true;
+        None
+    }
+
+    fn handle_submit_value(&mut self) -> Option<Action> {
+        let field = self.selected_field();
+        field.set_value(&mut self.request, &self.value_textarea.lines()[0]);
+        if matches!(field, GrpcField::ProtoFiles | GrpcField::ImportPaths) {
+            // The loaded definitions no longer match the files
+            self.pool = None;
+        }
+        self.is_edit_mode = false;
+        None
+    }
+
+    /// Select the next service or method of the loaded definitions
+    fn handle_cycle_value(&mut self) -> Option<Action> {
+        let field = self.selected_field();
+        let choices = match (field, &self.pool) {
+            (GrpcField::Service, Some(pool)) => service_names(pool),
+            (GrpcField::Method, Some(_)) => self.method_names(),
+            (GrpcField::Service | GrpcField::Method, None) => {
+                self.notice = Some(String::from("Press r to load the services first"));
+                return None;
+            },
+            _ => return None,
+        };
+
+        let current = field.value(&self.request);
+        let next_index = choices.iter().position(|choice| *choice == current).map_or(0, |index| index + 1);
+        let next_value = choices.get(next_index).or(choices.first()).cloned().unwrap_or_default();
+        field.set_value(&mut self.request, &next_value);
+        if field == GrpcField::Service {
+            self.request.method = self.method_names().first().cloned().unwrap_or_default();
+        }
+        None
+    }
+
+    fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.is_edit_mode = false;
+                None
+            },
+            Input { key: Key::Enter, .. } => self.handle_submit_value(),
+            input => {
+                self.value_textarea.input(input);
+                None
+            }
+        }
+    }
+
+    /// Signature of the selected method, e.g. `rpc SayHello(HelloRequest) returns (stream HelloReply)`
+    fn method_signature(&self) -> Option<String> {
+        let method = self.selected_method()?;
+        let stream = |is_streaming: bool| if is_streaming { "stream " } else { "" };
+        Some(format!("rpc {}({}{}) returns ({}{})",
+            method.name(),
+            stream(method.is_client_streaming()),
+            method.input().full_name(),
+            stream(method.is_server_streaming()),
+            method.output().full_name()))
+    }
+}
+
+impl<'a> Default for Grpc<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Component for Grpc<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.is_edit_mode {
+            return self.handle_edit_mode_key_events(input)
+        }
+
+        match Config::get().keys.command(Context::Grpc, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::Edit) => self.handle_edit_mode(),
+            Some(Command::Cycle) => self.handle_cycle_value(),
+            Some(Command::LoadServices) => Some(Action::GrpcLoadRequest),
+            Some(Command::Template) => Some(Action::GrpcTemplateRequest),
+            Some(Command::PreviousTab) => Some(Action::TabLeft),
+            Some(Command::NextTab) => Some(Action::TabRight),
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Grpc)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+
+        let mut lines = GrpcField::iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let value = field.value(&self.request);
+                let value = if value.is_empty() {
+                    Span::styled(field.placeholder(), Style::default().fg(Color::DarkGray))
+                } else {
+                    Span::raw(value)
+                };
+                let line = Line::from(vec![Span::raw(format!("{:<30}", field.label())), value]);
+                if index == self.selected_field_index {
+                    line.style(Style::default().bg(Config::get().theme.selected_row))
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(signature) = self.method_signature() {
+            lines.push(Line::from(Span::styled(signature, Style::default().fg(Color::Yellow))));
+        }
+        let key = |command| Config::get().keys.keys(Context::Grpc, command);
+        lines.push(Line::from(Span::styled(
+            format!("{} load services, {} pick service/method, {} edit, {} request template in Body",
+                key(Command::LoadServices), key(Command::Cycle), key(Command::Edit), key(Command::Template)),
+            Style::default().fg(Color::DarkGray))));
+        frame.render_widget(Paragraph::new(lines), layout[0]);
+
+        if let Some(notice) = &self.notice {
+            frame.render_widget(
+                Paragraph::new(notice.as_str()).style(Style::default().add_modifier(Modifier::BOLD)),
+                layout[1]);
+        }
+
+        if self.is_edit_mode {
+            let popup_area = centered_rect(50, 20, frame.size());
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(self.value_textarea.widget(), popup_area);
+        }
+
+        Ok(())
+    }
+}
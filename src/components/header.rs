@@ -82,11 +82,11 @@ impl<'a> Header<'a> {
     }
 
     pub fn get_key_value(&mut self) -> String {
-        if self.key_value_textarea[0 as usize].lines().len() < 1 {
+        if self.key_value_textarea[0].lines().is_empty() {
             String::new()
         } else {
-            let key_as_string = self.key_value_textarea[0 as usize].lines()[0].as_str();
-            let value_as_string = self.key_value_textarea[1 as usize].lines()[0].as_str();
+            let key_as_string = self.key_value_textarea[0].lines()[0].as_str();
+            let value_as_string = self.key_value_textarea[1].lines()[0].as_str();
             [key_as_string, value_as_string].join(":")
         }
     }
@@ -96,21 +96,21 @@ impl<'a> Header<'a> {
     }
 }
 
+impl<'a> Default for Header<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Component for Header<'a> {
-    fn handle_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Tab, .. } => self.toggle_selected_textarea(),
-                    input => {
-                        self.key_value_textarea[self.selected_textarea as usize].input(input);
-                        None
-                    }
-                }
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => self.handle_deselect(),
+            Input { key: Key::Tab, .. } => self.toggle_selected_textarea(),
+            input => {
+                self.key_value_textarea[self.selected_textarea as usize].input(input);
+                None
             }
-            Err(_) => Some(Action::Suspend)
         }
     }
 
@@ -152,8 +152,8 @@ impl<'a> Component for Header<'a> {
 
         frame.render_widget(row_block, row_layout[0]);
 
-        frame.render_widget(self.key_value_textarea[0 as usize].widget(), main_layout[0]);
-        frame.render_widget(self.key_value_textarea[1 as usize].widget(), main_layout[1]);
+        frame.render_widget(self.key_value_textarea[0].widget(), main_layout[0]);
+        frame.render_widget(self.key_value_textarea[1].widget(), main_layout[1]);
         Ok(())
     }
 }
@@ -1,4 +1,6 @@
 use crate::action::Action;
+use crate::config::Config;
+use crate::keymap::{Command, Context};
 
 use super::header::Header;
 use super::Component;
@@ -9,7 +11,6 @@ use ratatui::layout::Rect;
 use ratatui::style::Style;
 use ratatui::widgets::Paragraph;
 use tui_textarea::Input;
-use tui_textarea::Key;
 
 pub struct Headers<'a>  {
     pub headers: Vec<Header<'a>>,
@@ -29,7 +30,7 @@ impl<'a> Headers<'a> {
 
     pub fn new_with_headers(headers: Vec<String>) -> Self {
         Self {
-            headers: headers.into_iter().map(|x| Header::new_with_key_value_pair(x)).collect(),
+            headers: headers.into_iter().map(Header::new_with_key_value_pair).collect(),
             selected_header_index:0,
             is_in_edit_mode: false,
         }
@@ -79,47 +80,48 @@ impl<'a> Headers<'a> {
         None
     }
 
-    fn handle_headers_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Char('j'), .. } => self.handle_traverse_down_request(),
-                    Input { key: Key::Char('k'), .. } => self.handle_traverse_up_request(),
-                    Input { key: Key::Char('e'), .. } => self.handle_edit_header(),
-                    Input { key: Key::Char('a'), .. } => self.handle_add_header(),
-                    Input { key: Key::Char('d'), .. } => self.handle_delete_header(),
-                    Input { key: Key::Char('['), .. } => Some(Action::TabLeft),
-                    Input { key: Key::Char(']'), .. } => Some(Action::TabRight),
-                    Input { key: Key::Char('h'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::HistoryRequest)
-                    },
-                    Input { key: Key::Char('1'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window1Request)
-                    },
-                    Input { key: Key::Char('2'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window2Request)
-                    },
-                    Input { key: Key::Char('3'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window3Request)
-                    },
-                    _ => None
-                }
-            }
-            Err(_) => Some(Action::Suspend)
+    fn handle_headers_key_events(&mut self, input: Input) -> Option<Action> {
+        match Config::get().keys.command(Context::Headers, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::Edit) => self.handle_edit_header(),
+            Some(Command::Add) => self.handle_add_header(),
+            Some(Command::Delete) => self.handle_delete_header(),
+            Some(Command::PreviousTab) => Some(Action::TabLeft),
+            Some(Command::NextTab) => Some(Action::TabRight),
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Headers)),
+            _ => None
         }
     }
 }
 
+impl<'a> Default for Headers<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Component for Headers<'a> {
-    fn handle_key_events(&mut self) -> Option<Action> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
         if self.is_in_edit_mode {
-            let action = self.headers.get_mut(self.selected_header_index).unwrap().handle_key_events();
+            let action = self.headers.get_mut(self.selected_header_index).unwrap().handle_key_events(input);
 
             if action.is_some_and(|a| a == Action::Suspend) {
                 self.is_in_edit_mode = false;
@@ -127,7 +129,7 @@ impl<'a> Component for Headers<'a> {
             None
 
         } else {
-            self.handle_headers_key_events()
+            self.handle_headers_key_events(input)
         }
     }
 
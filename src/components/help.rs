@@ -0,0 +1,75 @@
+use ratatui::{layout::Rect, style::{Color, Style}, text::{Line, Span}, widgets::{Clear, Paragraph}};
+use tui_textarea::Input;
+
+use crate::{action::Action, config::Config, keymap::Context, utils::{tui_block::main_block, tui_frame_util::centered_rect}};
+
+use super::Component;
+
+/// Popup listing the keys of a context as they are bound in `config.toml`,
+/// any key closes it and the window it was opened from stays selected
+pub struct Help {
+    pub context: Option<Context>,
+}
+
+impl Help {
+    pub fn new() -> Self {
+        Self { context: None }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.context.is_some()
+    }
+}
+
+impl Default for Help {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for Help {
+    fn handle_key_events(&mut self, _input: Input) -> Option<Action> {
+        self.handle_deselect()
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        self.context = None;
+        None
+    }
+
+    fn handle_select(&mut self) {}
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let Some(context) = self.context else {
+            return Ok(())
+        };
+        let keymap = &Config::get().keys;
+        let bindings = keymap.bindings(context);
+        let key_width = bindings.iter()
+            .map(|(command, _)| keymap.keys(context, *command).chars().count())
+            .max()
+            .unwrap_or(0);
+        let description_width = bindings.iter()
+            .map(|(command, _)| command.description().len())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = bindings.iter()
+            .map(|(command, _)| {
+                let keys = keymap.keys(context, *command);
+                let keys = if keys.is_empty() { String::from("-") } else { keys };
+                Line::from(vec![
+                    Span::styled(format!(" {:>width$} ", keys, width = key_width), Style::default().fg(Color::Cyan)),
+                    Span::raw(format!("{:width$}", command.description(), width = description_width)),
+                    Span::styled(format!("  {}", command), Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect();
+
+        let popup_area = centered_rect(60, 60, area);
+        let title = format!("Keys of the {} (keys.{})", context.title(), context);
+        let block = main_block(&true, &title).title_bottom(" any key to close ");
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+        Ok(())
+    }
+}
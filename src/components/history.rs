@@ -1,45 +1,95 @@
-use std::usize;
-
 use ratatui::{layout::Rect, style::{Color, Style}, symbols::scrollbar, text::Line, widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState}};
-use tui_textarea::{Input, Key};
+use tui_textarea::Input;
 
-use crate::{action::Action, http_method::HTTPMethod, lazycurl_file:: LazyCurlFile, utils::tui_block::main_block};
+use crate::{action::Action, config::Config, http_file::HttpFile, http_method::HTTPMethod, keymap::{Command, Context}, lazycurl_file:: LazyCurlFile, utils::tui_block::main_block};
 
 use super::Component;
 
+/// Lists the history, or the requests of the open `.http` file
 pub struct History {
     selected_file: Option<LazyCurlFile>,
+    /// Index in the `.http` file of the selected request, None for history
+    selected_request: Option<usize>,
     currently_selected_file: usize,
     lazycurl_files: Vec<LazyCurlFile>,
     scrollbar_state: ScrollbarState,
     selected: bool,
+    pub http_file: Option<HttpFile>,
+    show_http_file: bool,
+    pub notice: Option<String>,
 }
 
 impl History {
     pub fn new() -> Self {
         Self {
             selected_file: None,
+            selected_request: None,
             currently_selected_file: 0,
             lazycurl_files: Vec::new(),
             scrollbar_state: ScrollbarState::new(0),
-            selected: false
+            selected: false,
+            http_file: None,
+            show_http_file: false,
+            notice: None,
         }
     }
 
+    pub fn set_http_file(&mut self, http_file: HttpFile) {
+        self.http_file = Some(http_file);
+        self.show_http_file = true;
+    }
 
     pub fn take_selected_file(&mut self) -> Option<LazyCurlFile> {
         self.selected_file.take()
     }
 
+    pub fn take_selected_request(&mut self) -> Option<usize> {
+        self.selected_request.take()
+    }
+
     fn handle_load_request(&mut self) -> Option<Action> {
-        if self.lazycurl_files.len() < 1 {
+        if let (true, Some(http_file)) = (self.show_http_file, &self.http_file) {
+            return match http_file.request(self.currently_selected_file) {
+                Ok(lazycurl_file) => {
+                    self.selected_file = Some(lazycurl_file);
+                    self.selected_request = Some(self.currently_selected_file);
+                    self.notice = None;
+                    Some(Action::LazycurlFileLoadRequest)
+                },
+                Err(e) => {
+                    self.notice = Some(e);
+                    None
+                },
+            };
+        }
+
+        if self.lazycurl_files.is_empty() {
             self.handle_deselect()
         } else {
             self.selected_file = Some(self.lazycurl_files.get(self.currently_selected_file).unwrap().clone());
+            self.selected_request = None;
             Some(Action::LazycurlFileLoadRequest)
         }
     }
 
+    /// Switch between the history and the requests of the open `.http` file
+    fn handle_switch_list(&mut self) -> Option<Action> {
+        if self.http_file.is_some() {
+            self.show_http_file = !self.show_http_file;
+            self.currently_selected_file = 0;
+            self.scrollbar_state = self.scrollbar_state.position(0);
+            self.notice = None;
+        }
+        None
+    }
+
+    fn list_len(&self) -> usize {
+        match (&self.http_file, self.show_http_file) {
+            (Some(http_file), true) => http_file.requests().count(),
+            _ => self.lazycurl_files.len(),
+        }
+    }
+
     fn handle_traverse_up_request(&mut self) -> Option<Action> {
         if self.currently_selected_file > 0  {
             self.currently_selected_file -= 1;
@@ -50,11 +100,11 @@ impl History {
     }
 
     fn handle_traverse_down_request(&mut self) -> Option<Action>{
-        if self.lazycurl_files.len() == 0 {
+        if self.list_len() == 0 {
             return None;
         }
 
-        if self.currently_selected_file < (self.lazycurl_files.len() - 1)  {
+        if self.currently_selected_file < (self.list_len() - 1)  {
             self.currently_selected_file += 1;
         }
 
@@ -70,26 +120,26 @@ impl History {
             String::new(), Vec::<String>::new(), HTTPMethod::POST
         ).get_history_lazycurlfiles().unwrap();
 
-        self.scrollbar_state = self.scrollbar_state.content_length(self.lazycurl_files.len())
+        self.scrollbar_state = self.scrollbar_state.content_length(self.list_len())
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Component for History {
-    fn handle_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Char('l'), .. } => self.handle_load_request(),
-                    Input { key: Key::Enter, .. } => self.handle_load_request(),
-                    Input { key: Key::Char(' '), .. } => self.handle_load_request(),
-                    Input { key: Key::Char('j'), .. } => self.handle_traverse_down_request(),
-                    Input { key: Key::Char('k'), .. } => self.handle_traverse_up_request(),
-                    _ => None
-                }
-            }
-            Err(_) => Some(Action::Suspend)
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        match Config::get().keys.command(Context::History, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Load) => self.handle_load_request(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::SwitchList) => self.handle_switch_list(),
+            Some(Command::Help) => Some(Action::HelpRequest(Context::History)),
+            _ => None
         }
     }
 
@@ -107,23 +157,45 @@ impl Component for History {
     fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
         self.get_lazycurl_files();
 
-        if self.lazycurl_files.is_empty() {
-            let mut p = Paragraph::new("No history found")
-                    .block(Block::default().title("History").borders(Borders::ALL));
-            p = p.style(Style::default().bg(Color::Red));
-            frame.render_widget(p, area);
-            return Ok(())
+        let (title, requests) = match (&self.http_file, self.show_http_file) {
+            (Some(http_file), true) => {
+                let file_name = http_file.path.file_name().map_or_else(String::new, |name| name.to_string_lossy().to_string());
+                let requests = http_file.requests()
+                    .map(|request| match (&request.name, &request.lazycurl_file) {
+                        (Some(name), Ok(f)) => format!("{} {} {}", name, f.http_method, f.url),
+                        (None, Ok(f)) => format!("{} {}", f.http_method, f.url),
+                        (name, Err(e)) => format!("{} {}", name.as_deref().unwrap_or("request"), e),
+                    })
+                    .collect::<Vec<_>>();
+                (file_name, requests)
+            },
+            _ => {
+                if self.lazycurl_files.is_empty() && self.http_file.is_none() {
+                    let mut p = Paragraph::new("No history found")
+                            .block(Block::default().title("History").borders(Borders::ALL));
+                    p = p.style(Style::default().bg(Color::Red));
+                    frame.render_widget(p, area);
+                    return Ok(())
+                }
+                (String::from("History"), self.lazycurl_files.iter().map(|f| format!("{} {}", f.http_method, f.url)).collect())
+            },
+        };
+
+        let mut block = main_block(&self.selected, &title);
+        if self.http_file.is_some() {
+            let other = if self.show_http_file { "history" } else { "file" };
+            block = block.title_bottom(Line::from(format!(" Tab {} ", other)).right_aligned());
+        }
+        if let Some(notice) = &self.notice {
+            block = block.title_bottom(Line::from(format!(" {} ", notice)));
         }
 
-        let block = main_block(&self.selected, "History");
-
-        let paragraph = Paragraph::new(self.lazycurl_files
-                .iter()
+        let paragraph = Paragraph::new(requests
+                .into_iter()
                 .enumerate()
-                .map(|(index, f)| {
-                        let request_info = format!("{} {}", f.http_method, f.url);
+                .map(|(index, request_info)| {
                         if index == self.currently_selected_file {
-                            Line::from(request_info).style(Style::default().bg(Color::Blue))
+                            Line::from(request_info).style(Style::default().bg(Config::get().theme.selected_row))
                         } else {
                             Line::from(request_info)
                         }
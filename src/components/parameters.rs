@@ -1,17 +1,28 @@
-use std::usize;
-
-use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::Line, widgets::{Block, Borders, Tabs, Widget}};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, text::Line, widgets::{Tabs, Widget}};
+use serde_json::Value;
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
+use tui_textarea::Input;
+
+use crate::{action::Action, config::Config, utils::tui_block::main_block};
 
-use crate::{action::Action, utils::tui_block::main_block};
+use crate::{assertion::{evaluate, parse_assertions, response_json, Assertion, AssertionResult}, extraction::{extract, parse_extractions, Extraction, ExtractionResult}, grpc::GrpcRequest, lazycurl_file::LazyCurlFile, script::{run_post_response, ScriptOutput}, request_settings::RequestSettings, utils::{curl_service::CurlResponse, graphql::GraphQLBody, grpc_service::request_template}, variables::Variables};
 
-use super::{body::Body, headers::Headers, Component};
+use super::{body::Body, grpc::Grpc, headers::Headers, settings::Settings, rules::{RuleKind, Rules}, Component};
 
 
 #[derive(EnumIter, Display, Copy, Clone, FromRepr)]
 pub enum SelectedTab {
     Headers,
     Body,
+    Settings,
+    #[strum(serialize = "gRPC")]
+    Grpc,
+    Tests,
+    Extract,
+    #[strum(serialize = "Pre-request")]
+    PreRequest,
+    #[strum(serialize = "Post-response")]
+    PostResponse,
 }
 
 impl SelectedTab {
@@ -38,6 +49,12 @@ pub struct Parameters<'a> {
     pub selected: bool,
     pub headers_component: Headers<'a>,
     pub body_component: Body<'a>,
+    pub settings_component: Settings<'a>,
+    pub grpc_component: Grpc<'a>,
+    pub tests_component: Rules<'a>,
+    pub extract_component: Rules<'a>,
+    pub pre_request_component: Rules<'a>,
+    pub post_response_component: Rules<'a>,
     pub selected_tab: SelectedTab,
 }
 
@@ -48,15 +65,27 @@ impl<'a> Parameters<'a> {
             headers_component: Headers::new(),
             selected_tab: SelectedTab::Headers,
             body_component: Body::new(),
+            settings_component: Settings::new(),
+            grpc_component: Grpc::new(),
+            tests_component: Rules::new(RuleKind::Tests),
+            extract_component: Rules::new(RuleKind::Extract),
+            pre_request_component: Rules::new(RuleKind::PreRequest),
+            post_response_component: Rules::new(RuleKind::PostResponse),
         }
     }
 
-    pub fn new_with_headers(headers: Vec<String>) -> Self {
+    pub fn new_with_lazycurl_file(lazycurl_file: &LazyCurlFile) -> Self {
         Self {
             selected: false,
-            headers_component: Headers::new_with_headers(headers),
+            headers_component: Headers::new_with_headers(lazycurl_file.headers.clone()),
             selected_tab: SelectedTab::Headers,
-            body_component: Body::new(),
+            body_component: Body::new_with_body(lazycurl_file.body.clone(), lazycurl_file.graphql.clone()),
+            settings_component: Settings::new_with_settings(lazycurl_file.settings.clone()),
+            grpc_component: Grpc::new_with_request(lazycurl_file.grpc.clone().unwrap_or_default()),
+            tests_component: Rules::new_with_rules(RuleKind::Tests, &lazycurl_file.assertions),
+            extract_component: Rules::new_with_rules(RuleKind::Extract, &lazycurl_file.extractions),
+            pre_request_component: Rules::new_with_text(RuleKind::PreRequest, &lazycurl_file.pre_request_script),
+            post_response_component: Rules::new_with_text(RuleKind::PostResponse, &lazycurl_file.post_response_script),
         }
     }
 
@@ -64,10 +93,77 @@ impl<'a> Parameters<'a> {
         self.headers_component.get_key_values()
     }
 
-    pub fn get_body(&mut self) -> &str {
+    pub fn get_body(&self) -> Result<String, String> {
         self.body_component.get_body_text()
     }
 
+    pub fn get_raw_body(&self) -> String {
+        self.body_component.get_raw_body()
+    }
+
+    pub fn get_graphql(&self) -> Option<GraphQLBody> {
+        self.body_component.get_graphql()
+    }
+
+    pub fn get_settings(&self) -> RequestSettings {
+        self.settings_component.settings.clone()
+    }
+
+    pub fn get_grpc(&self) -> GrpcRequest {
+        self.grpc_component.request.clone()
+    }
+
+    pub fn get_assertions(&self) -> Result<Vec<Assertion>, String> {
+        parse_assertions(&self.tests_component.get_text())
+    }
+
+    pub fn get_extractions(&self) -> Result<Vec<Extraction>, String> {
+        parse_extractions(&self.extract_component.get_text())
+    }
+
+    pub fn get_pre_request_script(&self) -> String {
+        self.pre_request_component.get_text()
+    }
+
+    pub fn get_post_response_script(&self) -> String {
+        self.post_response_component.get_text()
+    }
+
+    /// Check the response against the Tests tab, set the variables of the
+    /// Extract tab and run the post-response script, whose checks are added
+    /// to the test results. `json` replaces the body parsed as JSON, e.g.
+    /// with the messages of a gRPC call.
+    pub fn evaluate_response(&self, response: &CurlResponse, json: Option<Value>, variables: &mut Variables) -> (Vec<AssertionResult>, Vec<ExtractionResult>, ScriptOutput) {
+        let assertions = self.get_assertions().unwrap_or_default();
+        let extractions = self.get_extractions().unwrap_or_default();
+        let script = self.get_post_response_script();
+        if assertions.is_empty() && extractions.is_empty() && script.trim().is_empty() {
+            return (Vec::new(), Vec::new(), ScriptOutput::default());
+        }
+        let json = json.or_else(|| response_json(response));
+        let mut test_results = evaluate(&assertions, response, json.as_ref());
+        let extraction_results = extract(&extractions, response, json.as_ref(), variables);
+        let mut script_output = run_post_response(&script, response, json.as_ref(), variables);
+        test_results.append(&mut script_output.test_results);
+        (test_results, extraction_results, script_output)
+    }
+
+    /// Fill the body with the input message of the selected gRPC method
+    fn insert_grpc_template(&mut self) -> Option<Action> {
+        match self.grpc_component.selected_method() {
+            Some(method) => {
+                self.body_component.set_raw_body(&request_template(&method.input()));
+                self.grpc_component.notice = Some(format!("Body set to a {} message", method.input().name()));
+            },
+            None => self.grpc_component.notice = Some(String::from("Load the services and pick a method first")),
+        }
+        None
+    }
+
+    pub fn get_effective_settings(&self) -> RequestSettings {
+        self.settings_component.get_effective_settings()
+    }
+
     pub fn previous_tab(&mut self) -> Option<Action> {
         self.selected_tab = self.selected_tab.previous();
         None
@@ -79,16 +175,34 @@ impl<'a> Parameters<'a> {
     }
 }
 
+impl<'a> Default for Parameters<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Component for Parameters<'a> {
-    fn handle_key_events(&mut self) -> Option<Action> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
         let event = match self.selected_tab {
-            SelectedTab::Headers => self.headers_component.handle_key_events(),
-            SelectedTab::Body => self.body_component.handle_key_events(),
+            SelectedTab::Headers => self.headers_component.handle_key_events(input),
+            SelectedTab::Body => self.body_component.handle_key_events(input),
+            SelectedTab::Settings => self.settings_component.handle_key_events(input),
+            SelectedTab::Grpc => self.grpc_component.handle_key_events(input),
+            SelectedTab::Tests => self.tests_component.handle_key_events(input),
+            SelectedTab::Extract => self.extract_component.handle_key_events(input),
+            SelectedTab::PreRequest => self.pre_request_component.handle_key_events(input),
+            SelectedTab::PostResponse => self.post_response_component.handle_key_events(input),
         };
         match event {
             Some(Action::Suspend) => self.handle_deselect(),
             Some(Action::TabRight) => self.next_tab(),
             Some(Action::TabLeft) => self.previous_tab(),
+            // The body window stays selected while the schema is fetched
+            Some(Action::GraphQLIntrospectionRequest) => Some(Action::GraphQLIntrospectionRequest),
+            Some(Action::GrpcLoadRequest) => Some(Action::GrpcLoadRequest),
+            Some(Action::GrpcTemplateRequest) => self.insert_grpc_template(),
+            // The window stays selected behind the help popup
+            Some(Action::HelpRequest(context)) => Some(Action::HelpRequest(context)),
             some_action => {
                 if some_action.is_some() {
                     self.handle_deselect();
@@ -124,14 +238,20 @@ impl<'a> Component for Parameters<'a> {
 
         Tabs::new(tab_titles)
             .block(block)
-            .highlight_style(Color::Yellow)
+            .highlight_style(Config::get().theme.selected_tab)
             .select(self.selected_tab as usize)
             .divider("|")
             .render(area, frame.buffer_mut());
 
         let _ = match self.selected_tab {
             SelectedTab::Headers => self.headers_component.render_frame(frame, layout[1]),
-            SelectedTab::Body => self.body_component.render_frame(frame, layout[1])
+            SelectedTab::Body => self.body_component.render_frame(frame, layout[1]),
+            SelectedTab::Settings => self.settings_component.render_frame(frame, layout[1]),
+            SelectedTab::Grpc => self.grpc_component.render_frame(frame, layout[1]),
+            SelectedTab::Tests => self.tests_component.render_frame(frame, layout[1]),
+            SelectedTab::Extract => self.extract_component.render_frame(frame, layout[1]),
+            SelectedTab::PreRequest => self.pre_request_component.render_frame(frame, layout[1]),
+            SelectedTab::PostResponse => self.post_response_component.render_frame(frame, layout[1]),
         };
 
         Ok(())
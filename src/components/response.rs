@@ -1,59 +1,552 @@
-use ratatui::{layout::Rect, style::{Color, Style}, widgets::{Block, Borders, Paragraph}};
+use std::{fmt, fs};
+
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, Paragraph, Tabs}};
 use serde_json::Value;
-use tui_textarea::{Input, Key};
+use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, config::Config, assertion::AssertionResult, extraction::ExtractionResult, keymap::{Command, Context}, script::ConsoleEntry, utils::{binary, json_filter, bytes::{escape_bytes, format_size, hex_dump}, charset::{decode, detect_charset, encoding_for_label}, curl_service::{self, CurlResponse, StreamError}, grpc_service::GrpcResponse, sse::{SseEvent, SseParser}, tui_block::main_block, tui_frame_util::centered_rect}};
 
-use crate::{action::Action, utils::tui_block::main_block};
+use super::{filter_bar::{FilterBar, FilterEvent}, Component};
 
-use super::Component;
+#[derive(EnumIter, Display, Copy, Clone, FromRepr)]
+pub enum ResponseView {
+    Body,
+    Raw,
+    Hex,
+    Events,
+    Tests,
+    Console,
+}
+
+impl ResponseView {
+    fn title(self) -> Line<'static> {
+        format!(" {self} ")
+            .into()
+    }
 
-pub struct Response {
+    fn next(self) -> Self {
+        let current_index = self as usize;
+        let next_index = current_index.saturating_add(1);
+        Self::from_repr(next_index).unwrap_or(self)
+    }
+
+    fn previous(self) -> Self {
+        let current_index = self as usize;
+        let previous_index = current_index.saturating_sub(1);
+        Self::from_repr(previous_index).unwrap_or(self)
+    }
+}
+
+/// Single line input shown as a popup over the response
+#[derive(Clone, Copy)]
+pub enum Prompt {
+    SaveBody,
+    Charset,
+}
+
+impl Prompt {
+    fn title(self) -> &'static str {
+        match self {
+            Prompt::SaveBody => " Save response body as ",
+            Prompt::Charset => " Decode response as charset (empty = auto) ",
+        }
+    }
+}
+
+pub struct Response<'a> {
     pub selected: bool,
     pub response_value: String,
+    pub raw_value: String,
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    pub charset_override: Option<String>,
+    pub suggested_filename: String,
+    pub metadata: String,
+    pub charset_metadata: String,
+    pub notice: Option<String>,
+    pub selected_view: ResponseView,
+    /// First line in view, a line of 16 bytes in the Hex view
+    pub scroll: usize,
+    /// Keep the end of the response in view while it streams in
+    pub follow: bool,
+    pub prompt: Option<Prompt>,
+    pub prompt_textarea: TextArea<'a>,
+    pub is_streaming: bool,
+    pub events: Vec<SseEvent>,
+    pub sse_parser: SseParser,
+    /// Bytes at the end of the streamed chunks that start a character the
+    /// next chunk completes
+    pub pending_utf8: Vec<u8>,
+    /// Outcome of the checks of the Tests tab for this response
+    pub test_results: Vec<AssertionResult>,
+    /// Variables set by the rules of the Extract tab
+    pub extraction_results: Vec<ExtractionResult>,
+    /// Output of the scripts of the last send
+    pub console: Vec<ConsoleEntry>,
+    /// The body parsed as JSON, what the filter applies to
+    pub json: Option<Value>,
+    pub filter_bar: FilterBar<'a>,
+    /// The body after the filter, the last one that could be applied
+    pub filtered_value: String,
 }
 
-impl Response {
+impl<'a> Response<'a> {
     pub fn new() -> Self {
         Self {
             selected: false,
             response_value: String::new(),
+            raw_value: String::new(),
+            body: Vec::new(),
+            content_type: None,
+            charset_override: None,
+            suggested_filename: String::new(),
+            metadata: String::new(),
+            charset_metadata: String::new(),
+            notice: None,
+            selected_view: ResponseView::Body,
+            scroll: 0,
+            follow: false,
+            prompt: None,
+            prompt_textarea: TextArea::default(),
+            is_streaming: false,
+            events: Vec::new(),
+            sse_parser: SseParser::default(),
+            pending_utf8: Vec::new(),
+            test_results: Vec::new(),
+            extraction_results: Vec::new(),
+            console: Vec::new(),
+            json: None,
+            filter_bar: FilterBar::new(),
+            filtered_value: String::new(),
+        }
+    }
+
+    /// Show a response, decoding its body with `charset_override` when set
+    pub fn update_response(&mut self, response: &CurlResponse, charset_override: Option<String>) {
+        self.body = response.body.clone();
+        self.content_type = response.content_type().map(String::from);
+        self.charset_override = charset_override;
+        self.suggested_filename = response.suggested_filename();
+        self.raw_value = escape_bytes(&response.body);
+        self.selected_view = if self.is_binary() { ResponseView::Hex } else { ResponseView::Body };
+        self.decode_body();
+        self.update_metadata(response);
+        self.notice = None;
+        self.scroll = 0;
+        self.follow = false;
+        self.events = Vec::new();
+        self.test_results = Vec::new();
+        self.extraction_results = Vec::new();
+    }
+
+    /// Clear the response before the chunks of a streamed response arrive
+    pub fn start_stream(&mut self, charset_override: Option<String>) {
+        *self = Self {
+            selected: self.selected,
+            charset_override,
+            metadata: String::from(" Connecting... "),
+            notice: Some(String::from("Streaming, press x to stop")),
+            follow: true,
+            is_streaming: true,
+            console: std::mem::take(&mut self.console),
+            filter_bar: std::mem::take(&mut self.filter_bar),
+            ..Self::new()
+        };
+    }
+
+    pub fn update_stream_headers(&mut self, headers: &[String]) {
+        self.content_type = curl_service::header_values(headers, "Content-Type").first().map(|value| value.to_string());
+        if self.is_event_stream() {
+            self.selected_view = ResponseView::Events;
+        }
+        self.metadata = format!(" {} {} | streaming ", curl_service::http_version(headers), curl_service::status(headers));
+    }
+
+    pub fn update_stream_chunk(&mut self, chunk: &[u8]) {
+        self.body.extend_from_slice(chunk);
+        self.raw_value.push_str(&escape_bytes(chunk));
+
+        self.pending_utf8.extend_from_slice(chunk);
+        let mut complete = 0;
+        let complete = loop {
+            match std::str::from_utf8(&self.pending_utf8[complete..]) {
+                Ok(_) => break self.pending_utf8.len(),
+                // Invalid bytes are replaced below, a character cut off at the end waits for the next chunk
+                Err(e) => match e.error_len() {
+                    Some(len) => complete += e.valid_up_to() + len,
+                    None => break complete + e.valid_up_to(),
+                },
+            }
+        };
+        let incomplete = self.pending_utf8.split_off(complete);
+        self.response_value.push_str(&String::from_utf8_lossy(&self.pending_utf8));
+        self.pending_utf8 = incomplete;
+
+        if self.is_event_stream() {
+            self.events.extend(self.sse_parser.push(chunk));
+        }
+    }
+
+    pub fn finish_stream(&mut self, result: &Result<CurlResponse, StreamError>) {
+        self.is_streaming = false;
+        match result {
+            Ok(response) => {
+                self.suggested_filename = response.suggested_filename();
+                self.decode_body();
+                self.update_metadata(response);
+                self.notice = Some(String::from("Stream finished"));
+            },
+            Err(error) => self.notice = Some(error.to_string()),
+        }
+    }
+
+    fn is_event_stream(&self) -> bool {
+        self.content_type.as_deref().is_some_and(|content_type| content_type.starts_with("text/event-stream"))
+    }
+
+    fn is_binary(&self) -> bool {
+        binary::is_binary(self.content_type.as_deref(), &self.body)
+    }
+
+    fn decode_body(&mut self) {
+        if self.is_binary() {
+            let summary = binary::summary(&self.body, &format_size(self.body.len() as u64));
+            self.response_value = format!("{}\n\nSee the Hex view or press s to save the body to a file.", summary);
+            self.charset_metadata = String::new();
+            self.json = None;
+            self.refresh_filter();
+            return;
+        }
+
+        let (encoding, source) = detect_charset(self.charset_override.as_deref(), self.content_type.as_deref(), &self.body);
+        self.update_response_value(decode(&self.body, encoding));
+        self.charset_metadata = format!(" {} ({}) ", encoding.name(), source.description());
+    }
+
+    pub fn update_metadata(&mut self, response: &CurlResponse) {
+        let size = if response.download_size != self.body.len() as u64 {
+            format!("{} encoded / {} decoded", format_size(response.download_size), format_size(self.body.len() as u64))
+        } else {
+            format_size(self.body.len() as u64)
+        };
+
+        self.metadata = format!(" {} {} | {} ms | {} | {} attempt(s) ",
+            response.http_version,
+            response.status,
+            response.total_time.as_millis(),
+            size,
+            response.attempts);
+    }
+
+    pub fn update_error(&mut self, error: &impl fmt::Display) {
+        self.response_value = error.to_string();
+        self.raw_value = String::new();
+        self.body = Vec::new();
+        self.content_type = None;
+        self.metadata = String::from(" Request failed ");
+        self.charset_metadata = String::new();
+        self.notice = None;
+        self.scroll = 0;
+        self.test_results = Vec::new();
+        self.extraction_results = Vec::new();
+        self.json = None;
+        self.refresh_filter();
+    }
+
+    /// Show which checks of the Tests tab the response passed and the
+    /// variables set by the Extract tab
+    pub fn update_rule_results(&mut self, test_results: Vec<AssertionResult>, extraction_results: Vec<ExtractionResult>) {
+        let passed = test_results.iter().filter(|result| result.passed).count();
+        if !test_results.is_empty() {
+            self.metadata = format!("{}| tests {}/{} passed ", self.metadata, passed, test_results.len());
+        }
+        let set = extraction_results.iter().filter(|result| result.value.is_ok()).count();
+        if passed < test_results.len() {
+            self.notice = Some(format!("{} test(s) failed, see the Tests view", test_results.len() - passed));
+        } else if set < extraction_results.len() {
+            self.notice = Some(format!("{} variable(s) not set, see the Tests view", extraction_results.len() - set));
+        } else if set > 0 {
+            self.notice = Some(format!("Set {} variable(s)", set));
+        }
+        self.test_results = test_results;
+        self.extraction_results = extraction_results;
+    }
+
+    pub fn clear_console(&mut self) {
+        self.console = Vec::new();
+    }
+
+    pub fn add_console(&mut self, entries: Vec<ConsoleEntry>) {
+        if let Some(error) = entries.iter().find(|entry| entry.is_error) {
+            self.notice = Some(format!("{} script failed, see the Console view", error.stage.label()));
+        }
+        self.console.extend(entries);
+    }
+
+    fn console_lines(&self) -> Vec<Line<'_>> {
+        if self.console.is_empty() {
+            return vec![Line::styled("No output, print() in the Pre-request and Post-response tabs shows here", Style::default().fg(Color::DarkGray))];
+        }
+        self.console.iter()
+            .map(|entry| {
+                let style = if entry.is_error { Style::default().fg(Color::Red) } else { Style::default() };
+                Line::styled(entry.to_string(), style)
+            })
+            .collect()
+    }
+
+    fn test_result_lines(&self) -> Vec<Line<'_>> {
+        if self.test_results.is_empty() && self.extraction_results.is_empty() {
+            return vec![Line::styled("No tests, add checks in the Tests tab of the Parameters window", Style::default().fg(Color::DarkGray))];
+        }
+        let test_lines = self.test_results.iter()
+            .map(|result| {
+                let color = if result.passed { Color::Green } else { Color::Red };
+                Line::styled(result.to_string(), Style::default().fg(color))
+            });
+        let extraction_lines = self.extraction_results.iter()
+            .map(|result| {
+                let color = if result.value.is_ok() { Color::Cyan } else { Color::Red };
+                Line::styled(result.to_string(), Style::default().fg(color))
+            });
+        test_lines.chain(extraction_lines).collect()
+    }
+
+    /// Show the messages of a gRPC call as JSON, followed by its status and trailers
+    pub fn update_grpc_response(&mut self, grpc_response: &GrpcResponse) {
+        self.update_response(&grpc_response.response, None);
+
+        let mut value = serde_json::to_string_pretty(&grpc_response.messages_value()).unwrap_or_default();
+        value.push_str(&format!("\n\nStatus: {}", grpc_response.status_description()));
+        if !grpc_response.status_message.is_empty() {
+            value.push_str(&format!("\nMessage: {}", grpc_response.status_message));
         }
+        if !grpc_response.trailers.is_empty() {
+            value.push_str("\nTrailers:");
+            for trailer in &grpc_response.trailers {
+                value.push_str(&format!("\n  {}", trailer));
+            }
+        }
+
+        self.response_value = value;
+        self.json = Some(grpc_response.messages_value());
+        self.refresh_filter();
+        self.selected_view = ResponseView::Body;
+        self.metadata = format!("{}| gRPC {} ", self.metadata, grpc_response.status_description());
     }
 
     pub fn update_response_value(&mut self, response: String) {
-        self.response_value = match serde_json::from_str::<Value>(response.as_str()) {
-            Ok(v) => serde_json::to_string_pretty(&v).expect("Erro found"),
-            Err(_) => response.clone()
+        self.json = serde_json::from_str::<Value>(response.as_str()).ok();
+        self.response_value = match &self.json {
+            Some(v) => serde_json::to_string_pretty(v).expect("Erro found"),
+            None => response.clone()
         };
+        self.refresh_filter();
     }
-}
 
-impl Component for Response {
-    fn handle_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Char('h'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::HistoryRequest)
-                    },
-                    Input { key: Key::Char('1'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window1Request)
-                    },
-                    Input { key: Key::Char('2'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window2Request)
-                    },
-                    Input { key: Key::Char('3'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window3Request)
-                    },
-                    _ => None
+    /// Apply the filter to the body again, keeping the last result when the
+    /// expression is not valid yet
+    fn refresh_filter(&mut self) {
+        let expression = self.filter_bar.expression();
+        if expression.is_empty() {
+            self.filter_bar.error = None;
+            self.filtered_value = String::new();
+            return;
+        }
+        let result = match &self.json {
+            Some(json) => json_filter::apply(&expression, json),
+            None => Err(String::from("The response is not JSON")),
+        };
+        match result {
+            Ok(filtered_value) => {
+                self.filtered_value = filtered_value;
+                self.filter_bar.error = None;
+            },
+            Err(e) => self.filter_bar.error = Some(e),
+        }
+    }
+
+    fn handle_open_filter(&mut self) -> Option<Action> {
+        self.filter_bar.open();
+        self.selected_view = ResponseView::Body;
+        self.scroll = 0;
+        None
+    }
+
+    fn handle_filter_key_events(&mut self, input: Input) -> Option<Action> {
+        if let FilterEvent::Changed = self.filter_bar.handle_input(input) {
+            self.refresh_filter();
+            self.scroll = 0;
+        }
+        None
+    }
+
+    /// The body as shown in the Body view, filtered when a filter is set
+    fn body_value(&self) -> &str {
+        if self.filter_bar.expression().is_empty() || (self.filtered_value.is_empty() && self.filter_bar.error.is_some()) {
+            &self.response_value
+        } else {
+            &self.filtered_value
+        }
+    }
+
+    fn handle_scroll_down(&mut self) -> Option<Action> {
+        self.scroll = self.scroll.saturating_add(1);
+        self.follow = false;
+        None
+    }
+
+    fn handle_scroll_up(&mut self) -> Option<Action> {
+        self.scroll = self.scroll.saturating_sub(1);
+        self.follow = false;
+        None
+    }
+
+    fn handle_follow(&mut self) -> Option<Action> {
+        self.follow = true;
+        None
+    }
+
+    fn event_lines(&self) -> Vec<Line<'_>> {
+        self.events.iter()
+            .flat_map(|event| {
+                let mut header = vec![
+                    Span::styled(event.received_at.format("%H:%M:%S%.3f ").to_string(), Style::default().fg(Color::DarkGray)),
+                    Span::styled(event.event.clone(), Style::default().fg(Color::Yellow)),
+                ];
+                if let Some(id) = &event.id {
+                    header.push(Span::raw(format!("  id={}", id)));
                 }
+                if let Some(retry) = event.retry {
+                    header.push(Span::raw(format!("  retry={}ms", retry)));
+                }
+
+                std::iter::once(Line::from(header))
+                    .chain(event.data.lines().map(|line| Line::from(format!("  {}", line))))
+            })
+            .collect()
+    }
+
+    fn handle_open_prompt(&mut self, prompt: Prompt) -> Option<Action> {
+        if self.body.is_empty() {
+            self.notice = Some(String::from("No response"));
+            return None;
+        }
+
+        let value = match prompt {
+            Prompt::SaveBody => self.suggested_filename.clone(),
+            Prompt::Charset => self.charset_override.clone().unwrap_or_default(),
+        };
+        self.prompt_textarea = TextArea::new(vec![value]);
+        self.prompt_textarea.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(prompt.title())
+            .border_style(Style::default().fg(Config::get().theme.selected_border)));
+        self.prompt_textarea.move_cursor(tui_textarea::CursorMove::End);
+        self.prompt = Some(prompt);
+        None
+    }
+
+    fn handle_submit_prompt(&mut self, prompt: Prompt) -> Option<Action> {
+        let value = self.prompt_textarea.lines()[0].trim().to_string();
+        match prompt {
+            Prompt::SaveBody => {
+                if value.is_empty() {
+                    return None;
+                }
+                self.notice = Some(match fs::write(&value, &self.body) {
+                    Ok(()) => format!("Saved {} to {}", format_size(self.body.len() as u64), value),
+                    Err(e) => format!("Could not save {}: {}", value, e),
+                });
+            },
+            Prompt::Charset => {
+                if !value.is_empty() && encoding_for_label(&value).is_none() {
+                    self.notice = Some(format!("Unknown charset {}", value));
+                    return None;
+                }
+                self.charset_override = Some(value).filter(|v| !v.is_empty());
+                self.notice = None;
+                self.decode_body();
+            },
+        }
+        self.prompt = None;
+        None
+    }
+
+    fn handle_prompt_key_events(&mut self, prompt: Prompt, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.prompt = None;
+                None
+            },
+            Input { key: Key::Enter, .. } => self.handle_submit_prompt(prompt),
+            input => {
+                self.prompt_textarea.input(input);
+                None
             }
-            Err(_) => Some(Action::Suspend)
+        }
+    }
+
+    fn previous_view(&mut self) -> Option<Action> {
+        self.selected_view = self.selected_view.previous();
+        self.scroll = 0;
+        self.follow = self.is_streaming;
+        None
+    }
+
+    fn next_view(&mut self) -> Option<Action> {
+        self.selected_view = self.selected_view.next();
+        self.scroll = 0;
+        self.follow = self.is_streaming;
+        None
+    }
+}
+
+impl<'a> Default for Response<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Component for Response<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if let Some(prompt) = self.prompt {
+            return self.handle_prompt_key_events(prompt, input)
+        }
+        if self.filter_bar.is_edit_mode {
+            return self.handle_filter_key_events(input)
+        }
+
+        match Config::get().keys.command(Context::Response, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_scroll_down(),
+            Some(Command::Up) => self.handle_scroll_up(),
+            Some(Command::PreviousView) => self.previous_view(),
+            Some(Command::NextView) => self.next_view(),
+            Some(Command::SaveBody) => self.handle_open_prompt(Prompt::SaveBody),
+            Some(Command::Charset) => self.handle_open_prompt(Prompt::Charset),
+            Some(Command::Filter) => self.handle_open_filter(),
+            Some(Command::Follow) => self.handle_follow(),
+            Some(Command::Stop) => Some(Action::StopStream),
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Response)),
+            _ => None
         }
     }
 
@@ -68,11 +561,79 @@ impl Component for Response {
     }
 
     fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
-        let block = main_block(&self.selected, "[3]-Response");
-        let p = Paragraph::new(self.response_value.as_str())
-                    .block(block);
-        frame.render_widget(p, area);
+        let mut block = main_block(&self.selected, "[3]-Response")
+            .title_bottom(self.metadata.as_str())
+            .title_bottom(self.charset_metadata.as_str());
+        if let Some(notice) = &self.notice {
+            block = block.title_bottom(Line::from(format!(" {} ", notice)).right_aligned());
+        }
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(block.inner(area));
+        frame.render_widget(block, area);
+
+        let tabs = Tabs::new(ResponseView::iter().map(ResponseView::title))
+            .highlight_style(Config::get().theme.selected_tab)
+            .select(self.selected_view as usize)
+            .divider("|");
+        frame.render_widget(tabs, layout[0]);
+
+        let layout = if self.filter_bar.is_active() && matches!(self.selected_view, ResponseView::Body) {
+            let body_layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(3)])
+                .split(layout[1]);
+            self.filter_bar.render_frame(frame, body_layout[1]);
+            [layout[0], body_layout[0]]
+        } else {
+            [layout[0], layout[1]]
+        };
+
+        if self.follow {
+            let line_count = match self.selected_view {
+                ResponseView::Body => self.body_value().lines().count(),
+                ResponseView::Raw => self.raw_value.lines().count(),
+                ResponseView::Hex => self.body.len().div_ceil(16),
+                ResponseView::Events => self.events.iter().map(|event| 1 + event.data.lines().count()).sum(),
+                ResponseView::Tests => self.test_results.len() + self.extraction_results.len(),
+                ResponseView::Console => self.console.len(),
+            };
+            self.scroll = line_count.saturating_sub(layout[1].height as usize);
+        }
+
+        let height = layout[1].height;
+        let p = match self.selected_view {
+            ResponseView::Body => Paragraph::new(visible_lines(self.body_value().lines(), self.scroll, height)),
+            ResponseView::Raw => Paragraph::new(visible_lines(self.raw_value.lines(), self.scroll, height)),
+            ResponseView::Hex => {
+                // Only dump the visible lines, bodies can be large
+                let start = self.scroll.saturating_mul(16).min(self.body.len());
+                let end = (start + height as usize * 16).min(self.body.len());
+                Paragraph::new(hex_dump(&self.body[start..end], start))
+            },
+            ResponseView::Events => Paragraph::new(visible_lines(self.event_lines(), self.scroll, height)),
+            ResponseView::Tests => Paragraph::new(visible_lines(self.test_result_lines(), self.scroll, height)),
+            ResponseView::Console => Paragraph::new(visible_lines(self.console_lines(), self.scroll, height)),
+        };
+        frame.render_widget(p, layout[1]);
+
+        if self.prompt.is_some() {
+            let popup_area = centered_rect(60, 20, frame.size());
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(self.prompt_textarea.widget(), popup_area);
+        }
 
         Ok(())
     }
 }
+
+/// The lines in view from line `scroll`, Paragraph can only scroll by u16
+/// lines and a streamed response can be longer
+fn visible_lines<'b>(lines: impl IntoIterator<Item = impl Into<Line<'b>>>, scroll: usize, height: u16) -> Vec<Line<'b>> {
+    lines.into_iter()
+        .skip(scroll)
+        .take(height as usize)
+        .map(Into::into)
+        .collect()
+}
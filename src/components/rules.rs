@@ -0,0 +1,176 @@
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::{Line, Span}, widgets::Paragraph};
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, assertion::parse_assertions, config::Config, extraction::parse_extractions, keymap::{Command, Context}, script};
+
+use super::{body::textarea_with_text, Component};
+
+/// What the lines of a rules editor are
+#[derive(Clone, Copy)]
+pub enum RuleKind {
+    /// Assertions checked against the response
+    Tests,
+    /// Extractions setting variables from the response
+    Extract,
+    /// Rhai script run before the request is sent
+    PreRequest,
+    /// Rhai script run once the response arrived
+    PostResponse,
+}
+
+impl RuleKind {
+    fn example(self) -> &'static str {
+        match self {
+            RuleKind::Tests => "e.g. status == 200 | status in 200..299 | header Content-Type matches json | json $.items[0].id is number | json $.name == \"Jo\" | time < 500 | body contains ok",
+            RuleKind::Extract => "e.g. token = json $.access_token | id = regex \"id\":\\s*(\\d+) | session = header X-Session | sid = cookie SESSIONID",
+            RuleKind::PreRequest => "e.g. request.headers[\"X-Signature\"] = hmac_sha256(vars.secret, request.body); vars.nonce = uuid();",
+            RuleKind::PostResponse => "e.g. test(\"has id\", response.json.id != ()); vars.id = response.json.id; print(response.status);",
+        }
+    }
+
+    fn hint(self) -> (&'static str, String) {
+        let edit = Config::get().keys.keys(Context::Rules, Command::Edit);
+        match self {
+            RuleKind::Tests | RuleKind::Extract => ("One rule per line", format!(" - {} edit, Esc leave, # comment", edit)),
+            RuleKind::PreRequest | RuleKind::PostResponse => ("Rhai script", format!(" - {} edit, Esc leave, // comment, output in the Console view", edit)),
+        }
+    }
+
+    /// Number of valid rules in `text`, or the first error
+    fn count(self, text: &str) -> Result<usize, String> {
+        match self {
+            RuleKind::Tests => parse_assertions(text).map(|assertions| assertions.len()),
+            RuleKind::Extract => parse_extractions(text).map(|extractions| extractions.len()),
+            RuleKind::PreRequest | RuleKind::PostResponse => script::check(text),
+        }
+    }
+
+    fn summary(self, count: usize) -> String {
+        match self {
+            RuleKind::Tests => format!("{} assertion(s), checked after each send", count),
+            RuleKind::Extract => format!("{} variable(s) set after each send, use them as {{{{name}}}}", count),
+            RuleKind::PreRequest => format!("{} line(s), run before each send", count),
+            RuleKind::PostResponse => format!("{} line(s), run after each send", count),
+        }
+    }
+}
+
+/// Editor of rules applied to the response, one per line
+pub struct Rules<'a> {
+    pub selected: bool,
+    pub kind: RuleKind,
+    pub rules_textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+}
+
+impl<'a> Rules<'a> {
+    pub fn new(kind: RuleKind) -> Self {
+        Self::new_with_rules(kind, &[] as &[String])
+    }
+
+    pub fn new_with_rules(kind: RuleKind, rules: &[impl ToString]) -> Self {
+        let text = rules.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::new_with_text(kind, &text)
+    }
+
+    pub fn new_with_text(kind: RuleKind, text: &str) -> Self {
+        Self {
+            selected: false,
+            kind,
+            rules_textarea: textarea_with_text(text),
+            is_edit_mode: false,
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        self.rules_textarea.lines().join("\n")
+    }
+
+    fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.is_edit_mode = false;
+                None
+            },
+            input => {
+                self.rules_textarea.input(input);
+                None
+            }
+        }
+    }
+
+    fn status_line(&self) -> Line<'_> {
+        match self.kind.count(&self.get_text()) {
+            Err(e) => Line::from(Span::styled(e, Style::default().fg(Color::Red))),
+            Ok(0) => Line::from(Span::styled(self.kind.example(), Style::default().fg(Color::DarkGray))),
+            Ok(count) => Line::from(self.kind.summary(count)),
+        }
+    }
+}
+
+impl<'a> Component for Rules<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.is_edit_mode {
+            return self.handle_edit_mode_key_events(input)
+        }
+
+        match Config::get().keys.command(Context::Rules, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::PreviousTab) => Some(Action::TabLeft),
+            Some(Command::NextTab) => Some(Action::TabRight),
+            Some(Command::Edit) => {
+                self.is_edit_mode = true;
+                None
+            },
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Rules)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        self.selected = false;
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+        self.selected = true;
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+
+        let (title, keys) = self.kind.hint();
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(title, Style::default().fg(Color::Yellow)),
+                Span::styled(keys, Style::default().fg(Color::DarkGray)),
+            ])),
+            layout[0]);
+        frame.render_widget(self.rules_textarea.widget(), layout[1]);
+        frame.render_widget(Paragraph::new(self.status_line()), layout[2]);
+        Ok(())
+    }
+}
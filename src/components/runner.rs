@@ -0,0 +1,309 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{Clear, Paragraph}};
+use tui_textarea::Input;
+
+use crate::{action::Action, config::Config, keymap::{Command, Context}, utils::{bytes::format_size, directory::{init_collection_directory_if_not_exist, Directory}, runner::{request_files, result_name, save_reports, Outcome, RequestResult, RunOptions, Summary}, tui_block::main_block, tui_frame_util::centered_rect}};
+
+use super::Component;
+
+/// Choices for the number of requests run at the same time
+const PARALLEL_CHOICES: [usize; 4] = [1, 2, 4, 8];
+
+#[derive(PartialEq)]
+enum RunnerFocus {
+    Folders,
+    Results,
+}
+
+pub struct Runner {
+    pub selected: bool,
+    /// The collection directory and every directory below it
+    folders: Vec<PathBuf>,
+    selected_folder_index: usize,
+    focus: RunnerFocus,
+    pub options: RunOptions,
+    /// Folder of the last run, report names are relative to it
+    run_folder: PathBuf,
+    files: Vec<PathBuf>,
+    /// Result per file, None until the request finished
+    results: Vec<Option<RequestResult>>,
+    selected_result_index: usize,
+    pub is_running: bool,
+    pub notice: Option<String>,
+    pending_run: Option<(Vec<PathBuf>, RunOptions)>,
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self {
+            selected: false,
+            folders: Vec::new(),
+            selected_folder_index: 0,
+            focus: RunnerFocus::Folders,
+            options: RunOptions::default(),
+            run_folder: PathBuf::new(),
+            files: Vec::new(),
+            results: Vec::new(),
+            selected_result_index: 0,
+            is_running: false,
+            notice: None,
+            pending_run: None,
+        }
+    }
+
+    /// The requests and options of the run started by the user
+    pub fn take_run(&mut self) -> Option<(Vec<PathBuf>, RunOptions)> {
+        self.pending_run.take()
+    }
+
+    pub fn add_result(&mut self, index: usize, result: Box<RequestResult>) {
+        if let Some(slot) = self.results.get_mut(index) {
+            *slot = Some(*result);
+        }
+    }
+
+    pub fn finish_run(&mut self) {
+        self.is_running = false;
+        self.notice = Some(Summary::new(self.results.iter().flatten()).to_string());
+    }
+
+    fn load_folders(&mut self) {
+        let root = init_collection_directory_if_not_exist();
+        let mut folders = vec![root.clone()];
+        collect_folders(&root, &mut folders);
+        self.folders = folders;
+        self.selected_folder_index = self.selected_folder_index.min(self.folders.len() - 1);
+    }
+
+    fn handle_run_request(&mut self) -> Option<Action> {
+        if self.is_running {
+            self.notice = Some(String::from("A run is in progress, press x to stop it"));
+            return None;
+        }
+        let folder = self.folders.get(self.selected_folder_index).cloned()?;
+
+        match request_files(&folder) {
+            Ok(files) if !files.is_empty() => {
+                self.results = files.iter().map(|_| None).collect();
+                self.files = files.clone();
+                self.run_folder = folder;
+                self.selected_result_index = 0;
+                self.is_running = true;
+                self.notice = None;
+                self.pending_run = Some((files, self.options.clone()));
+                Some(Action::CollectionRunRequest)
+            },
+            Ok(_) => {
+                self.notice = Some(format!("No requests found in {}", folder.display()));
+                None
+            },
+            Err(e) => {
+                self.notice = Some(format!("{}: {}", folder.display(), e));
+                None
+            },
+        }
+    }
+
+    fn handle_cycle_parallel(&mut self) -> Option<Action> {
+        let index = PARALLEL_CHOICES.iter().position(|choice| *choice == self.options.parallel).map_or(0, |index| index + 1);
+        self.options.parallel = PARALLEL_CHOICES[index % PARALLEL_CHOICES.len()];
+        None
+    }
+
+    fn handle_save_reports(&mut self) -> Option<Action> {
+        if self.is_running || self.results.is_empty() {
+            self.notice = Some(String::from("Reports are saved once a run has finished"));
+            return None;
+        }
+        let results = self.results.iter().flatten().collect::<Vec<_>>();
+        self.notice = Some(match save_reports(&results, &self.run_folder) {
+            Ok(path) => format!("Reports saved to {}", path.with_extension("{xml,json}").display()),
+            Err(e) => format!("Could not save reports: {}", e),
+        });
+        None
+    }
+
+    fn handle_traverse_up_request(&mut self) -> Option<Action> {
+        match self.focus {
+            RunnerFocus::Folders => self.selected_folder_index = self.selected_folder_index.saturating_sub(1),
+            RunnerFocus::Results => self.selected_result_index = self.selected_result_index.saturating_sub(1),
+        }
+        None
+    }
+
+    fn handle_traverse_down_request(&mut self) -> Option<Action> {
+        let (index, len) = match self.focus {
+            RunnerFocus::Folders => (&mut self.selected_folder_index, self.folders.len()),
+            RunnerFocus::Results => (&mut self.selected_result_index, self.results.len()),
+        };
+        if *index + 1 < len {
+            *index += 1;
+        }
+        None
+    }
+
+    fn handle_switch_focus(&mut self) -> Option<Action> {
+        self.focus = match self.focus {
+            RunnerFocus::Folders => RunnerFocus::Results,
+            RunnerFocus::Results => RunnerFocus::Folders,
+        };
+        None
+    }
+
+    fn folder_name(&self, folder: &Path) -> String {
+        let root = Path::new(&Directory::Collection.path()).to_path_buf();
+        match folder.strip_prefix(&root) {
+            Ok(relative) if relative.as_os_str().is_empty() => String::from("(all requests)"),
+            Ok(relative) => format!("{}/", relative.display()),
+            Err(_) => folder.display().to_string(),
+        }
+    }
+
+    fn result_line(&self, index: usize) -> Line<'static> {
+        let name = self.files.get(index)
+            .map(|file| file.strip_prefix(&self.run_folder).unwrap_or(file).display().to_string())
+            .unwrap_or_default();
+        let Some(Some(result)) = self.results.get(index) else {
+            let state = if self.is_running { "...  " } else { "     " };
+            return Line::from(vec![Span::styled(state, Style::default().fg(Color::DarkGray)), Span::raw(name)]);
+        };
+
+        let color = match result.outcome {
+            Outcome::Passed => Color::Green,
+            Outcome::Failed | Outcome::Error => Color::Red,
+            Outcome::Skipped => Color::DarkGray,
+        };
+        let status = result.status().map(|status| status.to_string()).unwrap_or_else(|| String::from("-"));
+        let mut spans = vec![
+            Span::styled(format!("{:<6}", result.outcome.label()), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{:<5}{:>8} {:>10}  ", status, format!("{} ms", result.time.as_millis()), format_size(result.size()))),
+            Span::raw(result_name(result, &self.run_folder)),
+        ];
+        if let Some(message) = &result.message {
+            spans.push(Span::styled(format!("  {}", message), Style::default().fg(Color::DarkGray)));
+        }
+        Line::from(spans)
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn collect_folders(dir: &Path, folders: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut dirs = entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect::<Vec<_>>();
+    dirs.sort();
+    for dir in dirs {
+        folders.push(dir.clone());
+        collect_folders(&dir, folders);
+    }
+}
+
+impl Component for Runner {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        match Config::get().keys.command(Context::Runner, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::SwitchPane) => self.handle_switch_focus(),
+            Some(Command::Run) => self.handle_run_request(),
+            Some(Command::Parallel) => self.handle_cycle_parallel(),
+            Some(Command::StopOnFailure) => {
+                self.options.stop_on_failure = !self.options.stop_on_failure;
+                None
+            },
+            Some(Command::StopRun) => Some(Action::StopCollectionRun),
+            Some(Command::SaveReports) => self.handle_save_reports(),
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Runner)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        self.selected = false;
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+        self.selected = true;
+        self.load_folders();
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let popup_area = centered_rect(80, 80, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(main_block(&self.selected, "Collection runner"), popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1), Constraint::Length(1)])
+            .split(popup_area);
+        let list_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
+            .split(layout[1]);
+
+        let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+        frame.render_widget(Paragraph::new(format!(
+            "Parallel: {}   Stop on failure: {}{}",
+            self.options.parallel,
+            on_off(self.options.stop_on_failure),
+            if self.is_running { "   Running..." } else { "" })), layout[0]);
+
+        let highlight = |line: Line<'static>, is_selected: bool, focus: bool| {
+            if is_selected && focus {
+                line.style(Style::default().bg(Config::get().theme.selected_row))
+            } else {
+                line
+            }
+        };
+        let folder_lines = self.folders.iter()
+            .enumerate()
+            .map(|(index, folder)| highlight(
+                Line::from(self.folder_name(folder)),
+                index == self.selected_folder_index,
+                self.focus == RunnerFocus::Folders))
+            .collect::<Vec<_>>();
+        let visible_height = list_layout[0].height.saturating_sub(2) as usize;
+        frame.render_widget(
+            Paragraph::new(folder_lines)
+                .block(main_block(&(self.focus == RunnerFocus::Folders), "Folders"))
+                .scroll((self.selected_folder_index.saturating_sub(visible_height.saturating_sub(1)) as u16, 0)),
+            list_layout[0]);
+
+        let result_lines = (0..self.results.len())
+            .map(|index| highlight(
+                self.result_line(index),
+                index == self.selected_result_index,
+                self.focus == RunnerFocus::Results))
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(result_lines)
+                .block(main_block(&(self.focus == RunnerFocus::Results), "Results"))
+                .scroll((self.selected_result_index.saturating_sub(visible_height.saturating_sub(1)) as u16, 0)),
+            list_layout[1]);
+
+        let summary = Summary::new(self.results.iter().flatten());
+        let status = self.notice.clone().unwrap_or_else(|| format!("{} of {} finished", summary.total(), self.results.len()));
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::BOLD)),
+            layout[2]);
+        let key = |command| Config::get().keys.keys(Context::Runner, command);
+        let help = format!("{} run folder, {} switch list, {} parallel, {} stop on failure, {} stop, {} save reports, {} close, {} keys",
+            key(Command::Run), key(Command::SwitchPane), key(Command::Parallel), key(Command::StopOnFailure),
+            key(Command::StopRun), key(Command::SaveReports), key(Command::Deselect), key(Command::Help));
+        frame.render_widget(
+            Paragraph::new(help)
+                .style(Style::default().fg(Color::DarkGray)),
+            layout[3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, Paragraph}};
+use strum::IntoEnumIterator;
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, config::Config, keymap::{Command, Context}, request_settings::{RequestSettings, SettingField}, utils::tui_frame_util::centered_rect};
+
+use super::Component;
+
+pub struct Settings<'a> {
+    pub settings: RequestSettings,
+    pub global_settings: RequestSettings,
+    pub selected_field_index: usize,
+    pub value_textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+    pub error: Option<String>,
+}
+
+impl<'a> Settings<'a> {
+    pub fn new() -> Self {
+        Self::new_with_settings(RequestSettings::default())
+    }
+
+    pub fn new_with_settings(settings: RequestSettings) -> Self {
+        let (global_settings, error) = match RequestSettings::load_global() {
+            Ok(global_settings) => (global_settings, None),
+            Err(e) => (RequestSettings::default(), Some(format!("settings.json: {}", e))),
+        };
+
+        Self {
+            settings,
+            global_settings,
+            selected_field_index: 0,
+            value_textarea: TextArea::default(),
+            is_edit_mode: false,
+            error,
+        }
+    }
+
+    /// The request settings with unset values taken from the global settings
+    pub fn get_effective_settings(&self) -> RequestSettings {
+        self.settings.merge(&self.global_settings)
+    }
+
+    fn selected_field(&self) -> SettingField {
+        SettingField::from_repr(self.selected_field_index).unwrap_or(SettingField::ConnectTimeout)
+    }
+
+    fn handle_traverse_up_request(&mut self) -> Option<Action> {
+        self.selected_field_index = self.selected_field_index.saturating_sub(1);
+        None
+    }
+
+    fn handle_traverse_down_request(&mut self) -> Option<Action> {
+        if self.selected_field_index < SettingField::iter().count() - 1 {
+            self.selected_field_index += 1;
+        }
+        None
+    }
+
+    fn handle_edit_mode(&mut self) -> Option<Action> {
+        let mut text_area = TextArea::default();
+        text_area.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} (empty = inherit) ", self.selected_field().label()))
+            .border_style(Style::default().fg(Config::get().theme.selected_border)));
+        text_area.insert_str(self.selected_field().value(&self.settings).unwrap_or_default());
+
+        self.value_textarea = text_area;
+        self.is_edit_mode = true;
+        None
+    }
+
+    fn handle_submit_value(&mut self) -> Option<Action> {
+        let value = self.value_textarea.lines()[0].clone();
+        match self.selected_field().set_value(&mut self.settings, &value) {
+            Ok(()) => {
+                self.error = None;
+                self.is_edit_mode = false;
+            },
+            Err(e) => self.error = Some(e),
+        }
+        None
+    }
+
+    /// Select the next choice of the field, wrapping around to unset
+    fn handle_cycle_value(&mut self) -> Option<Action> {
+        let field = self.selected_field();
+        let choices = field.choices();
+        if choices.is_empty() {
+            return None;
+        }
+
+        let next_value = match field.value(&self.settings) {
+            None => choices[0].clone(),
+            Some(value) => choices.iter()
+                .position(|choice| *choice == value)
+                .and_then(|index| choices.get(index + 1))
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let _ = field.set_value(&mut self.settings, &next_value);
+        None
+    }
+
+    fn handle_clear_value(&mut self) -> Option<Action> {
+        let _ = self.selected_field().set_value(&mut self.settings, "");
+        None
+    }
+
+    fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.is_edit_mode = false;
+                None
+            },
+            Input { key: Key::Enter, .. } => self.handle_submit_value(),
+            input => {
+                self.value_textarea.input(input);
+                None
+            }
+        }
+    }
+}
+
+impl<'a> Default for Settings<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Component for Settings<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.is_edit_mode {
+            return self.handle_edit_mode_key_events(input)
+        }
+
+        match Config::get().keys.command(Context::Settings, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::Edit) => self.handle_edit_mode(),
+            Some(Command::Clear) => self.handle_clear_value(),
+            Some(Command::Cycle) => self.handle_cycle_value(),
+            Some(Command::PreviousTab) => Some(Action::TabLeft),
+            Some(Command::NextTab) => Some(Action::TabRight),
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Settings)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(area);
+
+        let effective_settings = self.get_effective_settings();
+        let lines = SettingField::iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let value = match field.value(&self.settings) {
+                    Some(value) => Span::raw(value),
+                    None => Span::styled(
+                        format!("{} (inherited)", field.value(&effective_settings).unwrap_or(field.default_value())),
+                        Style::default().fg(Color::DarkGray)),
+                };
+                let line = Line::from(vec![Span::raw(format!("{:<30}", field.label())), value]);
+                if index == self.selected_field_index {
+                    line.style(Style::default().bg(Config::get().theme.selected_row))
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let scroll = self.selected_field_index.saturating_sub(layout[0].height.saturating_sub(1) as usize);
+        frame.render_widget(Paragraph::new(lines).scroll((scroll as u16, 0)), layout[0]);
+
+        if let Some(error) = &self.error {
+            frame.render_widget(
+                Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                layout[1]);
+        }
+
+        if self.is_edit_mode {
+            let popup_area = centered_rect(50, 20, frame.size());
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(self.value_textarea.widget(), popup_area);
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use crate::action::Action;
+use crate::config::Config;
 use crate::http_method::HTTPMethod;
+use crate::keymap::{Command, Context};
 use crate::utils::tui_block::main_block;
 use crate::utils::tui_frame_util::centered_rect;
 
@@ -7,7 +9,6 @@ use super::Component;
 use ratatui::layout::Constraint;
 use ratatui::layout::Direction;
 use ratatui::layout::Layout;
-use ratatui::style::Color;
 use ratatui::layout::Rect;
 use ratatui::style::Style;
 use ratatui::widgets::Tabs;
@@ -37,7 +38,7 @@ impl<'a> Url<'a> {
         Self {
             url_text_area: text_area,
             edit_mode: false,
-            http_method: HTTPMethod::POST,
+            http_method: Config::get().default_method.unwrap_or(HTTPMethod::POST),
             selected: false,
         }
     }
@@ -62,7 +63,7 @@ impl<'a> Url<'a> {
         self.url_text_area.lines()[0].as_str()
     }
 
-    pub fn get_method(&mut self) -> HTTPMethod {
+    pub fn get_method(&self) -> HTTPMethod {
         self.http_method
     }
 
@@ -70,7 +71,7 @@ impl<'a> Url<'a> {
         self.url_text_area.set_block(Block::default()
             .borders(Borders::ALL)
             .title(" Editing URL ")
-            .border_style(Style::default().fg(Color::Green)));
+            .border_style(Style::default().fg(Config::get().theme.selected_border)));
         self.edit_mode = true;
         None
     }
@@ -84,19 +85,13 @@ impl<'a> Url<'a> {
         None
     }
 
-    pub fn handle_edit_mode_key_events(&mut self) -> Option<Action> {
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_exit_edit_mode(),
-                    input => {
-                        self.url_text_area.input(input);
-                        None
-                    }
-                }
+    pub fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => self.handle_exit_edit_mode(),
+            input => {
+                self.url_text_area.input(input);
+                None
             }
-            Err(_) => Some(Action::Suspend)
         }
     }
 
@@ -111,46 +106,48 @@ impl<'a> Url<'a> {
     }
 }
 
+impl<'a> Default for Url<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Component for Url<'a> {
-    fn handle_key_events(&mut self) -> Option<Action> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
         if self.edit_mode {
-            return self.handle_edit_mode_key_events()
+            return self.handle_edit_mode_key_events(input)
         }
 
-        let event_result = crossterm::event::read();
-        match event_result {
-            Ok(event) => {
-                match event.into() {
-                    Input { key: Key::Esc, .. } => self.handle_deselect(),
-                    Input { key: Key::Char('e'), .. } => self.handle_edit_mode(),
-                    Input { key: Key::Enter, .. } => self.handle_submit(),                    Input { key: Key::Char('['), .. } => {
-                        self.http_method = self.http_method.prev();
-                        None
-                    },
-                    Input { key: Key::Char(']'), .. } => {
-                        self.http_method = self.http_method.next();
-                        None
-                    },
-                    Input { key: Key::Char('h'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::HistoryRequest)
-                    },
-                    Input { key: Key::Char('1'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window1Request)
-                    },
-                    Input { key: Key::Char('2'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window2Request)
-                    },
-                    Input { key: Key::Char('3'), .. } => {
-                        self.handle_deselect();
-                        Some(Action::Window3Request)
-                    },
-                    _ => None
-                }
-            }
-            Err(_) => Some(Action::Suspend)
+        match Config::get().keys.command(Context::Url, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Edit) => self.handle_edit_mode(),
+            Some(Command::Send) => self.handle_submit(),
+            Some(Command::PreviousMethod) => {
+                self.http_method = self.http_method.prev();
+                None
+            },
+            Some(Command::NextMethod) => {
+                self.http_method = self.http_method.next();
+                None
+            },
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Url)),
+            _ => None
         }
     }
 
@@ -165,11 +162,14 @@ impl<'a> Component for Url<'a> {
 
     fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
         let http_method_lines = HTTPMethod::iter().map(HTTPMethod::line);
-        let block = main_block(&self.selected, "[1]—URL—(press E to edit, enter to submit)");
+        let keys = &Config::get().keys;
+        let title = format!("[1]—URL—(press {} to edit, {} to submit, {} for keys)",
+            keys.keys(Context::Url, Command::Edit), keys.keys(Context::Url, Command::Send), keys.keys(Context::Url, Command::Help));
+        let block = main_block(&self.selected, &title);
 
         Tabs::new(http_method_lines)
             .block(block)
-            .highlight_style(Color::LightYellow)
+            .highlight_style(Config::get().theme.selected_tab)
             .select(self.http_method as usize)
             .divider("|")
             .render(area, frame.buffer_mut());
@@ -0,0 +1,258 @@
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{Block, Borders, Clear, Paragraph}};
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, config::Config, keymap::{Command, Context}, utils::{tui_block::main_block, tui_frame_util::centered_rect}, variables::Variables};
+
+use super::Component;
+
+/// Popup listing the variables used by `{{name}}` placeholders, values can
+/// be added, changed and removed by hand and the environment switched
+pub struct VariableList<'a> {
+    pub selected: bool,
+    variables: Variables,
+    selected_index: usize,
+    input_textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+    /// Name of the variable being edited, None when adding one
+    editing: Option<String>,
+    pub notice: Option<String>,
+    pending_variables: Option<Variables>,
+}
+
+impl<'a> VariableList<'a> {
+    pub fn new() -> Self {
+        Self {
+            selected: false,
+            variables: Variables::default(),
+            selected_index: 0,
+            input_textarea: TextArea::default(),
+            is_edit_mode: false,
+            editing: None,
+            notice: None,
+            pending_variables: None,
+        }
+    }
+
+    pub fn set_variables(&mut self, variables: Variables) {
+        self.variables = variables;
+        self.selected_index = self.selected_index.min(self.variables.values.len().saturating_sub(1));
+    }
+
+    /// The variables as changed by the user, to be saved
+    pub fn take_variables(&mut self) -> Option<Variables> {
+        self.pending_variables.take()
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.variables.values.keys().nth(self.selected_index).cloned()
+    }
+
+    fn start_edit(&mut self, editing: Option<String>) -> Option<Action> {
+        let text = editing.as_ref()
+            .map(|name| format!("{} = {}", name, self.variables.values.get(name).cloned().unwrap_or_default()))
+            .unwrap_or_default();
+        self.input_textarea = TextArea::new(vec![text]);
+        self.input_textarea.move_cursor(tui_textarea::CursorMove::End);
+        self.editing = editing;
+        self.is_edit_mode = true;
+        self.notice = None;
+        None
+    }
+
+    fn handle_edit_request(&mut self) -> Option<Action> {
+        match self.selected_name() {
+            Some(name) => self.start_edit(Some(name)),
+            None => self.start_edit(None),
+        }
+    }
+
+    fn handle_delete_request(&mut self) -> Option<Action> {
+        let name = self.selected_name()?;
+        self.variables.values.remove(&name);
+        self.selected_index = self.selected_index.min(self.variables.values.len().saturating_sub(1));
+        self.notice = Some(format!("Removed {}", name));
+        self.pending_variables = Some(self.variables.clone());
+        Some(Action::VariablesSaveRequest)
+    }
+
+    /// Apply the `name = value` line of the input
+    fn handle_apply_edit(&mut self) -> Option<Action> {
+        let line = self.input_textarea.lines().join("");
+        let Some((name, value)) = line.split_once('=') else {
+            self.notice = Some(String::from("Expected <name> = <value>"));
+            return None;
+        };
+        let name = name.trim();
+        if !Variables::is_valid_name(name) {
+            self.notice = Some(format!("Invalid variable name {}", name));
+            return None;
+        }
+
+        if let Some(previous) = self.editing.take() {
+            self.variables.values.remove(&previous);
+        }
+        self.variables.set(name, value.trim().to_string());
+        self.selected_index = self.variables.values.keys().position(|key| key == name).unwrap_or(0);
+        self.is_edit_mode = false;
+        self.notice = Some(format!("Set {}", name));
+        self.pending_variables = Some(self.variables.clone());
+        Some(Action::VariablesSaveRequest)
+    }
+
+    fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.is_edit_mode = false;
+                self.editing = None;
+                None
+            },
+            Input { key: Key::Enter, .. } => self.handle_apply_edit(),
+            input => {
+                self.input_textarea.input(input);
+                None
+            }
+        }
+    }
+
+    /// Switch to the next environment, after the last one to none
+    fn handle_next_environment_request(&mut self) -> Option<Action> {
+        let environments = Variables::environments();
+        if environments.is_empty() {
+            self.notice = Some(String::from("No environments, import one with lazy_curl import"));
+            return None;
+        }
+        let next = match &self.variables.environment {
+            Some(current) => environments.iter()
+                .position(|name| name == current)
+                .and_then(|index| environments.get(index + 1))
+                .cloned(),
+            None => environments.first().cloned(),
+        };
+        if let Err(e) = self.variables.use_environment(next) {
+            self.notice = Some(format!("Could not load environment: {}", e));
+            return None;
+        }
+        self.notice = Some(match &self.variables.environment {
+            Some(name) => format!("Environment {}", name),
+            None => String::from("No environment"),
+        });
+        self.pending_variables = Some(self.variables.clone());
+        Some(Action::VariablesSaveRequest)
+    }
+
+    fn handle_traverse_up_request(&mut self) -> Option<Action> {
+        self.selected_index = self.selected_index.saturating_sub(1);
+        None
+    }
+
+    fn handle_traverse_down_request(&mut self) -> Option<Action> {
+        if self.selected_index + 1 < self.variables.values.len() {
+            self.selected_index += 1;
+        }
+        None
+    }
+}
+
+impl<'a> Default for VariableList<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Component for VariableList<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.is_edit_mode {
+            return self.handle_edit_mode_key_events(input)
+        }
+        match Config::get().keys.command(Context::Variables, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Down) => self.handle_traverse_down_request(),
+            Some(Command::Up) => self.handle_traverse_up_request(),
+            Some(Command::Edit) => self.handle_edit_request(),
+            Some(Command::Add) => self.start_edit(None),
+            Some(Command::Delete) => self.handle_delete_request(),
+            Some(Command::NextEnvironment) => self.handle_next_environment_request(),
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Variables)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        self.selected = false;
+        self.is_edit_mode = false;
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+        self.selected = true;
+        self.notice = None;
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let popup_area = centered_rect(60, 60, area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(main_block(&self.selected, "Variables"), popup_area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(1), Constraint::Length(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(popup_area);
+
+        let mut lines = if self.variables.values.is_empty() {
+            vec![Line::styled("No variables, press a to add one or set them from the Extract tab", Style::default().fg(Color::DarkGray))]
+        } else {
+            self.variables.values.iter()
+                .enumerate()
+                .map(|(index, (name, value))| {
+                    let line = Line::from(vec![
+                        Span::styled(name.clone(), Style::default().fg(Color::Cyan)),
+                        Span::raw(format!(" = {}", value)),
+                    ]);
+                    if index == self.selected_index && !self.is_edit_mode {
+                        line.style(Style::default().bg(Config::get().theme.selected_row))
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+        // Values of the open .http file take precedence over the ones above,
+        // values of the environment are only used for names not set above
+        lines.extend(self.variables.file_values.iter()
+            .map(|(name, value)| Line::styled(format!("{} = {} (file)", name, value), Style::default().fg(Color::DarkGray))));
+        if let Some(environment) = &self.variables.environment {
+            lines.extend(self.variables.environment_values.iter()
+                .filter(|(name, _)| !self.variables.values.contains_key(*name) && !self.variables.file_values.contains_key(*name))
+                .map(|(name, value)| Line::styled(format!("{} = {} ({})", name, value, environment), Style::default().fg(Color::DarkGray))));
+        }
+        let visible_height = layout[0].height as usize;
+        frame.render_widget(
+            Paragraph::new(lines).scroll((self.selected_index.saturating_sub(visible_height.saturating_sub(1)) as u16, 0)),
+            layout[0]);
+
+        let border_style = if self.is_edit_mode { Style::default().fg(Config::get().theme.selected_border) } else { Style::default() };
+        self.input_textarea.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(" name = value ")
+            .border_style(border_style));
+        frame.render_widget(self.input_textarea.widget(), layout[1]);
+
+        frame.render_widget(
+            Paragraph::new(self.notice.clone().unwrap_or_else(|| format!("{} variable(s), environment {}, used as {{{{name}}}} in requests",
+                    self.variables.values.len(),
+                    self.variables.environment.as_deref().unwrap_or("none"))))
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+            layout[2]);
+        let key = |command| Config::get().keys.keys(Context::Variables, command);
+        let help = if self.is_edit_mode {
+            String::from("Enter apply, Esc cancel")
+        } else {
+            format!("{} edit, {} add, {} delete, {} next environment, {} close, {} keys",
+                key(Command::Edit), key(Command::Add), key(Command::Delete), key(Command::NextEnvironment), key(Command::Deselect), key(Command::Help))
+        };
+        frame.render_widget(Paragraph::new(help).style(Style::default().fg(Color::DarkGray)), layout[3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::{Line, Span}, widgets::{Block, Borders, Paragraph}};
+use tui_textarea::{Input, Key, TextArea};
+
+use crate::{action::Action, config::Config, keymap::{Command, Context}, utils::{tui_block::main_block, websocket_service::WebSocketCommand}, websocket::{from_hex, WebSocketDirection, WebSocketFrame}};
+
+use super::Component;
+
+pub struct WebSocket<'a> {
+    pub selected: bool,
+    pub frames: Vec<WebSocketFrame>,
+    pub composer_textarea: TextArea<'a>,
+    pub is_edit_mode: bool,
+    pub is_binary_mode: bool,
+    pub is_connected: bool,
+    pub scroll: u16,
+    pub follow: bool,
+    pub notice: Option<String>,
+    pending_command: Option<WebSocketCommand>,
+}
+
+impl<'a> WebSocket<'a> {
+    pub fn new() -> Self {
+        Self::new_with_frames(Vec::new())
+    }
+
+    pub fn new_with_frames(frames: Vec<WebSocketFrame>) -> Self {
+        Self {
+            selected: false,
+            frames,
+            composer_textarea: TextArea::default(),
+            is_edit_mode: false,
+            is_binary_mode: false,
+            is_connected: false,
+            scroll: 0,
+            follow: true,
+            notice: None,
+            pending_command: None,
+        }
+    }
+
+    /// Clear the log of the previous session
+    pub fn start_session(&mut self) {
+        self.frames = Vec::new();
+        self.is_connected = true;
+        self.follow = true;
+        self.notice = None;
+    }
+
+    pub fn end_session(&mut self) {
+        self.is_connected = false;
+    }
+
+    pub fn add_frame(&mut self, frame: WebSocketFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn take_command(&mut self) -> Option<WebSocketCommand> {
+        self.pending_command.take()
+    }
+
+    fn request_command(&mut self, command: WebSocketCommand) -> Option<Action> {
+        if !self.is_connected {
+            self.notice = Some(String::from("Not connected, press Enter in the URL window to connect"));
+            return None;
+        }
+        self.pending_command = Some(command);
+        Some(Action::WebSocketCommandRequest)
+    }
+
+    fn handle_send_message(&mut self) -> Option<Action> {
+        let message = self.composer_textarea.lines().join("\n");
+        let command = if self.is_binary_mode {
+            match from_hex(&message) {
+                Ok(bytes) => WebSocketCommand::Binary(bytes),
+                Err(e) => {
+                    self.notice = Some(e);
+                    return None;
+                },
+            }
+        } else {
+            WebSocketCommand::Text(message)
+        };
+        self.notice = None;
+        self.request_command(command)
+    }
+
+    fn handle_toggle_binary_mode(&mut self) -> Option<Action> {
+        self.is_binary_mode = !self.is_binary_mode;
+        None
+    }
+
+    fn handle_scroll_down(&mut self) -> Option<Action> {
+        self.scroll = self.scroll.saturating_add(1);
+        self.follow = false;
+        None
+    }
+
+    fn handle_scroll_up(&mut self) -> Option<Action> {
+        self.scroll = self.scroll.saturating_sub(1);
+        self.follow = false;
+        None
+    }
+
+    fn handle_edit_mode_key_events(&mut self, input: Input) -> Option<Action> {
+        match input {
+            Input { key: Key::Esc, .. } => {
+                self.is_edit_mode = false;
+                None
+            },
+            input => {
+                self.composer_textarea.input(input);
+                None
+            }
+        }
+    }
+
+    fn frame_lines(&self) -> Vec<Line<'_>> {
+        self.frames.iter()
+            .map(|frame| {
+                let style = match frame.direction {
+                    WebSocketDirection::Sent => Style::default().fg(Color::Green),
+                    WebSocketDirection::Received => Style::default().fg(Color::Cyan),
+                    WebSocketDirection::Info => Style::default().fg(Color::DarkGray),
+                };
+                Line::from(vec![
+                    Span::styled(frame.timestamp.format("%H:%M:%S%.3f ").to_string(), Style::default().fg(Color::DarkGray)),
+                    Span::styled(format!("{} {:<6} ", frame.direction, frame.kind), style),
+                    Span::raw(frame.payload.replace('\n', "\\n")),
+                ])
+            })
+            .collect()
+    }
+}
+
+impl<'a> Default for WebSocket<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Component for WebSocket<'a> {
+    fn handle_key_events(&mut self, input: Input) -> Option<Action> {
+        if self.is_edit_mode {
+            return self.handle_edit_mode_key_events(input)
+        }
+
+        match Config::get().keys.command(Context::Websocket, &input) {
+            Some(Command::Deselect) => self.handle_deselect(),
+            Some(Command::Edit) => {
+                self.is_edit_mode = true;
+                None
+            },
+            Some(Command::SendMessage) => self.handle_send_message(),
+            Some(Command::ToggleBinary) => self.handle_toggle_binary_mode(),
+            Some(Command::Ping) => self.request_command(WebSocketCommand::Ping),
+            Some(Command::Close) => self.request_command(WebSocketCommand::Close),
+            Some(Command::SaveSession) => Some(Action::WebSocketSaveRequest),
+            Some(Command::Down) => self.handle_scroll_down(),
+            Some(Command::Up) => self.handle_scroll_up(),
+            Some(Command::Follow) => {
+                self.follow = true;
+                None
+            },
+            Some(Command::History) => {
+                self.handle_deselect();
+                Some(Action::HistoryRequest)
+            },
+            Some(Command::Url) => {
+                self.handle_deselect();
+                Some(Action::Window1Request)
+            },
+            Some(Command::Parameters) => {
+                self.handle_deselect();
+                Some(Action::Window2Request)
+            },
+            Some(Command::Response) => {
+                self.handle_deselect();
+                Some(Action::Window3Request)
+            },
+            Some(Command::Help) => Some(Action::HelpRequest(Context::Websocket)),
+            _ => None
+        }
+    }
+
+    fn handle_deselect(&mut self) -> Option<Action> {
+        self.selected = false;
+        Some(Action::Suspend)
+    }
+
+    fn handle_select(&mut self) {
+        self.selected = true;
+    }
+
+    fn render_frame(&mut self, frame: &mut ratatui::prelude::Frame<'_>, area: Rect) -> std::io::Result<()> {
+        let status = if self.is_connected { " connected " } else { " disconnected " };
+        let mut block = main_block(&self.selected, "[3]-WebSocket")
+            .title_bottom(status);
+        if let Some(notice) = &self.notice {
+            block = block.title_bottom(Line::from(format!(" {} ", notice)).right_aligned());
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(3)])
+            .split(block.inner(area));
+        frame.render_widget(block, area);
+
+        if self.follow {
+            self.scroll = self.frames.len().saturating_sub(layout[0].height as usize) as u16;
+        }
+        frame.render_widget(Paragraph::new(self.frame_lines()).scroll((self.scroll, 0)), layout[0]);
+
+        let mode = if self.is_binary_mode { "binary, hex bytes" } else { "text" };
+        let key = |command| Config::get().keys.keys(Context::Websocket, command);
+        let border_style = if self.is_edit_mode { Style::default().fg(Config::get().theme.selected_border) } else { Style::default() };
+        self.composer_textarea.set_block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Message ({}) - {} edit, {} send, {} mode, {} ping, {} close, {} save ", mode,
+                key(Command::Edit), key(Command::SendMessage), key(Command::ToggleBinary), key(Command::Ping), key(Command::Close), key(Command::SaveSession)))
+            .border_style(border_style));
+        frame.render_widget(self.composer_textarea.widget(), layout[1]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, str::FromStr, sync::OnceLock};
+
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+use crate::{http_method::HTTPMethod, keymap::Keymap, request_settings::RequestSettings, utils::{charset::encoding_for_label, directory::Directory}};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings of lazycurl itself, read from `config.toml` in the config
+/// directory when it starts. Every value is optional.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Method of the request lazycurl starts with
+    pub default_method: Option<HTTPMethod>,
+    /// Transfer settings used when neither the request nor `settings.json` set them
+    pub request: RequestSettings,
+    /// Headers sent with every HTTP request that does not set them itself
+    pub headers: BTreeMap<String, String>,
+    pub history: HistoryConfig,
+    pub layout: LayoutConfig,
+    pub theme: ThemeConfig,
+    /// Keys of the main screen and the windows, by context
+    pub keys: Keymap,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// The oldest entries beyond this number are deleted, None keeps all
+    pub max_entries: Option<usize>,
+}
+
+/// Sizes of the windows in percent, the Response window takes the height
+/// left below the URL and Parameters windows
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub history_width: u16,
+    pub url_height: u16,
+    pub parameters_height: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self { history_width: 20, url_height: 10, parameters_height: 30 }
+    }
+}
+
+/// Colors by name (`green`, `lightblue`), index (`42`) or hex (`#ff8800`)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Border of the selected window or editor
+    #[serde(deserialize_with = "color")]
+    pub selected_border: Color,
+    /// Background of the selected line of lists
+    #[serde(deserialize_with = "color")]
+    pub selected_row: Color,
+    /// Selected tab and method
+    #[serde(deserialize_with = "color")]
+    pub selected_tab: Color,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self { selected_border: Color::Green, selected_row: Color::Blue, selected_tab: Color::Yellow }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Color::from_str(&name).map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", name)))
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        Path::new(&Directory::Config.path()).join("config.toml")
+    }
+
+    /// Read and check `config.toml`, a missing file is the default
+    /// configuration. Errors name the file and every invalid value.
+    pub fn load() -> Result<Config, String> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let contents = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            return Err(format!("{}:\n  {}", path.display(), errors.join("\n  ")));
+        }
+        Ok(config)
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let layout = &self.layout;
+        if !(1..100).contains(&layout.history_width) {
+            errors.push(format!("layout.history_width is {}, expected 1 to 99", layout.history_width));
+        }
+        for (name, height) in [("url_height", layout.url_height), ("parameters_height", layout.parameters_height)] {
+            if !(1..100).contains(&height) {
+                errors.push(format!("layout.{} is {}, expected 1 to 99", name, height));
+            }
+        }
+        if u32::from(layout.url_height) + u32::from(layout.parameters_height) >= 100 {
+            errors.push(format!(
+                "layout.url_height and layout.parameters_height are {} and {}, expected less than 100 together to leave room for the response",
+                layout.url_height, layout.parameters_height));
+        }
+
+        errors.extend(self.keys.validate());
+
+        for (name, value) in &self.headers {
+            if name.trim().is_empty() || name.contains(':') || name.chars().any(char::is_whitespace) {
+                errors.push(format!("headers: '{}' is not a header name", name));
+            }
+            if value.contains(['\r', '\n']) {
+                errors.push(format!("headers.{}: values cannot span lines", name));
+            }
+        }
+        if let Some(charset) = &self.request.charset {
+            if encoding_for_label(charset).is_none() {
+                errors.push(format!("request.charset: unknown charset '{}'", charset));
+            }
+        }
+        if self.history.max_entries == Some(0) {
+            errors.push(String::from("history.max_entries is 0, leave it out to keep every entry"));
+        }
+        errors
+    }
+
+    /// Make `config` the one returned by `get`, before the first `get`
+    pub fn init(config: Config) {
+        let _ = CONFIG.set(config);
+    }
+
+    /// The configuration read at start, or the default one
+    pub fn get() -> &'static Config {
+        CONFIG.get_or_init(Config::default)
+    }
+}
@@ -0,0 +1,152 @@
+use std::{backtrace::Backtrace, fmt::Write, fs::File, io::{self, Write as _}, path::PathBuf, sync::Mutex};
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::{lazycurl_file::LazyCurlFile, utils::directory::init_crashes_directory_if_not_exist};
+
+const REDACTED: &str = "[redacted]";
+
+/// Parts of header, query parameter, form field and JSON key names whose
+/// values are left out of crash reports
+const SECRET_NAMES: [&str; 10] = ["auth", "token", "secret", "password", "passwd", "cookie", "key", "session", "signature", "credential"];
+
+/// The request last opened or sent
+static REQUEST: Mutex<Option<LazyCurlFile>> = Mutex::new(None);
+
+/// Keep the request opened or sent for a crash report, it is not updated
+/// while the request is edited
+pub fn set_request(lazycurl_file: LazyCurlFile) {
+    if let Ok(mut request) = REQUEST.lock() {
+        *request = Some(lazycurl_file);
+    }
+}
+
+/// Write the panic, its backtrace and the request last opened or sent, with
+/// the values of secrets replaced, to the `crashes` directory
+pub fn write(panic: &str) -> io::Result<PathBuf> {
+    let now = Utc::now();
+    let mut report = format!("lazycurl {} crashed at {}\n\n{}\n\n", env!("CARGO_PKG_VERSION"), now.to_rfc3339(), panic);
+    let _ = write!(report, "Backtrace:\n{}\n", Backtrace::force_capture());
+
+    // The panic may have happened while the request was set
+    let request = REQUEST.try_lock().ok().and_then(|request| request.clone());
+    match request {
+        Some(lazycurl_file) => {
+            let _ = write!(report, "\nRequest (secrets redacted):\n{}", redacted_request(&lazycurl_file));
+        },
+        None => report.push_str("\nNo request\n"),
+    }
+
+    // Crashes within the same second get a numbered report each
+    let directory = init_crashes_directory_if_not_exist();
+    let timestamp = now.format("%Y-%m-%dT%H-%M-%SZ");
+    let mut count = 1;
+    loop {
+        let path = match count {
+            1 => directory.join(format!("crash-{}.txt", timestamp)),
+            _ => directory.join(format!("crash-{}_{}.txt", timestamp, count)),
+        };
+        match File::options().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(report.as_bytes())?;
+                return Ok(path);
+            },
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => count += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+fn redacted_request(lazycurl_file: &LazyCurlFile) -> String {
+    let mut request = format!("{} {}\n", lazycurl_file.http_method, redact_url(&lazycurl_file.url));
+    for header in &lazycurl_file.headers {
+        match header.split_once(':') {
+            Some((name, _)) if is_secret(name) => request.push_str(&format!("{}: {}\n", name, REDACTED)),
+            _ => request.push_str(&format!("{}\n", header)),
+        }
+    }
+    if let Ok(settings) = serde_json::to_string(&lazycurl_file.settings) {
+        request.push_str(&format!("Settings: {}\n", settings));
+    }
+    if let Some(graphql) = &lazycurl_file.graphql {
+        request.push_str(&format!("\n{}\n", graphql.query));
+        if !graphql.variables.trim().is_empty() {
+            request.push_str(&format!("\n{}\n", redact_body(&graphql.variables)));
+        }
+    } else if !lazycurl_file.body.is_empty() {
+        request.push_str(&format!("\n{}\n", redact_body(&lazycurl_file.body)));
+    }
+    if !lazycurl_file.pre_request_script.trim().is_empty() || !lazycurl_file.post_response_script.trim().is_empty() {
+        request.push_str("\nScripts left out\n");
+    }
+    request
+}
+
+/// Drop the password of the URL and the values of secret query parameters
+fn redact_url(url: &str) -> String {
+    let (url, query) = match url.split_once('?') {
+        Some((url, query)) => (url, Some(query)),
+        None => (url, None),
+    };
+    let url = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            match authority.rsplit_once('@').and_then(|(user_info, host)| Some((user_info.split_once(':')?.0, host))) {
+                Some((user, host)) => format!("{}://{}:{}@{}{}", scheme, user, REDACTED, host, path),
+                None => url.to_string(),
+            }
+        },
+        None => url.to_string(),
+    };
+    match query {
+        Some(query) => format!("{}?{}", url, redact_pairs(query)),
+        None => url,
+    }
+}
+
+/// JSON values and form fields of secret names, other bodies are kept
+fn redact_body(body: &str) -> String {
+    if let Ok(mut json) = serde_json::from_str::<Value>(body) {
+        redact_json(&mut json);
+        return serde_json::to_string_pretty(&json).unwrap_or_default();
+    }
+    let trimmed = body.trim();
+    let is_form = !trimmed.contains(char::is_whitespace)
+        && trimmed.split('&').all(|pair| pair.contains('='));
+    if is_form {
+        return redact_pairs(trimmed);
+    }
+    body.to_string()
+}
+
+fn redact_pairs(pairs: &str) -> String {
+    pairs.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret(name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if is_secret(name) {
+                    *value = Value::String(String::from(REDACTED));
+                } else {
+                    redact_json(value);
+                }
+            }
+        },
+        Value::Array(values) => values.iter_mut().for_each(redact_json),
+        _ => (),
+    }
+}
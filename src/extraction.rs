@@ -0,0 +1,135 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{assertion::{parse_pattern, split_word}, utils::{curl_service::CurlResponse, jsonpath}, variables::Variables};
+
+/// Where the value of a variable is taken from in the response
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum ExtractionSource {
+    Json { path: String },
+    /// The first capture group of the pattern in the body, or the whole match
+    Regex { pattern: String },
+    Header { name: String },
+    /// Value of a cookie set with `Set-Cookie`
+    Cookie { name: String },
+}
+
+/// Sets a variable from the response. Saved as JSON with the request and
+/// written one per line in the Extract tab, e.g. `token = json $.access_token`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Extraction {
+    pub variable: String,
+    #[serde(flatten)]
+    pub source: ExtractionSource,
+}
+
+pub struct ExtractionResult {
+    pub variable: String,
+    /// The extracted value, or why there is none
+    pub value: Result<String, String>,
+}
+
+impl fmt::Display for ExtractionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Ok(value) => write!(f, "→ {} = {}", self.variable, value),
+            Err(reason) => write!(f, "→ {} not set, {}", self.variable, reason),
+        }
+    }
+}
+
+impl Extraction {
+    /// `json` is the body parsed as JSON, or the messages of a gRPC call
+    pub fn extract(&self, response: &CurlResponse, json: Option<&Value>) -> ExtractionResult {
+        let value = match &self.source {
+            ExtractionSource::Json { path } => json
+                .ok_or_else(|| String::from("the body is not JSON"))
+                .and_then(|json| jsonpath::query(path, json))
+                .and_then(|value| value.ok_or_else(|| String::from("nothing at the path")))
+                .map(|value| jsonpath::value_text(&value)),
+            ExtractionSource::Regex { pattern } => Regex::new(pattern)
+                .map_err(|_| String::from("invalid pattern"))
+                .and_then(|regex| {
+                    let body = String::from_utf8_lossy(&response.body);
+                    regex.captures(&body)
+                        .map(|captures| captures.get(1).or(captures.get(0)).map_or(String::new(), |found| found.as_str().to_string()))
+                        .ok_or_else(|| String::from("no match in the body"))
+                }),
+            ExtractionSource::Header { name } => response.header(name)
+                .map(String::from)
+                .ok_or_else(|| String::from("no such header")),
+            ExtractionSource::Cookie { name } => cookie(response, name)
+                .ok_or_else(|| String::from("no such cookie")),
+        };
+        ExtractionResult { variable: self.variable.clone(), value }
+    }
+}
+
+/// Extract every variable and store the ones found in `variables`
+pub fn extract(extractions: &[Extraction], response: &CurlResponse, json: Option<&Value>, variables: &mut Variables) -> Vec<ExtractionResult> {
+    let results = extractions.iter()
+        .map(|extraction| extraction.extract(response, json))
+        .collect::<Vec<_>>();
+    for result in &results {
+        if let Ok(value) = &result.value {
+            variables.set(&result.variable, value.clone());
+        }
+    }
+    results
+}
+
+/// Parse the lines of the Extract tab, skipping blank lines and `#` comments.
+/// Errors carry the line number.
+pub fn parse_extractions(text: &str) -> Result<Vec<Extraction>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(|(index, line)| line.parse().map_err(|e| format!("Line {}: {}", index + 1, e)))
+        .collect()
+}
+
+fn cookie(response: &CurlResponse, name: &str) -> Option<String> {
+    response.header_values("Set-Cookie").into_iter()
+        .filter_map(|value| value.split(';').next()?.trim().split_once('='))
+        .find(|(cookie_name, _)| cookie_name.trim() == name)
+        .map(|(_, value)| value.trim().to_string())
+}
+
+impl FromStr for Extraction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (variable, rest) = line.split_once('=').ok_or("Expected <variable> = <json|regex|header|cookie> ...")?;
+        let variable = variable.trim();
+        if !Variables::is_valid_name(variable) {
+            return Err(format!("Invalid variable name {}", variable));
+        }
+
+        let source = match split_word(rest) {
+            ("json", path) => {
+                jsonpath::select(path, &Value::Null)?;
+                ExtractionSource::Json { path: path.to_string() }
+            },
+            ("regex", pattern) if !pattern.is_empty() => ExtractionSource::Regex { pattern: parse_pattern(pattern)? },
+            ("header", name) if !name.is_empty() => ExtractionSource::Header { name: name.to_string() },
+            ("cookie", name) if !name.is_empty() => ExtractionSource::Cookie { name: name.to_string() },
+            _ => return Err(String::from("Expected json <path>, regex <pattern>, header <name> or cookie <name>")),
+        };
+        Ok(Extraction { variable: variable.to_string(), source })
+    }
+}
+
+impl fmt::Display for Extraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            ExtractionSource::Json { path } => write!(f, "{} = json {}", self.variable, path),
+            ExtractionSource::Regex { pattern } => write!(f, "{} = regex {}", self.variable, pattern),
+            ExtractionSource::Header { name } => write!(f, "{} = header {}", self.variable, name),
+            ExtractionSource::Cookie { name } => write!(f, "{} = cookie {}", self.variable, name),
+        }
+    }
+}
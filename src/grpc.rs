@@ -0,0 +1,25 @@
+use serde::{Serialize, Deserialize};
+
+/// The gRPC method a request calls and where its service definitions come
+/// from. The request message is the JSON body of the request.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GrpcRequest {
+    /// `.proto` files to compile, server reflection is used when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proto_files: Vec<String>,
+    /// Directories imports are resolved against, defaults to the directories of the proto files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub import_paths: Vec<String>,
+    /// Fully qualified service name, e.g. `helloworld.Greeter`
+    #[serde(default)]
+    pub service: String,
+    #[serde(default)]
+    pub method: String,
+}
+
+impl GrpcRequest {
+    /// Path of the method on the server, e.g. `helloworld.Greeter/SayHello`
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.service, self.method)
+    }
+}
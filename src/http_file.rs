@@ -0,0 +1,417 @@
+use std::{fs, path::{Path, PathBuf}, str::FromStr};
+
+use crate::{http_method::HTTPMethod, lazycurl_file::LazyCurlFile, utils::graphql::add_json_content_type, variables::Variables};
+
+/// A `.http` or `.rest` file of the VS Code REST Client and the JetBrains
+/// HTTP Client: requests separated by `###` lines, `@name = value` variables
+/// and `#` or `//` comments. Requests that are not changed are written back
+/// exactly as they were read.
+pub struct HttpFile {
+    pub path: PathBuf,
+    blocks: Vec<Block>,
+}
+
+/// The lines from one `###` separator to the next
+struct Block {
+    /// Text of the block as read, or as written by the last save
+    text: String,
+    /// The `###` line, None for the lines before the first separator
+    separator: Option<String>,
+    /// Comment lines before the request line, including `# @name`
+    comments: Vec<String>,
+    variables: Vec<(String, String)>,
+    request: Option<HttpRequest>,
+    /// Response handler and redirection lines after the body, `> {% ... %}`
+    /// and `>> file`, lazycurl does not run them
+    handler: Vec<String>,
+}
+
+pub struct HttpRequest {
+    /// Name from the `###` line or a `# @name` comment
+    pub name: Option<String>,
+    /// The request, or why it cannot be opened
+    pub lazycurl_file: Result<LazyCurlFile, String>,
+}
+
+enum Section {
+    Start,
+    Headers,
+    Body,
+    Handler,
+}
+
+impl HttpFile {
+    pub fn is_http_file(path: &Path) -> bool {
+        path.extension().is_some_and(|extension| extension == "http" || extension == "rest")
+    }
+
+    /// Read the file, a file that does not exist yet is opened empty and
+    /// created by the first save
+    pub fn load(path: &Path) -> Result<HttpFile, Box<dyn std::error::Error>> {
+        if !Self::is_http_file(path) {
+            return Err("not a .http or .rest file".into());
+        }
+        let text = if path.exists() { fs::read_to_string(path)? } else { String::new() };
+        Ok(Self::parse(path, &text))
+    }
+
+    pub fn parse(path: &Path, text: &str) -> HttpFile {
+        let mut blocks = Vec::new();
+        let mut lines = Vec::new();
+        for line in text.split_inclusive('\n') {
+            if line.trim_start().starts_with("###") && !lines.is_empty() {
+                blocks.push(Block::parse(std::mem::take(&mut lines)));
+            }
+            lines.push(line);
+        }
+        if !lines.is_empty() {
+            blocks.push(Block::parse(lines));
+        }
+        HttpFile { path: path.to_path_buf(), blocks }
+    }
+
+    pub fn requests(&self) -> impl Iterator<Item = &HttpRequest> {
+        self.blocks.iter().filter_map(|block| block.request.as_ref())
+    }
+
+    /// The request at `index` of `requests`
+    pub fn request(&self, index: usize) -> Result<LazyCurlFile, String> {
+        self.requests().nth(index)
+            .ok_or_else(|| format!("{} has no request {}", self.path.display(), index + 1))?
+            .lazycurl_file.clone()
+    }
+
+    /// The `@name = value` definitions of every block, in file order
+    pub fn variables(&self) -> Vec<(String, String)> {
+        self.blocks.iter().flat_map(|block| block.variables.clone()).collect()
+    }
+
+    /// Replace the request at `index`, or add it at the end, and write the
+    /// file. Only the method, URL, headers and body are kept. Returns the
+    /// index of the request.
+    pub fn save_request(&mut self, index: Option<usize>, lazycurl_file: &LazyCurlFile) -> Result<usize, Box<dyn std::error::Error>> {
+        if matches!(lazycurl_file.http_method, HTTPMethod::GRPC | HTTPMethod::GRPCWEB) {
+            return Err("gRPC calls cannot be saved to .http files".into());
+        }
+        let block_index = index.and_then(|index| self.blocks.iter()
+            .enumerate()
+            .filter(|(_, block)| block.request.is_some())
+            .nth(index)
+            .map(|(block_index, _)| block_index));
+        let index = match block_index {
+            Some(block_index) => {
+                let block = &mut self.blocks[block_index];
+                if let Some(request) = &mut block.request {
+                    request.lazycurl_file = Ok(lazycurl_file.clone());
+                }
+                block.text = block.render();
+                index.unwrap_or_default()
+            },
+            None => {
+                if let Some(last) = self.blocks.last_mut() {
+                    if !last.text.ends_with('\n') {
+                        last.text.push('\n');
+                    }
+                    if !last.text.ends_with("\n\n") {
+                        last.text.push('\n');
+                    }
+                }
+                let mut block = Block {
+                    text: String::new(),
+                    separator: Some(String::from("###")),
+                    comments: Vec::new(),
+                    variables: Vec::new(),
+                    request: Some(HttpRequest { name: None, lazycurl_file: Ok(lazycurl_file.clone()) }),
+                    handler: Vec::new(),
+                };
+                block.text = block.render();
+                self.blocks.push(block);
+                self.requests().count() - 1
+            },
+        };
+        fs::write(&self.path, self.blocks.iter().map(|block| block.text.as_str()).collect::<String>())?;
+        Ok(index)
+    }
+
+    /// Path naming the request at `index` of a `.http` file, so requests of
+    /// one file can be run and reported like request files
+    pub fn request_path(path: &Path, index: usize) -> PathBuf {
+        PathBuf::from(format!("{}#{}", path.display(), index + 1))
+    }
+
+    /// The file and request index of a path made by `request_path`
+    pub fn split_request_path(path: &Path) -> Option<(PathBuf, usize)> {
+        let text = path.to_str()?;
+        let (file, number) = text.rsplit_once('#')?;
+        let number = number.parse::<usize>().ok().filter(|number| *number > 0)?;
+        let file = PathBuf::from(file);
+        Self::is_http_file(&file).then_some((file, number - 1))
+    }
+
+    /// The requests of the file as paths made by `request_path`
+    pub fn request_paths(path: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let http_file = Self::load(path)?;
+        Ok((0..http_file.requests().count()).map(|index| Self::request_path(path, index)).collect())
+    }
+
+    /// Load the request of a path made by `request_path`, with the variables
+    /// of the file filled in
+    pub fn load_request(path: &Path) -> Result<LazyCurlFile, Box<dyn std::error::Error>> {
+        let (file, index) = Self::split_request_path(path).ok_or("not a request of a .http file")?;
+        let http_file = Self::load(&file)?;
+        let lazycurl_file = http_file.request(index)?;
+        let mut variables = Variables::default();
+        variables.use_file_variables(&http_file.variables());
+        Ok(lazycurl_file.with_variables(&variables))
+    }
+}
+
+impl Block {
+    fn parse(lines: Vec<&str>) -> Block {
+        let text = lines.concat();
+        let mut block = Block {
+            text,
+            separator: None,
+            comments: Vec::new(),
+            variables: Vec::new(),
+            request: None,
+            handler: Vec::new(),
+        };
+        let mut name = None;
+        let mut lines = lines.iter().map(|line| line.trim_end_matches(['\r', '\n'])).peekable();
+        if let Some(separator) = lines.next_if(|line| line.trim_start().starts_with("###")) {
+            let separator_name = separator.trim_start().trim_start_matches('#').trim();
+            name = (!separator_name.is_empty()).then(|| separator_name.to_string());
+            block.separator = Some(separator.to_string());
+        }
+
+        let mut section = Section::Start;
+        let mut request_line = None;
+        let mut headers = Vec::new();
+        let mut body = Vec::new();
+        for line in lines {
+            let trimmed = line.trim();
+            match section {
+                Section::Start if trimmed.is_empty() => (),
+                Section::Start if is_comment(trimmed) => {
+                    let comment = trimmed.trim_start_matches(['#', '/']).trim();
+                    if let Some(comment_name) = comment.strip_prefix("@name") {
+                        name = Some(comment_name.trim().trim_start_matches('=').trim().to_string());
+                    }
+                    block.comments.push(line.to_string());
+                },
+                Section::Start if trimmed.starts_with('@') && trimmed.contains('=') => {
+                    if let Some((variable, value)) = trimmed[1..].split_once('=') {
+                        block.variables.push((variable.trim().to_string(), value.trim().to_string()));
+                    }
+                },
+                Section::Start => {
+                    request_line = Some(trimmed.to_string());
+                    section = Section::Headers;
+                },
+                // Long query strings may go on the lines below the request line
+                Section::Headers if headers.is_empty() && (trimmed.starts_with('?') || trimmed.starts_with('&')) => {
+                    if let Some(request_line) = &mut request_line {
+                        request_line.push_str(trimmed);
+                    }
+                },
+                Section::Headers if trimmed.is_empty() => section = Section::Body,
+                Section::Headers if is_comment(trimmed) => (),
+                Section::Headers => headers.push(trimmed.to_string()),
+                Section::Body if is_handler(line) => {
+                    block.handler.push(line.to_string());
+                    section = Section::Handler;
+                },
+                Section::Body => body.push(line),
+                Section::Handler => block.handler.push(line.to_string()),
+            }
+        }
+        while body.last().is_some_and(|line| line.trim().is_empty()) {
+            body.pop();
+        }
+
+        block.request = request_line.map(|request_line| HttpRequest {
+            name,
+            lazycurl_file: parse_request_line(&request_line).map(|(http_method, url)| {
+                let mut lazycurl_file = LazyCurlFile::new(url, headers, http_method);
+                lazycurl_file.body = body.join("\n");
+                lazycurl_file
+            }),
+        });
+        block
+    }
+
+    /// The block as it is written to the file, ending with an empty line
+    fn render(&self) -> String {
+        let mut lines = Vec::new();
+        lines.extend(self.separator.clone());
+        lines.extend(self.comments.clone());
+        lines.extend(self.variables.iter().map(|(name, value)| format!("@{} = {}", name, value)));
+        if let Some(Ok(lazycurl_file)) = self.request.as_ref().map(|request| &request.lazycurl_file) {
+            let method = match lazycurl_file.http_method {
+                HTTPMethod::WS => String::from("WEBSOCKET"),
+                http_method => http_method.to_string(),
+            };
+            lines.push(format!("{} {}", method, lazycurl_file.url));
+
+            let mut headers = lazycurl_file.headers.iter()
+                .filter(|header| header.split_once(':').is_some_and(|(name, _)| !name.trim().is_empty()))
+                .cloned()
+                .collect::<Vec<_>>();
+            let body = match &lazycurl_file.graphql {
+                Some(graphql) => {
+                    add_json_content_type(&mut headers);
+                    graphql.envelope().unwrap_or_default()
+                },
+                None => lazycurl_file.body.clone(),
+            };
+            lines.extend(headers);
+            if !body.is_empty() {
+                lines.push(String::new());
+                lines.push(body);
+            }
+        }
+        if !self.handler.is_empty() {
+            lines.push(String::new());
+            lines.extend(self.handler.clone());
+        }
+        lines.push(String::new());
+        lines.join("\n") + "\n"
+    }
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with("//")
+}
+
+fn is_handler(line: &str) -> bool {
+    line.starts_with("> ") || line.starts_with(">>") || line.starts_with(">{%")
+}
+
+/// `METHOD URL HTTP/1.1`, the method and version may be left out
+fn parse_request_line(request_line: &str) -> Result<(HTTPMethod, String), String> {
+    let (method, url) = match request_line.split_once(char::is_whitespace) {
+        Some((method, url)) if !method.is_empty() && method.chars().all(|c| c.is_ascii_uppercase() || c == '-') => (method, url.trim()),
+        _ => ("GET", request_line),
+    };
+    let url = match url.rsplit_once(char::is_whitespace) {
+        Some((url, version)) if version.starts_with("HTTP/") => url.trim(),
+        _ => url,
+    };
+    let http_method = match method {
+        "WEBSOCKET" => HTTPMethod::WS,
+        "GRPC" | "GRPC-WEB" => return Err(String::from("gRPC requests of .http files are not supported")),
+        method => HTTPMethod::from_str(method).map_err(|_| format!("the method {} is not supported", method))?,
+    };
+    Ok((http_method, url.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "@host = https://example.com\n\
+        @token = {{login.token}}\n\
+        \n\
+        ### Get users\n\
+        GET {{host}}/users\n    ?page=2\n    &per_page=10\n\
+        Accept: application/json\n\
+        \n\
+        ###\r\n\
+        # @name create\r\n\
+        POST {{host}}/users HTTP/1.1\r\n\
+        Content-Type: application/json\r\n\
+        \r\n\
+        {\"name\": \"Ada\"}\r\n\
+        \r\n\
+        > {%\r\n\
+        \x20   client.global.set(\"id\", response.body.id);\r\n\
+        %}\r\n";
+
+    fn http_file(name: &str) -> HttpFile {
+        let path = std::env::temp_dir().join(format!("lazycurl-{}-{}.http", name, std::process::id()));
+        HttpFile::parse(&path, TEXT)
+    }
+
+    #[test]
+    fn names_from_separator_and_name_comment() {
+        let http_file = http_file("names");
+        let names = http_file.requests().map(|request| request.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names, vec![Some(String::from("Get users")), Some(String::from("create"))]);
+    }
+
+    #[test]
+    fn variable_definitions_in_file_order() {
+        let variables = http_file("variables").variables();
+        assert_eq!(variables, vec![
+            (String::from("host"), String::from("https://example.com")),
+            (String::from("token"), String::from("{{login.token}}")),
+        ]);
+    }
+
+    #[test]
+    fn query_continuation_lines_join_the_url() {
+        let lazycurl_file = http_file("query").request(0).unwrap();
+        assert!(lazycurl_file.http_method == HTTPMethod::GET);
+        assert_eq!(lazycurl_file.url, "{{host}}/users?page=2&per_page=10");
+        assert_eq!(lazycurl_file.headers, vec!["Accept: application/json"]);
+        assert_eq!(lazycurl_file.body, "");
+    }
+
+    #[test]
+    fn handler_is_not_part_of_the_body() {
+        let http_file = http_file("handler");
+        let lazycurl_file = http_file.request(1).unwrap();
+        assert!(lazycurl_file.http_method == HTTPMethod::POST);
+        assert_eq!(lazycurl_file.url, "{{host}}/users");
+        assert_eq!(lazycurl_file.body, "{\"name\": \"Ada\"}");
+        assert_eq!(http_file.blocks[2].handler, vec![
+            "> {%",
+            "    client.global.set(\"id\", response.body.id);",
+            "%}",
+        ]);
+    }
+
+    #[test]
+    fn render_keeps_comments_and_handler() {
+        let mut http_file = http_file("render");
+        let block = &mut http_file.blocks[2];
+        if let Some(Ok(lazycurl_file)) = block.request.as_mut().map(|request| &mut request.lazycurl_file) {
+            lazycurl_file.url = String::from("https://example.org/users");
+        }
+        assert_eq!(block.render(), "###\n\
+            # @name create\n\
+            POST https://example.org/users\n\
+            Content-Type: application/json\n\
+            \n\
+            {\"name\": \"Ada\"}\n\
+            \n\
+            > {%\n\
+            \x20   client.global.set(\"id\", response.body.id);\n\
+            %}\n\
+            \n");
+    }
+
+    #[test]
+    fn save_request_leaves_other_blocks_untouched() {
+        let mut http_file = http_file("save");
+        let mut lazycurl_file = http_file.request(0).unwrap();
+        lazycurl_file.url = String::from("https://example.org/users");
+        assert_eq!(http_file.save_request(Some(0), &lazycurl_file).unwrap(), 0);
+        let added = LazyCurlFile::new(String::from("https://example.org/health"), Vec::new(), HTTPMethod::GET);
+        assert_eq!(http_file.save_request(None, &added).unwrap(), 2);
+
+        let saved = fs::read_to_string(&http_file.path).unwrap();
+        fs::remove_file(&http_file.path).unwrap();
+        let (variables, rest) = TEXT.split_at(TEXT.find("### Get users").unwrap());
+        let create = &rest[rest.find("###\r\n").unwrap()..];
+        assert_eq!(saved, format!("{}### Get users\n\
+            GET https://example.org/users\n\
+            Accept: application/json\n\
+            \n\
+            {}\n\
+            ###\n\
+            GET https://example.org/health\n\
+            \n", variables, create));
+    }
+}
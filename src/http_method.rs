@@ -1,11 +1,23 @@
 use ratatui::text::Line;
 use serde::{Serialize, Deserialize};
-use strum::{Display, EnumIter, FromRepr};
+use strum::{Display, EnumIter, EnumString, FromRepr};
 
-#[derive(Display, FromRepr, Clone, Copy, EnumIter, Deserialize, Serialize)]
+#[derive(Display, EnumString, FromRepr, Clone, Copy, EnumIter, PartialEq, Deserialize, Serialize)]
 pub enum HTTPMethod {
     POST,
     GET,
+    PUT,
+    PATCH,
+    DELETE,
+    HEAD,
+    OPTIONS,
+    /// WebSocket session, not an HTTP method but selected alongside them
+    WS,
+    /// gRPC call over HTTP/2, the method is picked in the gRPC tab
+    GRPC,
+    /// gRPC-Web call, for servers and proxies without HTTP/2 trailers
+    #[strum(serialize = "GRPC-WEB")]
+    GRPCWEB,
 }
 
 impl HTTPMethod {
@@ -14,6 +26,11 @@ impl HTTPMethod {
             .into()
     }
 
+    /// Methods whose body is sent even when it is empty
+    pub fn has_body(self) -> bool {
+        matches!(self, HTTPMethod::POST | HTTPMethod::PUT | HTTPMethod::PATCH)
+    }
+
     pub fn next(self) -> Self {
         let current_index = self as usize;
         let next_index = current_index.saturating_add(1);
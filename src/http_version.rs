@@ -0,0 +1,38 @@
+use curl::easy::HttpVersion;
+use serde::{Serialize, Deserialize};
+use strum::{Display, EnumIter, EnumString};
+
+/// HTTP version requested from curl
+#[derive(Display, EnumString, EnumIter, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum HTTPVersion {
+    #[strum(serialize = "1.0")]
+    #[serde(rename = "1.0")]
+    Http10,
+    #[strum(serialize = "1.1")]
+    #[serde(rename = "1.1")]
+    Http11,
+    /// HTTP/2 negotiated with ALPN over TLS, h2c upgrade over cleartext
+    #[strum(serialize = "2")]
+    #[serde(rename = "2")]
+    Http2,
+    /// HTTP/2 over TLS only, HTTP/1.1 over cleartext
+    #[strum(serialize = "2-tls")]
+    #[serde(rename = "2-tls")]
+    Http2Tls,
+    /// HTTP/2 without negotiation, h2c when used over cleartext
+    #[strum(serialize = "2-prior-knowledge")]
+    #[serde(rename = "2-prior-knowledge")]
+    Http2PriorKnowledge,
+}
+
+impl HTTPVersion {
+    pub fn curl_version(self) -> HttpVersion {
+        match self {
+            HTTPVersion::Http10 => HttpVersion::V10,
+            HTTPVersion::Http11 => HttpVersion::V11,
+            HTTPVersion::Http2 => HttpVersion::V2,
+            HTTPVersion::Http2Tls => HttpVersion::V2TLS,
+            HTTPVersion::Http2PriorKnowledge => HttpVersion::V2PriorKnowledge,
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::{collections::{BTreeMap, HashSet}, fmt, fs, path::{Path, PathBuf}};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde_json::Value;
+
+use crate::{lazycurl_file::LazyCurlFile, variables::Variables};
+
+pub mod postman;
+pub mod openapi;
+pub mod har;
+pub mod insomnia;
+pub mod bruno;
+
+/// Boundary of the multipart bodies made from form fields
+pub const FORM_BOUNDARY: &str = "lazycurl-form-boundary";
+
+/// What an import wrote, and what it could not convert
+#[derive(Default)]
+pub struct ImportReport {
+    /// Request files written, below the collection directory
+    pub requests: Vec<PathBuf>,
+    /// Environments written, with their number of values
+    pub environments: Vec<(String, usize)>,
+    /// Names of the variables added to `variables.json`
+    pub variables: Vec<String>,
+    /// Request files and environments that existed before the import, with
+    /// the numbered name written instead so they are not overwritten
+    pub renamed: Vec<(String, String)>,
+    /// Parts that were left out or only partly converted, one line each
+    pub warnings: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn warn(&mut self, location: &str, message: impl fmt::Display) {
+        self.warnings.push(format!("{}: {}", location, message));
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.requests.is_empty() {
+            writeln!(f, "Imported {} request(s)", self.requests.len())?;
+            for path in &self.requests {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        for (name, count) in &self.environments {
+            writeln!(f, "Imported environment {} with {} value(s)", name, count)?;
+        }
+        if !self.variables.is_empty() {
+            writeln!(f, "Added {} to variables.json", self.variables.join(", "))?;
+        }
+        if !self.renamed.is_empty() {
+            writeln!(f, "Kept what existed, imported under another name:")?;
+            for (existing, written) in &self.renamed {
+                writeln!(f, "  {} -> {}", existing, written)?;
+            }
+        }
+        if !self.warnings.is_empty() {
+            writeln!(f, "Not converted:")?;
+            for warning in &self.warnings {
+                writeln!(f, "  {}", warning)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the requests of an import, giving requests with the same name in
+/// a directory, or the name of a file already there, different file names
+pub struct RequestWriter {
+    used_paths: HashSet<PathBuf>,
+}
+
+impl RequestWriter {
+    pub fn new() -> Self {
+        Self { used_paths: HashSet::new() }
+    }
+
+    pub fn write(&mut self, directory: &Path, name: &str, lazycurl_file: &LazyCurlFile, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(directory)?;
+        let stem = file_name(name);
+        let mut path = directory.join(format!("{}.json", stem));
+        let mut count = 1;
+        while self.used_paths.contains(&path) {
+            count += 1;
+            path = directory.join(format!("{} {}.json", stem, count));
+        }
+        let wanted_path = path.clone();
+        while path.exists() || self.used_paths.contains(&path) {
+            count += 1;
+            path = directory.join(format!("{} {}.json", stem, count));
+        }
+        if path != wanted_path {
+            report.renamed.push((wanted_path.display().to_string(), path.display().to_string()));
+        }
+        fs::write(&path, serde_json::to_string_pretty(lazycurl_file)?)?;
+        self.used_paths.insert(wanted_path);
+        self.used_paths.insert(path.clone());
+        report.requests.push(path);
+        Ok(())
+    }
+}
+
+impl Default for RequestWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a JSON or YAML document, by the extension of the file
+pub fn read_document(path: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    let is_yaml = path.extension().is_some_and(|extension| extension == "yaml" || extension == "yml");
+    Ok(if is_yaml { serde_yaml::from_str(&contents)? } else { serde_json::from_str(&contents)? })
+}
+
+/// Strings as they are, null as nothing and other values as JSON
+pub fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// A name usable as a file or directory name
+pub fn file_name(name: &str) -> String {
+    let name = name.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')') { c } else { '_' })
+        .collect::<String>();
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() { String::from("request") } else { name.to_string() }
+}
+
+/// Save an environment, numbering its name when an environment of that name
+/// exists already
+pub fn write_environment(name: &str, values: &BTreeMap<String, String>, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = Variables::environments();
+    let mut written_name = name.to_string();
+    let mut count = 1;
+    while existing.contains(&written_name) {
+        count += 1;
+        written_name = format!("{} {}", name, count);
+    }
+    if written_name != name {
+        report.renamed.push((format!("environment {}", name), format!("environment {}", written_name)));
+    }
+    Variables::save_environment(&written_name, values)?;
+    report.environments.push((written_name, values.len()));
+    Ok(())
+}
+
+/// Add the values to `variables.json`, values already set there are kept
+pub fn add_variables(values: &BTreeMap<String, String>, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let mut variables = Variables::load()?;
+    let previous = variables.clone();
+    for (name, value) in values {
+        if !Variables::is_valid_name(name) {
+            report.warn(name, "not a valid variable name");
+        } else if variables.get(name).is_none() {
+            variables.set(name, value.clone());
+            report.variables.push(name.clone());
+        }
+    }
+    if variables != previous {
+        variables.save()?;
+    }
+    Ok(())
+}
+
+/// Encode `text` for a query string or form body, `{{name}}` placeholders
+/// are kept as they are so they can still be substituted
+pub fn form_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(length) = rest.strip_prefix("{{").and_then(|placeholder| placeholder.find("}}")) {
+            encoded.push_str(&rest[..length + 4]);
+            rest = &rest[length + 4..];
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' | '~' => encoded.push(c),
+            ' ' => encoded.push('+'),
+            c => {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            },
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    encoded
+}
+
+/// Decode a query string or form body value, `+` is a space
+pub fn form_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Add the header unless the request already has one with that name
+pub fn add_header(headers: &mut Vec<String>, name: &str, value: &str) {
+    let is_set = headers.iter()
+        .filter_map(|header| header.split_once(':'))
+        .any(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name));
+    if !is_set {
+        headers.push(format!("{}: {}", name, value));
+    }
+}
+
+/// Append a query parameter to the URL
+pub fn add_query_parameter(url: &mut String, name: &str, value: &str) {
+    url.push(if url.contains('?') { '&' } else { '?' });
+    url.push_str(&format!("{}={}", form_encode(name), form_encode(value)));
+}
+
+/// Replace the `:name` path segments of the URL
+pub fn replace_path_variable(url: &str, name: &str, value: &str) -> String {
+    let segment = format!("/:{}", name);
+    let mut result = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(start) = rest.find(&segment) {
+        let end = start + segment.len();
+        let is_whole_segment = rest[end..].chars().next().is_none_or(|c| matches!(c, '/' | '?' | '#'));
+        result.push_str(&rest[..start]);
+        if is_whole_segment {
+            result.push_str(&format!("/{}", value));
+        } else {
+            result.push_str(&segment);
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Add a basic auth header. Credentials with placeholders are encoded by a
+/// pre-request script, once the placeholders are filled in.
+pub fn add_basic_auth(lazycurl_file: &mut LazyCurlFile, username: &str, password: &str) {
+    let credentials = format!("{}:{}", username, password);
+    if credentials.contains("{{") {
+        lazycurl_file.pre_request_script.push_str(&format!(
+            "request.headers[\"Authorization\"] = \"Basic \" + base64_encode({});\n",
+            rhai_template(&credentials)));
+    } else {
+        add_header(&mut lazycurl_file.headers, "Authorization", &format!("Basic {}", STANDARD.encode(credentials)));
+    }
+}
+
+/// A Rhai expression building the text, with `{{name}}` read from `vars`
+fn rhai_template(template: &str) -> String {
+    let literal = |text: &str| serde_json::to_string(text).unwrap_or_default();
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            parts.push(literal(&rest[..start]));
+        }
+        parts.push(format!("(vars[{}] ?? \"\")", literal(rest[start + 2..start + 2 + length].trim())));
+        rest = &rest[start + length + 4..];
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(literal(rest));
+    }
+    parts.join(" + ")
+}
+
+/// Set a form-encoded body made of the fields
+pub fn set_form_body(lazycurl_file: &mut LazyCurlFile, fields: &[(String, String)]) {
+    lazycurl_file.body = fields.iter()
+        .map(|(name, value)| format!("{}={}", form_encode(name), form_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    add_header(&mut lazycurl_file.headers, "Content-Type", "application/x-www-form-urlencoded");
+}
+
+/// Set a multipart body made of the text fields, nothing is set without fields
+pub fn set_multipart_body(lazycurl_file: &mut LazyCurlFile, fields: &[(String, String)]) {
+    if fields.is_empty() {
+        return;
+    }
+    let mut multipart = String::new();
+    for (name, value) in fields {
+        multipart.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            FORM_BOUNDARY, name, value));
+    }
+    lazycurl_file.body = format!("{}--{}--\r\n", multipart, FORM_BOUNDARY);
+    add_header(&mut lazycurl_file.headers, "Content-Type", &format!("multipart/form-data; boundary={}", FORM_BOUNDARY));
+}
+
+/// Keep a JavaScript script of another tool as a comment, to be ported to Rhai
+pub fn add_script_comment(script: &mut String, tool: &str, source: &str) {
+    script.push_str(&format!("// {} script, to be ported to Rhai:\n", tool));
+    for line in source.lines() {
+        script.push_str(&format!("// {}\n", line));
+    }
+}
+
+/// Warn about `{{$name}}` placeholders, the dynamic variables of other tools
+pub fn warn_dynamic_variables(lazycurl_file: &LazyCurlFile, location: &str, report: &mut ImportReport) {
+    let text = format!("{}\n{}\n{}", lazycurl_file.url, lazycurl_file.headers.join("\n"), lazycurl_file.body);
+    let dynamic_variables = Variables::default().missing(&text).into_iter()
+        .filter(|name| name.starts_with('$'))
+        .collect::<Vec<_>>();
+    if !dynamic_variables.is_empty() {
+        report.warn(location, format!("dynamic variables {} have no value in lazycurl", dynamic_variables.join(", ")));
+    }
+}
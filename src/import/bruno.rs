@@ -0,0 +1,426 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{assertion::Assertion, extraction::Extraction, http_method::HTTPMethod, lazycurl_file::LazyCurlFile, utils::{directory::Directory, graphql::GraphQLBody}, variables::Variables};
+
+use super::{add_basic_auth, add_header, add_query_parameter, add_script_comment, add_variables, file_name, replace_path_variable, set_form_body, set_multipart_body, warn_dynamic_variables, write_environment, ImportReport, RequestWriter};
+
+/// The blocks of a `.bru` file, `name { ... }` or `name [ ... ]`, with their
+/// lines unindented
+struct BruFile {
+    blocks: Vec<(String, Vec<String>)>,
+}
+
+/// A `key: value` line of a block, `~key` is disabled
+#[derive(Clone)]
+struct Pair {
+    key: String,
+    value: String,
+    enabled: bool,
+}
+
+/// What `collection.bru` and `folder.bru` hand down to the requests below them
+#[derive(Clone, Default)]
+struct Scope {
+    auth: Option<(String, Vec<Pair>)>,
+    headers: Vec<(String, String)>,
+    /// `vars:pre-request` of the folders, filled into the requests
+    variables: BTreeMap<String, String>,
+}
+
+impl BruFile {
+    fn load(path: &Path) -> Result<BruFile, Box<dyn std::error::Error>> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    fn parse(text: &str) -> BruFile {
+        let mut blocks = Vec::new();
+        let mut current: Option<(String, &str, Vec<String>)> = None;
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            match &mut current {
+                Some((name, close, lines)) => {
+                    if line.trim_end() == *close {
+                        blocks.push((std::mem::take(name), std::mem::take(lines)));
+                        current = None;
+                    } else {
+                        lines.push(line.strip_prefix("  ").unwrap_or(line).to_string());
+                    }
+                },
+                None => {
+                    let trimmed = line.trim();
+                    if let Some(name) = trimmed.strip_suffix('{') {
+                        current = Some((name.trim().to_string(), "}", Vec::new()));
+                    } else if let Some(name) = trimmed.strip_suffix('[') {
+                        current = Some((name.trim().to_string(), "]", Vec::new()));
+                    } else if let Some((name, items)) = trimmed.split_once('[').filter(|_| trimmed.ends_with(']')) {
+                        // A list on one line, `vars:secret [a, b]`
+                        blocks.push((name.trim().to_string(), vec![items.trim_end_matches(']').to_string()]));
+                    }
+                },
+            }
+        }
+        BruFile { blocks }
+    }
+
+    fn block(&self, name: &str) -> Option<&[String]> {
+        self.blocks.iter().find(|(block_name, _)| block_name == name).map(|(_, lines)| lines.as_slice())
+    }
+
+    /// The lines of a text block, e.g. `body:json`
+    fn text(&self, name: &str) -> Option<String> {
+        self.block(name).map(|lines| lines.join("\n").trim().to_string())
+    }
+
+    /// The pairs of a dictionary block, e.g. `headers`
+    fn pairs(&self, name: &str) -> Vec<Pair> {
+        let Some(lines) = self.block(name) else {
+            return Vec::new();
+        };
+        let mut pairs = Vec::new();
+        let mut lines = lines.iter();
+        while let Some(line) = lines.next() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let (key, enabled) = match key.strip_prefix('~') {
+                Some(key) => (key, false),
+                None => (key, true),
+            };
+            let mut value = value.trim().to_string();
+            // Multiline values are written between ''' lines
+            if value == "'''" {
+                let value_lines = lines.by_ref()
+                    .take_while(|line| line.trim() != "'''")
+                    .map(|line| line.strip_prefix("  ").unwrap_or(line))
+                    .collect::<Vec<_>>();
+                value = value_lines.join("\n");
+            }
+            pairs.push(Pair { key: key.to_string(), value, enabled });
+        }
+        pairs
+    }
+
+    fn enabled_pairs(&self, name: &str) -> Vec<Pair> {
+        self.pairs(name).into_iter().filter(|pair| pair.enabled && !pair.key.is_empty()).collect()
+    }
+
+    fn value(&self, block: &str, key: &str) -> Option<String> {
+        self.pairs(block).into_iter().find(|pair| pair.key == key).map(|pair| pair.value)
+    }
+
+    /// The items of a list block, e.g. `vars:secret`
+    fn list(&self, name: &str) -> Vec<String> {
+        self.block(name).unwrap_or_default().iter()
+            .flat_map(|line| line.split(','))
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// The `auth:<mode>` block of the auth mode, None for `inherit`
+    fn auth(&self, mode: Option<String>) -> Option<(String, Vec<Pair>)> {
+        let mode = mode.unwrap_or_else(|| String::from("none"));
+        if mode == "inherit" {
+            return None;
+        }
+        let pairs = self.pairs(&format!("auth:{}", mode));
+        Some((mode, pairs))
+    }
+}
+
+/// Import a Bruno collection, the directory with `bruno.json` or that file,
+/// into the collection directory
+pub fn import(path: &Path) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let root = if path.is_dir() { path.to_path_buf() } else { path.parent().map(Path::to_path_buf).unwrap_or_default() };
+    let config = fs::read_to_string(root.join("bruno.json"))
+        .map_err(|_| format!("{} has no bruno.json, not a Bruno collection", root.display()))?;
+    let config: Value = serde_json::from_str(&config).map_err(|e| format!("bruno.json: {}", e))?;
+    let name = config.get("name").and_then(Value::as_str).map_or_else(
+        || root.file_name().map_or_else(|| String::from("bruno"), |name| name.to_string_lossy().to_string()),
+        str::to_string);
+
+    let mut report = ImportReport::default();
+    let mut scope = Scope::default();
+    let collection_path = root.join("collection.bru");
+    if collection_path.exists() {
+        let collection = BruFile::load(&collection_path)?;
+        let values = collection.enabled_pairs("vars:pre-request").into_iter()
+            .map(|pair| (pair.key, pair.value))
+            .collect::<BTreeMap<_, _>>();
+        add_variables(&values, &mut report)?;
+        scope = folder_scope(&collection, &scope, &name, &mut report);
+        scope.variables.clear();
+    }
+
+    let directory = Path::new(&Directory::Collection.path()).join(file_name(&name));
+    import_folder(&root, &directory, &scope, &name, &mut RequestWriter::new(), &mut report)?;
+    import_environments(&root.join("environments"), &mut report)?;
+    Ok(report)
+}
+
+fn folder_scope(folder: &BruFile, scope: &Scope, location: &str, report: &mut ImportReport) -> Scope {
+    let mut folder_scope = scope.clone();
+    // Folders without an auth block use the auth of their parent
+    if let Some(auth) = folder.value("auth", "mode").and_then(|mode| folder.auth(Some(mode))) {
+        folder_scope.auth = Some(auth);
+    }
+    folder_scope.headers.extend(folder.enabled_pairs("headers").into_iter().map(|pair| (pair.key, pair.value)));
+    folder_scope.variables.extend(folder.enabled_pairs("vars:pre-request").into_iter().map(|pair| (pair.key, pair.value)));
+    if ["script:pre-request", "script:post-response", "tests"].iter().any(|name| folder.block(name).is_some()) {
+        report.warn(location, "scripts and tests of collections and folders are not supported, they were left out");
+    }
+    folder_scope
+}
+
+fn import_folder(
+    folder: &Path,
+    directory: &Path,
+    scope: &Scope,
+    location: &str,
+    writer: &mut RequestWriter,
+    report: &mut ImportReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Folders and requests are ordered by the seq of their meta block
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if path.is_dir() {
+            if name.starts_with('.') || name == "environments" || name == "node_modules" {
+                continue;
+            }
+            let folder_file = path.join("folder.bru");
+            let bru_file = if folder_file.exists() { BruFile::load(&folder_file)? } else { BruFile { blocks: Vec::new() } };
+            entries.push((sequence(&bru_file), path, bru_file));
+        } else if path.extension().is_some_and(|extension| extension == "bru") && name != "folder.bru" && name != "collection.bru" {
+            let bru_file = BruFile::load(&path)?;
+            entries.push((sequence(&bru_file), path, bru_file));
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+    for (_, path, bru_file) in entries {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let name = bru_file.value("meta", "name").filter(|name| !name.is_empty()).unwrap_or(stem);
+        let entry_location = format!("{}/{}", location, name);
+        if path.is_dir() {
+            let folder_scope = folder_scope(&bru_file, scope, &entry_location, report);
+            import_folder(&path, &directory.join(file_name(&name)), &folder_scope, &entry_location, writer, report)?;
+        } else if let Some(lazycurl_file) = convert_request(&bru_file, scope, &entry_location, report) {
+            writer.write(directory, &name, &lazycurl_file, report)?;
+        }
+    }
+    Ok(())
+}
+
+fn sequence(bru_file: &BruFile) -> u64 {
+    bru_file.value("meta", "seq").and_then(|seq| seq.parse().ok()).unwrap_or(u64::MAX)
+}
+
+fn convert_request(bru_file: &BruFile, scope: &Scope, location: &str, report: &mut ImportReport) -> Option<LazyCurlFile> {
+    let kind = bru_file.value("meta", "type").unwrap_or_else(|| String::from("http"));
+    if kind != "http" && kind != "graphql" {
+        report.warn(location, format!("{} requests are not supported, the request was left out", kind));
+        return None;
+    }
+    let Some((http_method, method_block)) = bru_file.blocks.iter()
+        .find_map(|(name, _)| HTTPMethod::from_str(&name.to_uppercase()).ok()
+            .filter(|http_method| !matches!(http_method, HTTPMethod::WS | HTTPMethod::GRPC | HTTPMethod::GRPCWEB))
+            .map(|http_method| (http_method, name.clone())))
+    else {
+        report.warn(location, "no request method, the request was left out");
+        return None;
+    };
+
+    // The URL already has the query parameters of params:query
+    let mut url = bru_file.value(&method_block, "url").unwrap_or_default();
+    for parameter in bru_file.enabled_pairs("params:path") {
+        url = replace_path_variable(&url, &parameter.key, &parameter.value);
+    }
+    let headers = bru_file.enabled_pairs("headers").into_iter()
+        .map(|pair| format!("{}: {}", pair.key, pair.value))
+        .collect();
+    let mut lazycurl_file = LazyCurlFile::new(url, headers, http_method);
+
+    let auth = bru_file.auth(bru_file.value(&method_block, "auth")).or_else(|| scope.auth.clone());
+    if let Some((mode, pairs)) = auth {
+        convert_auth(&mode, &pairs, &mut lazycurl_file, location, report);
+    }
+    let body_mode = bru_file.value(&method_block, "body").unwrap_or_else(|| String::from("none"));
+    convert_body(bru_file, &body_mode, &mut lazycurl_file, location, report);
+    for (name, value) in &scope.headers {
+        add_header(&mut lazycurl_file.headers, name, value);
+    }
+    convert_post_response_variables(bru_file, &mut lazycurl_file, location, report);
+    convert_assertions(bru_file, &mut lazycurl_file, location, report);
+    for (block, stage) in [("script:pre-request", "pre-request"), ("script:post-response", "post-response"), ("tests", "tests")] {
+        let Some(source) = bru_file.text(block).filter(|source| !source.is_empty()) else {
+            continue;
+        };
+        let script = if stage == "pre-request" { &mut lazycurl_file.pre_request_script } else { &mut lazycurl_file.post_response_script };
+        add_script_comment(script, "Bruno", &source);
+        report.warn(location, format!("the {} script is JavaScript, it was kept as a comment", stage));
+    }
+
+    let mut variables = scope.variables.clone();
+    variables.extend(bru_file.enabled_pairs("vars:pre-request").into_iter().map(|pair| (pair.key, pair.value)));
+    if !variables.is_empty() {
+        let variables = Variables { values: variables, ..Variables::default() };
+        lazycurl_file = lazycurl_file.with_variables(&variables);
+    }
+    let request_text = format!("{}\n{}\n{}", lazycurl_file.url, lazycurl_file.headers.join("\n"), lazycurl_file.body);
+    if request_text.contains("{{process.env.") {
+        report.warn(location, "{{process.env.*}} values are not supported, set them as variables");
+    }
+    warn_dynamic_variables(&lazycurl_file, location, report);
+    Some(lazycurl_file)
+}
+
+fn convert_auth(mode: &str, pairs: &[Pair], lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    let field = |key: &str| pairs.iter().find(|pair| pair.key == key).map(|pair| pair.value.clone()).unwrap_or_default();
+    match mode {
+        "none" => {},
+        "bearer" => add_header(&mut lazycurl_file.headers, "Authorization", &format!("Bearer {}", field("token"))),
+        "basic" => add_basic_auth(lazycurl_file, &field("username"), &field("password")),
+        "apikey" => match field("placement").as_str() {
+            "queryparams" => add_query_parameter(&mut lazycurl_file.url, &field("key"), &field("value")),
+            _ => add_header(&mut lazycurl_file.headers, &field("key"), &field("value")),
+        },
+        mode => report.warn(location, format!("{} auth is not supported, set its header by hand", mode)),
+    }
+}
+
+fn convert_body(bru_file: &BruFile, mode: &str, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    let fields = |block: &str, report: &mut ImportReport| {
+        let mut fields = Vec::new();
+        for pair in bru_file.enabled_pairs(block) {
+            if pair.value.starts_with("@file(") {
+                report.warn(location, format!("file field {} {} of the form was left out", pair.key, pair.value));
+            } else {
+                fields.push((pair.key, pair.value));
+            }
+        }
+        fields
+    };
+    let content_type = match mode {
+        "none" => return,
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "text" => "text/plain",
+        "sparql" => "application/sparql-query",
+        "formUrlEncoded" => return set_form_body(lazycurl_file, &fields("body:form-urlencoded", report)),
+        "multipartForm" => return set_multipart_body(lazycurl_file, &fields("body:multipart-form", report)),
+        "graphql" => {
+            lazycurl_file.graphql = Some(GraphQLBody {
+                query: bru_file.text("body:graphql").unwrap_or_default(),
+                variables: bru_file.text("body:graphql:vars").unwrap_or_default(),
+                operation_name: None,
+            });
+            return;
+        },
+        mode => {
+            report.warn(location, format!("{} body is not supported", mode));
+            return;
+        },
+    };
+    lazycurl_file.body = bru_file.text(&format!("body:{}", mode)).unwrap_or_default();
+    if !lazycurl_file.body.is_empty() {
+        add_header(&mut lazycurl_file.headers, "Content-Type", content_type);
+    }
+}
+
+/// `res.body.a[0].b` is `$.a[0].b`
+fn json_path(expression: &str) -> Option<String> {
+    let path = expression.strip_prefix("res.body")?;
+    (path.is_empty() || path.starts_with('.') || path.starts_with('[')).then(|| format!("${}", path))
+}
+
+/// `res.headers.name` and `res.headers['name']` are `name`
+fn header_name(expression: &str) -> Option<String> {
+    let name = expression.strip_prefix("res.headers")?;
+    let name = name.strip_prefix('.').or_else(|| name.strip_prefix('[')?.strip_suffix(']'))?;
+    let name = name.trim_matches(['\'', '"']);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// `vars:post-response` of body and header values become extraction rules
+fn convert_post_response_variables(bru_file: &BruFile, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    for pair in bru_file.enabled_pairs("vars:post-response") {
+        let line = match (json_path(&pair.value), header_name(&pair.value)) {
+            (Some(path), _) => format!("{} = json {}", pair.key, path),
+            (None, Some(name)) => format!("{} = header {}", pair.key, name),
+            (None, None) => String::new(),
+        };
+        match Extraction::from_str(&line) {
+            Ok(extraction) => lazycurl_file.extractions.push(extraction),
+            Err(_) => report.warn(location, format!("the post-response variable {}: {} was left out", pair.key, pair.value)),
+        }
+    }
+}
+
+/// The `assert` lines that have a lazycurl test, e.g. `res.status: eq 200`
+fn convert_assertions(bru_file: &BruFile, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    for pair in bru_file.enabled_pairs("assert") {
+        let (operator, operand) = pair.value.split_once(' ').map_or((pair.value.as_str(), ""), |(operator, operand)| (operator, operand.trim()));
+        let json_type = match operator {
+            "isNumber" => Some("number"),
+            "isString" => Some("string"),
+            "isBoolean" => Some("boolean"),
+            "isArray" => Some("array"),
+            "isJson" => Some("object"),
+            "isNull" => Some("null"),
+            _ => None,
+        };
+        let line = match (pair.key.as_str(), operator) {
+            ("res.status", "eq") => format!("status == {}", operand),
+            ("res.responseTime", "lt") => format!("time < {}", operand),
+            ("res.responseTime", "lte") => format!("time < {}", operand.parse::<u64>().map_or(0, |ms| ms + 1)),
+            ("res.body", "contains") => format!("body contains {}", operand.trim_matches(['\'', '"'])),
+            (key, operator) => match (json_path(key), header_name(key), json_type) {
+                (Some(path), _, _) if operator == "eq" => format!("json {} == {}", path, operand),
+                (Some(path), _, _) if operator == "matches" => format!("json {} matches {}", path, operand),
+                (Some(path), _, Some(json_type)) => format!("json {} is {}", path, json_type),
+                (None, Some(name), _) if operator == "isDefined" => format!("header {} exists", name),
+                (None, Some(name), _) if operator == "matches" => format!("header {} matches {}", name, operand),
+                _ => String::new(),
+            },
+        };
+        match Assertion::from_str(&line) {
+            Ok(assertion) => lazycurl_file.assertions.push(assertion),
+            Err(_) => report.warn(location, format!("the assertion {}: {} was left out", pair.key, pair.value)),
+        }
+    }
+}
+
+/// Each `environments/NAME.bru` becomes the environment NAME, secrets are
+/// kept empty since Bruno does not export their values
+fn import_environments(directory: &Path, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Ok(());
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "bru"))
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    for path in paths {
+        let environment = BruFile::load(&path)?;
+        let name = file_name(&path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default());
+        let mut values = environment.enabled_pairs("vars").into_iter()
+            .map(|pair| (pair.key, pair.value))
+            .collect::<BTreeMap<_, _>>();
+        let secrets = environment.list("vars:secret");
+        if !secrets.is_empty() {
+            report.warn(&name, format!("the secrets {} have no value, set them in the environment", secrets.join(", ")));
+        }
+        for secret in secrets {
+            values.entry(secret.trim_start_matches('~').to_string()).or_default();
+        }
+        write_environment(&name, &values, report)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,407 @@
+use std::{fs, path::{Path, PathBuf}, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    http_method::HTTPMethod,
+    lazycurl_file::{LazyCurlFile, SavedResponse, SavedTimings},
+    request_settings::RequestSettings,
+    utils::directory::Directory,
+    variables::Variables,
+};
+
+use super::{add_header, file_name, form_decode, form_encode, ImportReport, RequestWriter};
+
+#[derive(Deserialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Deserialize)]
+struct Log {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: Option<String>,
+    #[serde(default)]
+    time: f64,
+    request: Request,
+    response: Option<Response>,
+    timings: Option<Timings>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<NameValue>,
+    post_data: Option<PostData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    #[serde(default)]
+    mime_type: String,
+    text: Option<String>,
+    #[serde(default)]
+    params: Vec<Param>,
+}
+
+#[derive(Deserialize)]
+struct Param {
+    name: String,
+    value: Option<String>,
+    #[serde(rename = "fileName")]
+    file_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    #[serde(default)]
+    status: u32,
+    #[serde(default)]
+    status_text: String,
+    #[serde(default)]
+    http_version: String,
+    #[serde(default)]
+    headers: Vec<NameValue>,
+    content: Option<Content>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    #[serde(default)]
+    size: i64,
+    #[serde(default)]
+    mime_type: String,
+    text: Option<String>,
+    encoding: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NameValue {
+    name: String,
+    #[serde(default)]
+    value: String,
+}
+
+/// Phases of the entry in milliseconds, -1 when they do not apply
+#[derive(Deserialize)]
+struct Timings {
+    #[serde(default)]
+    dns: f64,
+    #[serde(default)]
+    connect: f64,
+    #[serde(default)]
+    ssl: f64,
+    #[serde(default)]
+    send: f64,
+    #[serde(default)]
+    wait: f64,
+    #[serde(default)]
+    receive: f64,
+}
+
+/// Which entries of a HAR file to import, and where to
+#[derive(Default)]
+pub struct HarImportOptions {
+    /// Numbers of the entries as listed by `list`, all entries when empty
+    pub entries: Vec<usize>,
+    /// Only entries whose URL contains this text
+    pub url_filter: Option<String>,
+    /// Import into history with their responses instead of the collection
+    pub into_history: bool,
+}
+
+/// The entries of a HAR file, one line each with the number to pick it by
+pub fn list(path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let har = read(path)?;
+    Ok(har.log.entries.iter().enumerate()
+        .map(|(index, entry)| {
+            let status = entry.response.as_ref().map_or(0, |response| response.status);
+            format!("{:>4} {:<8} {:>3} {}", index + 1, entry.request.method, status, entry.request.url)
+        })
+        .collect())
+}
+
+/// Entry numbers from a list like `1,3-5`
+pub fn parse_entries(text: &str) -> Result<Vec<usize>, String> {
+    let mut entries = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let number = |text: &str| match text.trim().parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(format!("invalid entry number {}", text.trim())),
+        };
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (number(first)?, number(last)?);
+                if first > last {
+                    return Err(format!("invalid entry range {}", part));
+                }
+                entries.extend(first..=last);
+            },
+            None => entries.push(number(part)?),
+        }
+    }
+    Ok(entries)
+}
+
+pub fn import(path: &Path, options: &HarImportOptions) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let har = read(path)?;
+    let mut report = ImportReport::default();
+    if let Some(number) = options.entries.iter().find(|number| **number > har.log.entries.len()) {
+        return Err(format!("there is no entry {}, the file has {}", number, har.log.entries.len()).into());
+    }
+
+    let stem = path.file_stem().map_or_else(|| String::from("har"), |stem| stem.to_string_lossy().to_string());
+    let directory = Path::new(&Directory::Collection.path()).join(file_name(&stem));
+    let mut writer = RequestWriter::new();
+    for (index, entry) in har.log.entries.iter().enumerate() {
+        let number = index + 1;
+        if !options.entries.is_empty() && !options.entries.contains(&number) {
+            continue;
+        }
+        if options.url_filter.as_ref().is_some_and(|filter| !entry.request.url.contains(filter.as_str())) {
+            continue;
+        }
+        let location = format!("entry {} {} {}", number, entry.request.method, entry.request.url);
+        let Ok(http_method) = HTTPMethod::from_str(&entry.request.method.to_uppercase()) else {
+            report.warn(&location, "the method is not supported, the entry was left out");
+            continue;
+        };
+        let mut lazycurl_file = convert_request(&entry.request, http_method, &location, &mut report);
+
+        if options.into_history {
+            let started = entry.started_date_time.as_deref()
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map_or_else(Utc::now, |time| time.with_timezone(&Utc));
+            lazycurl_file.response = entry.response.as_ref().map(|response| convert_response(entry, response, started));
+            report.requests.push(lazycurl_file.save_as_of(started)?);
+        } else {
+            let name = format!("{:03} {} {}", number, entry.request.method, url_name(&entry.request.url));
+            writer.write(&directory, &name, &lazycurl_file, &mut report)?;
+        }
+    }
+    Ok(report)
+}
+
+fn read(path: &Path) -> Result<Har, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|e| format!("not a HAR file: {}", e).into())
+}
+
+fn convert_request(request: &Request, http_method: HTTPMethod, location: &str, report: &mut ImportReport) -> LazyCurlFile {
+    // HTTP/2 pseudo headers are part of the request line, the length is set by curl
+    let headers = request.headers.iter()
+        .filter(|header| !header.name.starts_with(':') && !header.name.eq_ignore_ascii_case("content-length"))
+        .map(|header| format!("{}: {}", header.name, header.value))
+        .collect();
+    let mut lazycurl_file = LazyCurlFile::new(request.url.clone(), headers, http_method);
+
+    if let Some(post_data) = &request.post_data {
+        lazycurl_file.body = match &post_data.text {
+            Some(text) => text.clone(),
+            None => {
+                if post_data.params.iter().any(|param| param.file_name.is_some()) {
+                    report.warn(location, "file fields of the form were left out");
+                }
+                post_data.params.iter()
+                    .filter(|param| param.file_name.is_none())
+                    .map(|param| format!("{}={}", form_encode(&param.name), form_encode(param.value.as_deref().unwrap_or_default())))
+                    .collect::<Vec<_>>()
+                    .join("&")
+            },
+        };
+        if !post_data.mime_type.is_empty() {
+            add_header(&mut lazycurl_file.headers, "Content-Type", &post_data.mime_type);
+        }
+    }
+    lazycurl_file
+}
+
+fn convert_response(entry: &Entry, response: &Response, started: DateTime<Utc>) -> SavedResponse {
+    let http_version = match response.http_version.to_uppercase().as_str() {
+        "" => String::from("HTTP/1.1"),
+        "H2" | "HTTP/2.0" => String::from("HTTP/2"),
+        "H3" | "HTTP/3.0" => String::from("HTTP/3"),
+        version => version.to_string(),
+    };
+    let status_line = format!("{} {} {}", http_version, response.status, response.status_text).trim_end().to_string();
+    let mut headers = std::iter::once(status_line)
+        .chain(response.headers.iter().map(|header| format!("{}: {}", header.name, header.value)))
+        .collect::<Vec<_>>();
+
+    let content = response.content.as_ref();
+    if let Some(mime_type) = content.map(|content| content.mime_type.as_str()).filter(|mime_type| !mime_type.is_empty()) {
+        add_header(&mut headers, "Content-Type", mime_type);
+    }
+    let body = content.and_then(|content| content.text.clone()).unwrap_or_default();
+    let body_base64 = content.and_then(|content| content.encoding.as_deref()) == Some("base64");
+    let body_size = match content.map_or(-1, |content| content.size) {
+        size if size >= 0 => size as usize,
+        _ if body_base64 => STANDARD.decode(&body).map_or(0, |bytes| bytes.len()),
+        _ => body.len(),
+    };
+
+    let phase = |time: f64| time.max(0.0);
+    let timings = entry.timings.as_ref().map_or(SavedTimings::default(), |timings| SavedTimings {
+        dns: phase(timings.dns),
+        connect: phase(timings.connect),
+        ssl: phase(timings.ssl),
+        send: phase(timings.send),
+        wait: phase(timings.wait),
+        receive: phase(timings.receive),
+        total: entry.time,
+    });
+
+    SavedResponse { started, status: response.status, http_version, headers, body, body_base64, body_size, timings }
+}
+
+/// Host and path of the URL, to name the request by
+fn url_name(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    url.trim_end_matches('/').replace('/', " ")
+}
+
+/// A HAR 1.2 log of the requests, with the responses kept in history.
+/// Placeholders are replaced by the values of `variables`, as they were sent.
+/// WebSocket sessions and gRPC calls are left out, returns the log and its
+/// number of entries.
+pub fn export(files: &[(PathBuf, LazyCurlFile)], variables: &Variables) -> (String, usize) {
+    let entries = files.iter()
+        .filter(|(_, lazycurl_file)| !matches!(lazycurl_file.http_method, HTTPMethod::WS | HTTPMethod::GRPC | HTTPMethod::GRPCWEB))
+        .filter_map(|(path, lazycurl_file)| export_entry(path, lazycurl_file, variables))
+        .collect::<Vec<_>>();
+    let har = json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "lazycurl", "version": env!("CARGO_PKG_VERSION") },
+            "entries": &entries,
+        }
+    });
+    (serde_json::to_string_pretty(&har).unwrap_or_default(), entries.len())
+}
+
+fn export_entry(path: &Path, lazycurl_file: &LazyCurlFile, variables: &Variables) -> Option<Value> {
+    let request = lazycurl_file.with_variables(variables).curl_request(&RequestSettings::default()).ok()?;
+    let name_values = |lines: &[String]| lines.iter()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| !name.trim().is_empty())
+        .map(|(name, value)| json!({ "name": name.trim(), "value": value.trim() }))
+        .collect::<Vec<_>>();
+    let query = request.url.split_once('?')
+        .map(|(_, query)| query.split('#').next().unwrap_or_default())
+        .unwrap_or_default();
+    let query_string = query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            json!({ "name": form_decode(name), "value": form_decode(value) })
+        })
+        .collect::<Vec<_>>();
+
+    let mut har_request = json!({
+        "method": request.method.to_string(),
+        "url": request.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": name_values(&request.headers),
+        "queryString": query_string,
+        "headersSize": -1,
+        "bodySize": request.body.len(),
+    });
+    if !request.body.is_empty() {
+        let mime_type = request.headers.iter()
+            .filter_map(|header| header.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+            .map_or("", |(_, value)| value.trim());
+        har_request["postData"] = json!({ "mimeType": mime_type, "text": String::from_utf8_lossy(&request.body) });
+    }
+
+    let (started, time, response, timings) = match &lazycurl_file.response {
+        Some(saved) => {
+            // The status line is followed by the headers
+            let status_text = saved.headers.first()
+                .and_then(|status_line| status_line.splitn(3, ' ').nth(2))
+                .unwrap_or_default();
+            let mime_type = saved.headers.iter().skip(1)
+                .filter_map(|header| header.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+                .map_or("", |(_, value)| value.trim());
+            let mut content = json!({ "size": saved.body_size, "mimeType": mime_type, "text": saved.body });
+            if saved.body_base64 {
+                content["encoding"] = json!("base64");
+            }
+            let timings = saved.timings;
+            let response = json!({
+                "status": saved.status,
+                "statusText": status_text,
+                "httpVersion": saved.http_version,
+                "cookies": [],
+                "headers": name_values(saved.headers.get(1..).unwrap_or_default()),
+                "content": content,
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": saved.body_size,
+            });
+            let timings = json!({
+                "dns": timings.dns,
+                "connect": timings.connect,
+                "ssl": if timings.ssl > 0.0 { timings.ssl } else { -1.0 },
+                "send": timings.send,
+                "wait": timings.wait,
+                "receive": timings.receive,
+            });
+            (saved.started, saved.timings.total, response, timings)
+        },
+        // Requests saved before responses were kept in history
+        None => {
+            let response = json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            });
+            let timings = json!({ "send": 0, "wait": 0, "receive": 0 });
+            (history_time(path).unwrap_or_else(Utc::now), 0.0, response, timings)
+        },
+    };
+    if let Some(saved) = &lazycurl_file.response {
+        har_request["httpVersion"] = json!(saved.http_version);
+    }
+
+    Some(json!({
+        "startedDateTime": started.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "time": time,
+        "request": har_request,
+        "response": response,
+        "cache": {},
+        "timings": timings,
+    }))
+}
+
+/// The time a history file was saved at, from its name
+fn history_time(path: &Path) -> Option<DateTime<Utc>> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let timestamp = stem.strip_prefix("lazy_curl_request_")?.get(..20)?;
+    let time = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H-%M-%SZ").ok()?;
+    Some(time.and_utc())
+}
@@ -0,0 +1,343 @@
+use std::{collections::{BTreeMap, HashMap}, path::Path, str::FromStr};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{http_method::HTTPMethod, lazycurl_file::LazyCurlFile, utils::{directory::Directory, graphql::GraphQLBody}, variables::Variables};
+
+use super::{add_basic_auth, add_header, add_query_parameter, add_script_comment, add_variables, file_name, read_document, replace_path_variable, set_form_body, set_multipart_body, text, write_environment, ImportReport, RequestWriter};
+
+/// Workspaces, folders, requests and environments, linked by `parentId`
+#[derive(Deserialize)]
+struct Export {
+    #[serde(rename = "__export_format")]
+    format: Option<u32>,
+    #[serde(default)]
+    resources: Vec<Resource>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Resource {
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(rename = "_type")]
+    kind: String,
+    parent_id: Option<String>,
+    #[serde(default)]
+    name: String,
+    meta_sort_key: Option<f64>,
+    #[serde(default)]
+    method: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    body: Value,
+    #[serde(default)]
+    parameters: Vec<Parameter>,
+    #[serde(default)]
+    path_parameters: Vec<Parameter>,
+    #[serde(default)]
+    headers: Vec<Parameter>,
+    #[serde(default)]
+    authentication: Value,
+    /// Values of a folder environment
+    #[serde(default)]
+    environment: Value,
+    /// Values of an environment
+    #[serde(default)]
+    data: Value,
+    #[serde(default)]
+    pre_request_script: String,
+    #[serde(default)]
+    after_response_script: String,
+}
+
+/// Header, query, path and form parameters
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Parameter {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    file_name: String,
+}
+
+/// What a folder hands down to the requests below it
+#[derive(Clone, Default)]
+struct Scope {
+    authentication: Value,
+    headers: Vec<(String, String)>,
+    /// Values of the folder environments, filled into the requests
+    variables: BTreeMap<String, String>,
+}
+
+/// Import an Insomnia v4 export, JSON or YAML, into the collection directory
+pub fn import(path: &Path) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let document = read_document(path)?;
+    if document.get("type").and_then(Value::as_str).is_some_and(|kind| kind.starts_with("collection.insomnia.rest/")) {
+        return Err("Insomnia v5 exports are not supported, export the collection as Insomnia v4".into());
+    }
+    let export: Export = serde_json::from_value(document).map_err(|e| format!("not an Insomnia export: {}", e))?;
+    if export.format != Some(4) {
+        return Err(format!("Insomnia export format {} is not supported, export the collection as Insomnia v4", export.format.map_or_else(|| String::from("unknown"), |format| format.to_string())).into());
+    }
+
+    let mut children = HashMap::<&str, Vec<&Resource>>::new();
+    for resource in &export.resources {
+        children.entry(resource.parent_id.as_deref().unwrap_or_default()).or_default().push(resource);
+    }
+    for resources in children.values_mut() {
+        resources.sort_by(|a, b| a.meta_sort_key.unwrap_or_default().total_cmp(&b.meta_sort_key.unwrap_or_default()));
+    }
+
+    let workspaces = export.resources.iter().filter(|resource| resource.kind == "workspace").collect::<Vec<_>>();
+    if workspaces.is_empty() {
+        return Err("the export has no workspace".into());
+    }
+    let mut report = ImportReport::default();
+    let mut writer = RequestWriter::new();
+    for workspace in workspaces {
+        let directory = Path::new(&Directory::Collection.path()).join(file_name(&workspace.name));
+        import_children(&children, &workspace.id, &directory, &Scope::default(), &workspace.name, &mut writer, &mut report)?;
+        import_environments(&children, workspace, &mut report)?;
+    }
+    Ok(report)
+}
+
+fn import_children(
+    children: &HashMap<&str, Vec<&Resource>>,
+    parent_id: &str,
+    directory: &Path,
+    scope: &Scope,
+    location: &str,
+    writer: &mut RequestWriter,
+    report: &mut ImportReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for resource in children.get(parent_id).into_iter().flatten() {
+        let resource_location = format!("{}/{}", location, resource.name);
+        match resource.kind.as_str() {
+            "request_group" => {
+                let folder_scope = folder_scope(resource, scope);
+                if !resource.pre_request_script.trim().is_empty() || !resource.after_response_script.trim().is_empty() {
+                    report.warn(&resource_location, "scripts of folders are not supported, they were left out");
+                }
+                import_children(children, &resource.id, &directory.join(file_name(&resource.name)), &folder_scope, &resource_location, writer, report)?;
+            },
+            "request" | "websocket_request" => {
+                if let Some(lazycurl_file) = convert_request(resource, scope, &resource_location, report) {
+                    writer.write(directory, &resource.name, &lazycurl_file, report)?;
+                }
+            },
+            "grpc_request" => report.warn(&resource_location, "gRPC requests are not supported, the request was left out"),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// The scope of the folder, its settings over the ones of its parent
+fn folder_scope(folder: &Resource, scope: &Scope) -> Scope {
+    let mut folder_scope = scope.clone();
+    if !is_inherited(&folder.authentication) {
+        folder_scope.authentication = folder.authentication.clone();
+    }
+    folder_scope.headers.extend(headers(&folder.headers).into_iter()
+        .filter_map(|header| header.split_once(':').map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))));
+    flatten("", &folder.environment, &mut folder_scope.variables);
+    folder_scope
+}
+
+/// Requests and folders without authentication of their own use the one of their parent
+fn is_inherited(authentication: &Value) -> bool {
+    match authentication.get("type").and_then(Value::as_str) {
+        Some(kind) => kind == "inherit",
+        None => true,
+    }
+}
+
+fn convert_request(resource: &Resource, scope: &Scope, location: &str, report: &mut ImportReport) -> Option<LazyCurlFile> {
+    let http_method = if resource.kind == "websocket_request" {
+        HTTPMethod::WS
+    } else {
+        let method = if resource.method.is_empty() { String::from("GET") } else { resource.method.to_uppercase() };
+        match HTTPMethod::from_str(&method) {
+            Ok(http_method) if !matches!(http_method, HTTPMethod::WS | HTTPMethod::GRPC | HTTPMethod::GRPCWEB) => http_method,
+            _ => {
+                report.warn(location, format!("method {} is not supported, the request was left out", method));
+                return None;
+            },
+        }
+    };
+
+    let mut url = template(&resource.url);
+    for parameter in resource.path_parameters.iter().filter(|parameter| !parameter.disabled) {
+        url = replace_path_variable(&url, &parameter.name, &template(&text(&parameter.value)));
+    }
+    for parameter in resource.parameters.iter().filter(|parameter| !parameter.disabled && !parameter.name.is_empty()) {
+        add_query_parameter(&mut url, &template(&parameter.name), &template(&text(&parameter.value)));
+    }
+    let mut lazycurl_file = LazyCurlFile::new(url, headers(&resource.headers), http_method);
+
+    let authentication = if is_inherited(&resource.authentication) { &scope.authentication } else { &resource.authentication };
+    convert_auth(authentication, &mut lazycurl_file, location, report);
+    if http_method != HTTPMethod::WS {
+        convert_body(&resource.body, &mut lazycurl_file, location, report);
+    }
+    for (name, value) in &scope.headers {
+        add_header(&mut lazycurl_file.headers, name, value);
+    }
+    for (script, source, stage) in [
+        (&mut lazycurl_file.pre_request_script, &resource.pre_request_script, "pre-request"),
+        (&mut lazycurl_file.post_response_script, &resource.after_response_script, "after-response"),
+    ] {
+        if !source.trim().is_empty() {
+            add_script_comment(script, "Insomnia", source);
+            report.warn(location, format!("the {} script is JavaScript, it was kept as a comment", stage));
+        }
+    }
+
+    if !scope.variables.is_empty() {
+        let variables = Variables { values: scope.variables.clone(), ..Variables::default() };
+        lazycurl_file = lazycurl_file.with_variables(&variables);
+    }
+    let request_text = format!("{}\n{}\n{}", lazycurl_file.url, lazycurl_file.headers.join("\n"), lazycurl_file.body);
+    if request_text.contains("{%") {
+        report.warn(location, "template tags {% ... %} are not supported, they were kept as they are");
+    }
+    Some(lazycurl_file)
+}
+
+fn headers(parameters: &[Parameter]) -> Vec<String> {
+    parameters.iter()
+        .filter(|header| !header.disabled && !header.name.is_empty())
+        .map(|header| format!("{}: {}", template(&header.name), template(&text(&header.value))))
+        .collect()
+}
+
+fn convert_auth(authentication: &Value, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    if authentication.get("disabled").and_then(Value::as_bool) == Some(true) {
+        return;
+    }
+    let field = |name: &str| template(&authentication.get(name).map(text).unwrap_or_default());
+    match authentication.get("type").and_then(Value::as_str).unwrap_or("none") {
+        "none" | "inherit" => {},
+        "bearer" => {
+            let prefix = field("prefix");
+            let prefix = if prefix.is_empty() { String::from("Bearer") } else { prefix };
+            add_header(&mut lazycurl_file.headers, "Authorization", &format!("{} {}", prefix, field("token")));
+        },
+        "basic" => add_basic_auth(lazycurl_file, &field("username"), &field("password")),
+        "apikey" => match field("addTo").as_str() {
+            "queryParams" => add_query_parameter(&mut lazycurl_file.url, &field("key"), &field("value")),
+            "cookie" => add_header(&mut lazycurl_file.headers, "Cookie", &format!("{}={}", field("key"), field("value"))),
+            _ => add_header(&mut lazycurl_file.headers, &field("key"), &field("value")),
+        },
+        kind => report.warn(location, format!("{} auth is not supported, set its header by hand", kind)),
+    }
+}
+
+fn convert_body(body: &Value, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    let mime_type = body.get("mimeType").and_then(Value::as_str).unwrap_or_default();
+    let body_text = template(&body.get("text").map(text).unwrap_or_default());
+    let parameters = body.get("params")
+        .and_then(|params| serde_json::from_value::<Vec<Parameter>>(params.clone()).ok())
+        .unwrap_or_default();
+    let fields = |report: &mut ImportReport| {
+        let mut fields = Vec::new();
+        for parameter in parameters.iter().filter(|parameter| !parameter.disabled && !parameter.name.is_empty()) {
+            if parameter.kind.as_deref() == Some("file") {
+                report.warn(location, format!("file field {} ({}) of the form was left out", parameter.name, parameter.file_name));
+            } else {
+                fields.push((template(&parameter.name), template(&text(&parameter.value))));
+            }
+        }
+        fields
+    };
+
+    match mime_type {
+        "application/x-www-form-urlencoded" => set_form_body(lazycurl_file, &fields(report)),
+        "multipart/form-data" => set_multipart_body(lazycurl_file, &fields(report)),
+        "application/graphql" => {
+            let graphql = serde_json::from_str::<Value>(&body_text).unwrap_or_default();
+            let variables = match graphql.get("variables") {
+                Some(Value::String(variables)) => variables.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(variables) => serde_json::to_string_pretty(variables).unwrap_or_default(),
+            };
+            lazycurl_file.graphql = Some(GraphQLBody {
+                query: graphql.get("query").map(text).unwrap_or_default(),
+                variables,
+                operation_name: graphql.get("operationName").and_then(Value::as_str).map(str::to_string),
+            });
+        },
+        _ if body.get("fileName").is_some() => {
+            report.warn(location, format!("the file body {} was left out", body.get("fileName").map(text).unwrap_or_default()));
+        },
+        _ => {
+            lazycurl_file.body = body_text;
+            if !mime_type.is_empty() && !lazycurl_file.body.is_empty() {
+                add_header(&mut lazycurl_file.headers, "Content-Type", mime_type);
+            }
+        },
+    }
+}
+
+/// The base environment of the workspace goes to `variables.json`, its
+/// sub environments to the environments directory
+fn import_environments(children: &HashMap<&str, Vec<&Resource>>, workspace: &Resource, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let environments = children.get(workspace.id.as_str()).into_iter().flatten().filter(|resource| resource.kind == "environment");
+    for base in environments {
+        let mut values = BTreeMap::new();
+        flatten("", &base.data, &mut values);
+        add_variables(&values, report)?;
+
+        for environment in children.get(base.id.as_str()).into_iter().flatten().filter(|resource| resource.kind == "environment") {
+            let mut values = BTreeMap::new();
+            flatten("", &environment.data, &mut values);
+            let name = file_name(&environment.name);
+            write_environment(&name, &values, report)?;
+        }
+    }
+    Ok(())
+}
+
+/// Nested values are named by their path, e.g. `{{ _.api.url }}` is `api.url`
+fn flatten(prefix: &str, value: &Value, values: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object {
+                let name = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+                flatten(&name, value, values);
+            }
+        },
+        Value::Null if prefix.is_empty() => {},
+        value => {
+            values.insert(prefix.to_string(), template(&text(value)));
+        },
+    }
+}
+
+/// `{{ _.name }}` and `{{ name }}` become `{{name}}`
+fn template(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + length].trim();
+        let name = name.strip_prefix("_.").unwrap_or(name);
+        result.push_str(&rest[..start]);
+        result.push_str(&format!("{{{{{}}}}}", name));
+        rest = &rest[start + length + 4..];
+    }
+    result.push_str(rest);
+    result
+}
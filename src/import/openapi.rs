@@ -0,0 +1,355 @@
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use serde_json::{json, Map, Value};
+
+use crate::{http_method::HTTPMethod, lazycurl_file::LazyCurlFile, utils::directory::Directory};
+
+use super::{add_header, add_query_parameter, add_variables, file_name, form_encode, read_document, text, write_environment, ImportReport, RequestWriter};
+
+/// Methods of a path item, in the order operations are imported
+const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+/// References are followed this many times at most, they may point to each other
+const MAX_REFERENCE_HOPS: usize = 8;
+
+/// An OpenAPI 3 or Swagger 2 document, `$ref`s are looked up in it
+struct Spec {
+    root: Value,
+    is_swagger: bool,
+}
+
+impl Spec {
+    /// The value, or the value its local `$ref` points to
+    fn resolve<'a>(&'a self, value: &'a Value) -> &'a Value {
+        let mut value = value;
+        for _ in 0..MAX_REFERENCE_HOPS {
+            match value.get("$ref").and_then(Value::as_str).and_then(|reference| reference.strip_prefix('#')) {
+                Some(pointer) => match self.root.pointer(pointer) {
+                    Some(target) => value = target,
+                    None => return &Value::Null,
+                },
+                None => return value,
+            }
+        }
+        value
+    }
+}
+
+/// Import the operations of an OpenAPI 3 or Swagger 2 document, in YAML or
+/// JSON, into `collection/<title>/<tag>/`
+pub fn import(path: &Path) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let root = read_document(path)?;
+    let is_swagger = match (root.get("openapi").and_then(Value::as_str), root.get("swagger")) {
+        (Some(version), _) if version.starts_with("3.") => false,
+        (Some(version), _) => return Err(format!("OpenAPI {} is not supported, only 3.x", version).into()),
+        (None, Some(_)) => true,
+        (None, None) => return Err("not an OpenAPI or Swagger document".into()),
+    };
+    let spec = Spec { root, is_swagger };
+    let mut report = ImportReport::default();
+
+    let title = spec.root.pointer("/info/title").and_then(Value::as_str).unwrap_or("OpenAPI");
+    let base_url_variable = format!("{}_base_url", variable_name(title));
+    import_servers(&spec, title, &base_url_variable, &mut report)?;
+
+    let directory = Path::new(&Directory::Collection.path()).join(file_name(title));
+    let mut writer = RequestWriter::new();
+    let paths = spec.root.get("paths").and_then(Value::as_object).cloned().unwrap_or_default();
+    for (path, path_item) in &paths {
+        let path_item = spec.resolve(path_item);
+        for method in METHODS {
+            let Some(operation) = path_item.get(method) else {
+                continue;
+            };
+            let location = format!("{} {}", method.to_uppercase(), path);
+            let Ok(http_method) = HTTPMethod::from_str(&method.to_uppercase()) else {
+                report.warn(&location, "the method is not supported, the operation was left out");
+                continue;
+            };
+            let lazycurl_file = convert_operation(&spec, path, path_item, operation, http_method, &base_url_variable, &mut report);
+            let name = operation.get("operationId")
+                .or_else(|| operation.get("summary"))
+                .and_then(Value::as_str)
+                .map_or_else(|| location.clone(), str::to_string);
+            let operation_directory = match operation.pointer("/tags/0").and_then(Value::as_str) {
+                Some(tag) => directory.join(file_name(tag)),
+                None => directory.clone(),
+            };
+            writer.write(&operation_directory, &name, &lazycurl_file, &mut report)?;
+        }
+    }
+    Ok(report)
+}
+
+/// The first server goes to `variables.json`, with several servers every
+/// one of them also gets an environment to switch between them
+fn import_servers(spec: &Spec, title: &str, base_url_variable: &str, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let servers = if spec.is_swagger {
+        let host = spec.root.get("host").and_then(Value::as_str).unwrap_or("localhost");
+        let base_path = spec.root.get("basePath").and_then(Value::as_str).unwrap_or("");
+        let schemes = spec.root.get("schemes").and_then(Value::as_array).cloned().unwrap_or_default();
+        let schemes = if schemes.is_empty() { vec![json!("https")] } else { schemes };
+        schemes.iter()
+            .filter_map(Value::as_str)
+            .map(|scheme| (scheme.to_string(), format!("{}://{}{}", scheme, host, base_path.trim_end_matches('/'))))
+            .collect::<Vec<_>>()
+    } else {
+        spec.root.get("servers").and_then(Value::as_array).into_iter().flatten()
+            .map(|server| {
+                let url = server_url(server);
+                let name = server.get("description").and_then(Value::as_str).map_or_else(|| url.clone(), str::to_string);
+                (name, url)
+            })
+            .collect()
+    };
+
+    let Some((_, first_url)) = servers.first() else {
+        report.warn(title, format!("no servers, set {} by hand", base_url_variable));
+        return Ok(());
+    };
+    if !first_url.contains("://") {
+        report.warn(title, format!("the server URL {} is relative, complete {} by hand", first_url, base_url_variable));
+    }
+    add_variables(&BTreeMap::from([(base_url_variable.to_string(), first_url.clone())]), report)?;
+    if servers.len() > 1 {
+        for (name, url) in &servers {
+            let name = file_name(&format!("{} - {}", title, name));
+            write_environment(&name, &BTreeMap::from([(base_url_variable.to_string(), url.clone())]), report)?;
+        }
+    }
+    Ok(())
+}
+
+/// The server URL with its `{variables}` set to their defaults
+fn server_url(server: &Value) -> String {
+    let mut url = server.get("url").and_then(Value::as_str).unwrap_or_default().to_string();
+    if let Some(variables) = server.get("variables").and_then(Value::as_object) {
+        for (name, variable) in variables {
+            let default = variable.get("default").map(text).unwrap_or_default();
+            url = url.replace(&format!("{{{}}}", name), &default);
+        }
+    }
+    url.trim_end_matches('/').to_string()
+}
+
+fn convert_operation(spec: &Spec, path: &str, path_item: &Value, operation: &Value, http_method: HTTPMethod, base_url_variable: &str, report: &mut ImportReport) -> LazyCurlFile {
+    let location = &format!("{} {}", http_method, path);
+    let mut url = format!("{{{{{}}}}}{}", base_url_variable, path_placeholders(path));
+    let mut lazycurl_file = LazyCurlFile::new(String::new(), Vec::new(), http_method);
+
+    // Operation parameters override the path ones with the same name and location
+    let key = |parameter: &Value| (text(parameter.get("name").unwrap_or(&Value::Null)), text(parameter.get("in").unwrap_or(&Value::Null)));
+    let mut parameters: Vec<&Value> = Vec::new();
+    for parameter in parameter_list(spec, path_item).into_iter().chain(parameter_list(spec, operation)) {
+        parameters.retain(|existing| key(existing) != key(parameter));
+        parameters.push(parameter);
+    }
+
+    let mut form_parameters = Map::new();
+    for parameter in &parameters {
+        let name = parameter.get("name").and_then(Value::as_str).unwrap_or_default();
+        let is_required = parameter.get("required").and_then(Value::as_bool).unwrap_or(false);
+        let placeholder = format!("{{{{{}}}}}", name);
+        match parameter.get("in").and_then(Value::as_str) {
+            Some("query") if is_required => add_query_parameter(&mut url, name, &placeholder),
+            Some("header") if is_required => add_header(&mut lazycurl_file.headers, name, &placeholder),
+            Some("body") => {
+                let value = parameter.get("schema").map_or(Value::Null, |schema| example(spec, schema, &mut Vec::new()));
+                set_body(&mut lazycurl_file, swagger_content_type(spec, operation, "consumes"), &value, location, report);
+            },
+            Some("formData") => {
+                if parameter.get("type").and_then(Value::as_str) == Some("file") {
+                    report.warn(location, format!("file parameter {} was left out", name));
+                } else {
+                    form_parameters.insert(name.to_string(), example(spec, parameter, &mut Vec::new()));
+                }
+            },
+            _ => {},
+        }
+    }
+    if !form_parameters.is_empty() {
+        set_body(&mut lazycurl_file, "application/x-www-form-urlencoded", &Value::Object(form_parameters), location, report);
+    }
+
+    if let Some(request_body) = operation.get("requestBody").map(|request_body| spec.resolve(request_body)) {
+        convert_request_body(spec, request_body, &mut lazycurl_file, location, report);
+    }
+    if spec.is_swagger {
+        if let Some(content_type) = operation.get("produces").or_else(|| spec.root.get("produces")).and_then(|produces| produces.get(0)).and_then(Value::as_str) {
+            add_header(&mut lazycurl_file.headers, "Accept", content_type);
+        }
+    }
+    convert_security(spec, operation, &mut lazycurl_file, &mut url, location, report);
+    lazycurl_file.url = url;
+    lazycurl_file
+}
+
+fn parameter_list<'a>(spec: &'a Spec, item: &'a Value) -> Vec<&'a Value> {
+    item.get("parameters").and_then(Value::as_array).into_iter().flatten()
+        .map(|parameter| spec.resolve(parameter))
+        .collect()
+}
+
+/// `/users/{id}` as `/users/{{id}}`
+fn path_placeholders(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(&format!("{{{{{}}}}}", &rest[start + 1..start + length]));
+        rest = &rest[start + length + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Swagger 2 content types are listed by the operation or the document
+fn swagger_content_type<'a>(spec: &'a Spec, operation: &'a Value, key: &str) -> &'a str {
+    operation.get(key).or_else(|| spec.root.get(key))
+        .and_then(|types| types.get(0))
+        .and_then(Value::as_str)
+        .unwrap_or("application/json")
+}
+
+/// Use the JSON content when there is one, its example or else an example
+/// made from its schema
+fn convert_request_body(spec: &Spec, request_body: &Value, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    let Some(content) = request_body.get("content").and_then(Value::as_object) else {
+        return;
+    };
+    let Some((content_type, media_type)) = content.iter()
+        .find(|(content_type, _)| is_json(content_type))
+        .or_else(|| content.iter().next()) else {
+        return;
+    };
+
+    let value = media_type.get("example").cloned()
+        .or_else(|| media_type.get("examples")
+            .and_then(Value::as_object)
+            .and_then(|examples| examples.values().next())
+            .map(|example| spec.resolve(example))
+            .and_then(|example| example.get("value").cloned()))
+        .unwrap_or_else(|| media_type.get("schema").map_or(Value::Null, |schema| example(spec, schema, &mut Vec::new())));
+    set_body(lazycurl_file, content_type, &value, location, report);
+}
+
+fn set_body(lazycurl_file: &mut LazyCurlFile, content_type: &str, value: &Value, location: &str, report: &mut ImportReport) {
+    lazycurl_file.body = if is_json(content_type) {
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    } else if content_type == "application/x-www-form-urlencoded" {
+        value.as_object().into_iter().flatten()
+            .map(|(name, value)| format!("{}={}", form_encode(name), form_encode(&text(value))))
+            .collect::<Vec<_>>()
+            .join("&")
+    } else if let Value::String(body) = value {
+        body.clone()
+    } else {
+        report.warn(location, format!("no example for the {} body", content_type));
+        String::new()
+    };
+    add_header(&mut lazycurl_file.headers, "Content-Type", content_type);
+}
+
+fn is_json(content_type: &str) -> bool {
+    content_type == "application/json" || content_type.ends_with("+json")
+}
+
+/// An example of the schema: its example or default, or else a value of its
+/// type. `references` are the schemas being made, a schema within itself is null.
+fn example(spec: &Spec, schema: &Value, references: &mut Vec<String>) -> Value {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if references.iter().any(|made| made == reference) {
+            return Value::Null;
+        }
+        references.push(reference.to_string());
+        let value = example(spec, spec.resolve(schema), references);
+        references.pop();
+        return value;
+    }
+    if let Some(value) = schema.get("example").or_else(|| schema.get("default")).or_else(|| schema.pointer("/enum/0")) {
+        return value.clone();
+    }
+    if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for schema in schemas {
+            if let Value::Object(object) = example(spec, schema, references) {
+                merged.extend(object);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(schema) = schema.pointer("/oneOf/0").or_else(|| schema.pointer("/anyOf/0")) {
+        return example(spec, schema, references);
+    }
+
+    let kind = match schema.get("type") {
+        // OpenAPI 3.1 lists the types, null among them
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).find(|kind| *kind != "null"),
+        Some(kind) => kind.as_str(),
+        None if schema.get("properties").is_some() => Some("object"),
+        None => None,
+    };
+    match kind {
+        Some("object") => Value::Object(schema.get("properties").and_then(Value::as_object).into_iter().flatten()
+            .map(|(name, property)| (name.clone(), example(spec, property, references)))
+            .collect()),
+        Some("array") => Value::Array(schema.get("items").map(|items| example(spec, items, references)).into_iter().collect()),
+        Some("integer") => json!(0),
+        Some("number") => json!(0.0),
+        Some("boolean") => json!(false),
+        Some("string") => json!(match schema.get("format").and_then(Value::as_str) {
+            Some("date-time") => "2024-01-01T00:00:00Z",
+            Some("date") => "2024-01-01",
+            Some("email") => "user@example.com",
+            Some("uuid") => "00000000-0000-0000-0000-000000000000",
+            Some("uri") => "https://example.com",
+            _ => "string",
+        }),
+        _ => Value::Null,
+    }
+}
+
+/// Bearer and API key schemes become placeholders, others are reported
+fn convert_security(spec: &Spec, operation: &Value, lazycurl_file: &mut LazyCurlFile, url: &mut String, location: &str, report: &mut ImportReport) {
+    let Some(requirement) = operation.get("security").or_else(|| spec.root.get("security"))
+        .and_then(|security| security.get(0))
+        .and_then(Value::as_object) else {
+        return;
+    };
+    let schemes_pointer = if spec.is_swagger { "/securityDefinitions" } else { "/components/securitySchemes" };
+    for name in requirement.keys() {
+        let Some(scheme) = spec.root.pointer(schemes_pointer).and_then(|schemes| schemes.get(name)).map(|scheme| spec.resolve(scheme)) else {
+            continue;
+        };
+        let kind = scheme.get("type").and_then(Value::as_str).unwrap_or_default();
+        let http_scheme = scheme.get("scheme").and_then(Value::as_str).unwrap_or_default().to_lowercase();
+        match kind {
+            "http" if http_scheme == "bearer" => add_header(&mut lazycurl_file.headers, "Authorization", "Bearer {{token}}"),
+            "http" if http_scheme == "basic" => add_header(&mut lazycurl_file.headers, "Authorization", "Basic {{basic_credentials}}"),
+            "basic" => add_header(&mut lazycurl_file.headers, "Authorization", "Basic {{basic_credentials}}"),
+            "apiKey" => {
+                let key = scheme.get("name").and_then(Value::as_str).unwrap_or(name);
+                let placeholder = format!("{{{{{}}}}}", variable_name(name));
+                match scheme.get("in").and_then(Value::as_str) {
+                    Some("query") => add_query_parameter(url, key, &placeholder),
+                    Some("header") => add_header(&mut lazycurl_file.headers, key, &placeholder),
+                    _ => report.warn(location, format!("API key {} in a cookie is not supported", name)),
+                }
+            },
+            kind => report.warn(location, format!("{} security scheme {} is not supported, set its header by hand", kind, name)),
+        }
+        // One scheme of the requirement is enough to authenticate
+        return;
+    }
+}
+
+/// A variable name from a title, e.g. `pet_store` from "Pet Store"
+fn variable_name(title: &str) -> String {
+    let name = title.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect::<String>();
+    let name = name.trim_matches('_');
+    if name.is_empty() { String::from("api") } else { name.to_string() }
+}
@@ -0,0 +1,419 @@
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{http_method::HTTPMethod, lazycurl_file::LazyCurlFile, utils::{directory::Directory, graphql::GraphQLBody}};
+
+use super::{add_basic_auth, add_header, add_query_parameter, add_script_comment, add_variables, file_name, replace_path_variable, set_form_body, set_multipart_body, text, warn_dynamic_variables, write_environment, ImportReport, RequestWriter};
+
+#[derive(Deserialize)]
+struct Collection {
+    info: Info,
+    #[serde(default)]
+    item: Vec<Item>,
+    auth: Option<Auth>,
+    #[serde(default)]
+    variable: Vec<Parameter>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    name: String,
+}
+
+/// A folder when it has items, a request otherwise
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default)]
+    name: String,
+    item: Option<Vec<Item>>,
+    request: Option<RequestField>,
+    auth: Option<Auth>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+/// A request may be given as its URL only
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RequestField {
+    Url(String),
+    Request(Box<Request>),
+}
+
+#[derive(Deserialize)]
+struct Request {
+    method: Option<String>,
+    url: Option<Value>,
+    #[serde(default)]
+    header: Value,
+    body: Option<Body>,
+    auth: Option<Auth>,
+}
+
+#[derive(Deserialize)]
+struct Body {
+    mode: Option<String>,
+    raw: Option<String>,
+    #[serde(default)]
+    urlencoded: Vec<Parameter>,
+    #[serde(default)]
+    formdata: Vec<Parameter>,
+    file: Option<Value>,
+    graphql: Option<GraphQL>,
+    options: Option<Value>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Deserialize)]
+struct GraphQL {
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    variables: Value,
+}
+
+/// Key and value of headers, query and form parameters and variables
+#[derive(Deserialize)]
+struct Parameter {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    src: Value,
+}
+
+#[derive(Deserialize)]
+struct Auth {
+    #[serde(rename = "type")]
+    kind: String,
+    /// Parameters are under the name of the type
+    #[serde(flatten)]
+    parameters: BTreeMap<String, Value>,
+}
+
+impl Auth {
+    fn parameter(&self, key: &str) -> String {
+        match self.parameters.get(&self.kind) {
+            // v2.1 lists the parameters, v2.0 maps them
+            Some(Value::Array(parameters)) => parameters.iter()
+                .find(|parameter| parameter.get("key").and_then(Value::as_str) == Some(key))
+                .map(|parameter| text(parameter.get("value").unwrap_or(&Value::Null)))
+                .unwrap_or_default(),
+            Some(Value::Object(parameters)) => parameters.get(key).map(text).unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Event {
+    listen: String,
+    script: Option<Script>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Deserialize)]
+struct Script {
+    #[serde(default)]
+    exec: Value,
+}
+
+#[derive(Deserialize)]
+struct Environment {
+    name: String,
+    values: Vec<EnvironmentValue>,
+}
+
+#[derive(Deserialize)]
+struct EnvironmentValue {
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Import a Postman v2.0 or v2.1 collection into the collection directory,
+/// or a Postman environment into the environments
+pub fn import(path: &Path) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut report = ImportReport::default();
+    if value.get("info").is_some() {
+        import_collection(serde_json::from_value(value)?, &mut report)?;
+    } else if value.get("values").is_some() {
+        import_environment(serde_json::from_value(value)?, &mut report)?;
+    } else if value.get("requests").is_some() {
+        return Err("Postman v1 collections are not supported, export the collection as v2.1".into());
+    } else {
+        return Err("not a Postman collection or environment".into());
+    }
+    Ok(report)
+}
+
+fn import_collection(collection: Collection, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let directory = Path::new(&Directory::Collection.path()).join(file_name(&collection.info.name));
+    warn_scripts(&collection.event, &collection.info.name, report);
+    import_items(&collection.item, &directory, collection.auth.as_ref(), "", &mut RequestWriter::new(), report)?;
+
+    let variables = collection.variable.iter()
+        .filter(|variable| !variable.disabled && !variable.key.is_empty())
+        .map(|variable| (variable.key.clone(), text(&variable.value)))
+        .collect::<BTreeMap<_, _>>();
+    add_variables(&variables, report)
+}
+
+fn import_items(items: &[Item], directory: &Path, auth: Option<&Auth>, location: &str, writer: &mut RequestWriter, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    for item in items {
+        let item_location = if location.is_empty() { item.name.clone() } else { format!("{}/{}", location, item.name) };
+        let item_auth = match &item.auth {
+            Some(item_auth) if item_auth.kind != "inherit" => Some(item_auth),
+            _ => auth,
+        };
+        match (&item.item, &item.request) {
+            (Some(children), _) => {
+                warn_scripts(&item.event, &item_location, report);
+                import_items(children, &directory.join(file_name(&item.name)), item_auth, &item_location, writer, report)?;
+            },
+            (None, Some(request)) => {
+                if let Some(lazycurl_file) = convert_request(request, &item.event, item_auth, &item_location, report) {
+                    writer.write(directory, &item.name, &lazycurl_file, report)?;
+                }
+            },
+            (None, None) => report.warn(&item_location, "neither a folder nor a request"),
+        }
+    }
+    Ok(())
+}
+
+fn convert_request(request: &RequestField, events: &[Event], auth: Option<&Auth>, location: &str, report: &mut ImportReport) -> Option<LazyCurlFile> {
+    let request = match request {
+        RequestField::Url(url) => return Some(LazyCurlFile::new(url.clone(), Vec::new(), HTTPMethod::GET)),
+        RequestField::Request(request) => request,
+    };
+
+    let method = request.method.as_deref().unwrap_or("GET").to_uppercase();
+    let http_method = match HTTPMethod::from_str(&method) {
+        Ok(http_method) if !matches!(http_method, HTTPMethod::WS | HTTPMethod::GRPC | HTTPMethod::GRPCWEB) => http_method,
+        _ => {
+            report.warn(location, format!("method {} is not supported, the request was left out", method));
+            return None;
+        },
+    };
+    let mut lazycurl_file = LazyCurlFile::new(url_text(request.url.as_ref()), headers(&request.header), http_method);
+
+    let auth = match &request.auth {
+        Some(request_auth) if request_auth.kind != "inherit" => Some(request_auth),
+        _ => auth,
+    };
+    if let Some(auth) = auth {
+        convert_auth(auth, &mut lazycurl_file, location, report);
+    }
+    if let Some(body) = &request.body {
+        convert_body(body, &mut lazycurl_file, location, report);
+    }
+    convert_scripts(events, &mut lazycurl_file, location, report);
+    warn_dynamic_variables(&lazycurl_file, location, report);
+    Some(lazycurl_file)
+}
+
+/// The raw URL, or the URL built from its parts, with the `:name` path
+/// variables filled in
+fn url_text(url: Option<&Value>) -> String {
+    let Some(Value::Object(url)) = url else {
+        return url.map(text).unwrap_or_default();
+    };
+
+    let mut url_text = match url.get("raw").and_then(Value::as_str) {
+        Some(raw) => raw.to_string(),
+        None => {
+            let mut built = url.get("protocol").and_then(Value::as_str).map(|protocol| format!("{}://", protocol)).unwrap_or_default();
+            built.push_str(&joined(url.get("host"), "."));
+            if let Some(port) = url.get("port").and_then(Value::as_str) {
+                built.push_str(&format!(":{}", port));
+            }
+            let path = joined(url.get("path"), "/");
+            if !path.is_empty() {
+                built.push_str(&format!("/{}", path));
+            }
+            for parameter in parameters(url.get("query")).iter().filter(|parameter| !parameter.disabled) {
+                built.push(if built.contains('?') { '&' } else { '?' });
+                built.push_str(&format!("{}={}", parameter.key, text(&parameter.value)));
+            }
+            built
+        },
+    };
+
+    for variable in parameters(url.get("variable")) {
+        let value = text(&variable.value);
+        let value = if value.is_empty() { format!("{{{{{}}}}}", variable.key) } else { value };
+        url_text = replace_path_variable(&url_text, &variable.key, &value);
+    }
+    url_text
+}
+
+/// Host and path are arrays of segments, or strings
+fn joined(value: Option<&Value>, separator: &str) -> String {
+    match value {
+        Some(Value::Array(segments)) => segments.iter()
+            .map(|segment| segment.get("value").map_or_else(|| text(segment), text))
+            .collect::<Vec<_>>()
+            .join(separator),
+        Some(value) => text(value),
+        None => String::new(),
+    }
+}
+
+fn parameters(value: Option<&Value>) -> Vec<Parameter> {
+    value.and_then(|value| serde_json::from_value(value.clone()).ok()).unwrap_or_default()
+}
+
+/// Headers are a list, or a string with one header per line
+fn headers(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(headers) => headers.lines()
+            .filter(|line| line.contains(':'))
+            .map(|line| line.trim().to_string())
+            .collect(),
+        value => parameters(Some(value)).iter()
+            .filter(|header| !header.disabled && !header.key.is_empty())
+            .map(|header| format!("{}: {}", header.key, text(&header.value)))
+            .collect(),
+    }
+}
+
+fn convert_auth(auth: &Auth, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    match auth.kind.as_str() {
+        "noauth" => {},
+        "bearer" => add_header(&mut lazycurl_file.headers, "Authorization", &format!("Bearer {}", auth.parameter("token"))),
+        "basic" => add_basic_auth(lazycurl_file, &auth.parameter("username"), &auth.parameter("password")),
+        "apikey" => {
+            let key = auth.parameter("key");
+            let value = auth.parameter("value");
+            if auth.parameter("in") == "query" {
+                add_query_parameter(&mut lazycurl_file.url, &key, &value);
+            } else {
+                add_header(&mut lazycurl_file.headers, &key, &value);
+            }
+        },
+        kind => report.warn(location, format!("{} auth is not supported, set its header by hand", kind)),
+    }
+}
+
+fn convert_body(body: &Body, lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    if body.disabled {
+        return;
+    }
+    match body.mode.as_deref() {
+        Some("raw") => {
+            lazycurl_file.body = body.raw.clone().unwrap_or_default();
+            let language = body.options.as_ref()
+                .and_then(|options| options.pointer("/raw/language"))
+                .and_then(Value::as_str);
+            let content_type = match language {
+                Some("json") => Some("application/json"),
+                Some("xml") => Some("application/xml"),
+                Some("html") => Some("text/html"),
+                Some("javascript") => Some("application/javascript"),
+                Some("text") => Some("text/plain"),
+                _ => None,
+            };
+            if let (Some(content_type), false) = (content_type, lazycurl_file.body.is_empty()) {
+                add_header(&mut lazycurl_file.headers, "Content-Type", content_type);
+            }
+        },
+        Some("urlencoded") => {
+            let fields = body.urlencoded.iter()
+                .filter(|parameter| !parameter.disabled)
+                .map(|parameter| (parameter.key.clone(), text(&parameter.value)))
+                .collect::<Vec<_>>();
+            set_form_body(lazycurl_file, &fields);
+        },
+        Some("formdata") => {
+            let mut fields = Vec::new();
+            for parameter in body.formdata.iter().filter(|parameter| !parameter.disabled) {
+                if parameter.kind.as_deref() == Some("file") {
+                    report.warn(location, format!("file field {} ({}) of the form was left out", parameter.key, text(&parameter.src)));
+                } else {
+                    fields.push((parameter.key.clone(), text(&parameter.value)));
+                }
+            }
+            set_multipart_body(lazycurl_file, &fields);
+        },
+        Some("graphql") => {
+            let graphql = body.graphql.as_ref();
+            let variables = match graphql.map(|graphql| &graphql.variables) {
+                Some(Value::String(variables)) => variables.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(variables) => serde_json::to_string_pretty(variables).unwrap_or_default(),
+            };
+            lazycurl_file.graphql = Some(GraphQLBody {
+                query: graphql.map(|graphql| graphql.query.clone()).unwrap_or_default(),
+                variables,
+                operation_name: None,
+            });
+        },
+        Some("file") => {
+            let src = body.file.as_ref().and_then(|file| file.get("src")).map(text).unwrap_or_default();
+            report.warn(location, format!("the file body {} was left out", src));
+        },
+        Some(mode) => report.warn(location, format!("{} body is not supported", mode)),
+        None => {},
+    }
+}
+
+/// Postman scripts are JavaScript, they are kept as comments to be ported
+fn convert_scripts(events: &[Event], lazycurl_file: &mut LazyCurlFile, location: &str, report: &mut ImportReport) {
+    for event in events.iter().filter(|event| !event.disabled) {
+        let Some(source) = event.script.as_ref().map(|script| script_source(&script.exec)).filter(|source| !source.trim().is_empty()) else {
+            continue;
+        };
+        let (script, stage) = match event.listen.as_str() {
+            "prerequest" => (&mut lazycurl_file.pre_request_script, "pre-request"),
+            "test" => (&mut lazycurl_file.post_response_script, "test"),
+            _ => continue,
+        };
+        add_script_comment(script, "Postman", &source);
+        report.warn(location, format!("the {} script is JavaScript, it was kept as a comment", stage));
+    }
+}
+
+fn warn_scripts(events: &[Event], location: &str, report: &mut ImportReport) {
+    for event in events.iter().filter(|event| !event.disabled && event.script.as_ref().is_some_and(|script| !script_source(&script.exec).trim().is_empty())) {
+        report.warn(location, format!("{} scripts of collections and folders are not supported, it was left out", event.listen));
+    }
+}
+
+fn script_source(exec: &Value) -> String {
+    match exec {
+        Value::Array(lines) => lines.iter().map(text).collect::<Vec<_>>().join("\n"),
+        exec => text(exec),
+    }
+}
+
+fn import_environment(environment: Environment, report: &mut ImportReport) -> Result<(), Box<dyn std::error::Error>> {
+    let name = file_name(&environment.name);
+    let values = environment.values.iter()
+        .filter(|value| value.enabled && !value.key.is_empty())
+        .map(|value| (value.key.clone(), text(&value.value)))
+        .collect::<BTreeMap<_, _>>();
+    for value in environment.values.iter().filter(|value| !value.enabled) {
+        report.warn(&name, format!("the disabled value {} was left out", value.key));
+    }
+    write_environment(&name, &values, report)?;
+    Ok(())
+}
@@ -0,0 +1,496 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+use tui_textarea::{Input, Key};
+
+/// Where a key is pressed, every context has its own bindings
+#[derive(Deserialize, Display, EnumIter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Context {
+    /// No window selected
+    Main,
+    Url,
+    Headers,
+    Body,
+    History,
+    Response,
+    Settings,
+    Grpc,
+    /// The Tests, Extract, Pre-request and Post-response tabs
+    Rules,
+    Websocket,
+    Runner,
+    Variables,
+}
+
+impl Context {
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Main => "main screen",
+            Context::Url => "URL window",
+            Context::Headers => "Headers tab",
+            Context::Body => "Body tab",
+            Context::History => "History window",
+            Context::Response => "Response window",
+            Context::Settings => "Settings tab",
+            Context::Grpc => "gRPC tab",
+            Context::Rules => "Tests, Extract and script tabs",
+            Context::Websocket => "WebSocket window",
+            Context::Runner => "collection runner",
+            Context::Variables => "variables",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<(Command, &'static [&'static str])> {
+        use Command::*;
+        let windows: [(Command, &'static [&'static str]); 4] = [
+            (History, &["h"]),
+            (Url, &["1"]),
+            (Parameters, &["2"]),
+            (Response, &["3"]),
+        ];
+        let mut bindings = match self {
+            Context::Main => vec![
+                (Quit, &["q"][..]),
+                (History, &["h"]),
+                (Url, &["1"]),
+                (Parameters, &["2"]),
+                (Response, &["3"]),
+                (Stop, &["x"]),
+                (Runner, &["r"]),
+                (SaveToFile, &["w"]),
+                (Variables, &["v"]),
+            ],
+            Context::Url => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Edit, &["e"]),
+                    (Send, &["Enter"]),
+                    (PreviousMethod, &["["]),
+                    (NextMethod, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Headers => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Down, &["j"]),
+                    (Up, &["k"]),
+                    (Edit, &["e"]),
+                    (Add, &["a"]),
+                    (Delete, &["d"]),
+                    (PreviousTab, &["["]),
+                    (NextTab, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Body => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Edit, &["e"]),
+                    (ToggleMode, &["m"]),
+                    (SwitchEditor, &["Tab"]),
+                    (NextOperation, &["o"]),
+                    (Introspect, &["i"]),
+                    (PreviousTab, &["["]),
+                    (NextTab, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::History => vec![
+                (Deselect, &["Esc"][..]),
+                (Load, &["l", "Enter", "Space"]),
+                (Down, &["j"]),
+                (Up, &["k"]),
+                (SwitchList, &["Tab"]),
+            ],
+            Context::Response => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Down, &["j"]),
+                    (Up, &["k"]),
+                    (PreviousView, &["["]),
+                    (NextView, &["]"]),
+                    (SaveBody, &["s"]),
+                    (Charset, &["c"]),
+                    (Filter, &["/"]),
+                    (Follow, &["G"]),
+                    (Stop, &["x"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Settings => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Down, &["j"]),
+                    (Up, &["k"]),
+                    (Edit, &["e"]),
+                    (Clear, &["d"]),
+                    (Cycle, &["Space"]),
+                    (PreviousTab, &["["]),
+                    (NextTab, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Grpc => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Down, &["j"]),
+                    (Up, &["k"]),
+                    (Edit, &["e"]),
+                    (Cycle, &["Space"]),
+                    (LoadServices, &["r"]),
+                    (Template, &["n"]),
+                    (PreviousTab, &["["]),
+                    (NextTab, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Rules => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Edit, &["e"]),
+                    (PreviousTab, &["["]),
+                    (NextTab, &["]"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Websocket => [
+                vec![
+                    (Deselect, &["Esc"][..]),
+                    (Edit, &["e"]),
+                    (SendMessage, &["Enter"]),
+                    (ToggleBinary, &["t"]),
+                    (Ping, &["p"]),
+                    (Close, &["c"]),
+                    (SaveSession, &["s"]),
+                    (Down, &["j"]),
+                    (Up, &["k"]),
+                    (Follow, &["G"]),
+                ],
+                windows.to_vec(),
+            ].concat(),
+            Context::Runner => vec![
+                (Deselect, &["Esc"][..]),
+                (Down, &["j"]),
+                (Up, &["k"]),
+                (SwitchPane, &["Tab"]),
+                (Run, &["Enter"]),
+                (Parallel, &["p"]),
+                (StopOnFailure, &["f"]),
+                (StopRun, &["x"]),
+                (SaveReports, &["w"]),
+            ],
+            Context::Variables => vec![
+                (Deselect, &["Esc"][..]),
+                (Down, &["j"]),
+                (Up, &["k"]),
+                (Edit, &["e"]),
+                (Add, &["a"]),
+                (Delete, &["d"]),
+                (NextEnvironment, &["n"]),
+            ],
+        };
+        bindings.push((Help, &["?"]));
+        bindings
+    }
+}
+
+/// What a key does, the names are the ones used in `config.toml`
+#[derive(Display, EnumString, Clone, Copy, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Help,
+    Deselect,
+    History,
+    Url,
+    Parameters,
+    Response,
+    Stop,
+    Runner,
+    SaveToFile,
+    Variables,
+    Send,
+    Edit,
+    PreviousMethod,
+    NextMethod,
+    Down,
+    Up,
+    Add,
+    Delete,
+    PreviousTab,
+    NextTab,
+    ToggleMode,
+    SwitchEditor,
+    NextOperation,
+    Introspect,
+    Load,
+    SwitchList,
+    PreviousView,
+    NextView,
+    SaveBody,
+    Charset,
+    Filter,
+    Follow,
+    Clear,
+    Cycle,
+    LoadServices,
+    Template,
+    SendMessage,
+    ToggleBinary,
+    Ping,
+    Close,
+    SaveSession,
+    SwitchPane,
+    Run,
+    Parallel,
+    StopOnFailure,
+    StopRun,
+    SaveReports,
+    NextEnvironment,
+}
+
+impl Command {
+    pub fn description(self) -> &'static str {
+        match self {
+            Command::Quit => "quit",
+            Command::Help => "show these keys",
+            Command::Deselect => "deselect the window",
+            Command::History => "select the history window",
+            Command::Url => "select the URL window",
+            Command::Parameters => "select the Parameters window",
+            Command::Response => "select the Response window",
+            Command::Stop => "stop the streamed response",
+            Command::Runner => "open the collection runner",
+            Command::SaveToFile => "save the request to the open .http file",
+            Command::Variables => "open the variables",
+            Command::Send => "send the request / connect the WebSocket",
+            Command::Edit => "edit",
+            Command::PreviousMethod => "previous method",
+            Command::NextMethod => "next method",
+            Command::Down => "down",
+            Command::Up => "up",
+            Command::Add => "add an entry",
+            Command::Delete => "delete the selected entry",
+            Command::PreviousTab => "previous tab",
+            Command::NextTab => "next tab",
+            Command::ToggleMode => "switch between raw and GraphQL mode",
+            Command::SwitchEditor => "switch between query and variables (GraphQL)",
+            Command::NextOperation => "cycle the operation name (GraphQL)",
+            Command::Introspect => "fetch the schema (GraphQL)",
+            Command::Load => "load the request",
+            Command::SwitchList => "switch between history and the open .http file",
+            Command::PreviousView => "previous view",
+            Command::NextView => "next view",
+            Command::SaveBody => "save the body to a file",
+            Command::Charset => "decode with another charset",
+            Command::Filter => "filter the JSON body",
+            Command::Follow => "follow the end of a stream or session",
+            Command::Clear => "clear the value, to inherit it",
+            Command::Cycle => "cycle the choices",
+            Command::LoadServices => "load the services",
+            Command::Template => "insert a request message in the Body tab",
+            Command::SendMessage => "send the message",
+            Command::ToggleBinary => "switch between text and binary messages",
+            Command::Ping => "send a ping",
+            Command::Close => "close the connection",
+            Command::SaveSession => "save the session to history",
+            Command::SwitchPane => "switch between folders and results",
+            Command::Run => "run the folder",
+            Command::Parallel => "cycle the number of parallel requests",
+            Command::StopOnFailure => "toggle stopping on the first failure",
+            Command::StopRun => "stop the run",
+            Command::SaveReports => "save the reports",
+            Command::NextEnvironment => "switch to the next environment",
+        }
+    }
+}
+
+/// A key with its modifiers, written `e`, `G`, `Enter`, `C-r` or `A-Down`
+#[derive(Clone, Copy, PartialEq)]
+pub struct KeySpec {
+    key: Key,
+    ctrl: bool,
+    alt: bool,
+}
+
+impl KeySpec {
+    /// Shift is part of the character, `G` is shift and g
+    pub fn matches(&self, input: &Input) -> bool {
+        self.key == input.key && self.ctrl == input.ctrl && self.alt == input.alt
+    }
+}
+
+impl FromStr for KeySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = KeySpec { key: Key::Null, ctrl: false, alt: false };
+        let mut name = s;
+        loop {
+            if let Some(rest) = name.strip_prefix("C-").filter(|rest| !rest.is_empty()) {
+                spec.ctrl = true;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("A-").filter(|rest| !rest.is_empty()) {
+                spec.alt = true;
+                name = rest;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = name.chars();
+        spec.key = match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_control() => Key::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "esc" => Key::Esc,
+                "enter" => Key::Enter,
+                "tab" => Key::Tab,
+                "space" => Key::Char(' '),
+                "backspace" => Key::Backspace,
+                "delete" => Key::Delete,
+                "up" => Key::Up,
+                "down" => Key::Down,
+                "left" => Key::Left,
+                "right" => Key::Right,
+                "home" => Key::Home,
+                "end" => Key::End,
+                "pageup" => Key::PageUp,
+                "pagedown" => Key::PageDown,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => Key::F(n),
+                    _ => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "C-")?;
+        }
+        if self.alt {
+            write!(f, "A-")?;
+        }
+        match self.key {
+            Key::Char(' ') => write!(f, "Space"),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "F{}", n),
+            Key::Esc => write!(f, "Esc"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Tab => write!(f, "Tab"),
+            Key::Backspace => write!(f, "Backspace"),
+            Key::Delete => write!(f, "Delete"),
+            Key::Up => write!(f, "Up"),
+            Key::Down => write!(f, "Down"),
+            Key::Left => write!(f, "Left"),
+            Key::Right => write!(f, "Right"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+            Key::PageUp => write!(f, "PageUp"),
+            Key::PageDown => write!(f, "PageDown"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// One key or a list of keys
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a key or a list of keys")]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The commands of every context and the keys that run them, the
+/// `[keys.<context>]` tables of `config.toml` replace the keys of the
+/// commands they name
+pub struct Keymap {
+    bindings: BTreeMap<Context, Vec<(Command, Vec<KeySpec>)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Context::iter()
+            .map(|context| {
+                let bindings = context.default_bindings().into_iter()
+                    .map(|(command, keys)| (command, keys.iter().map(|key| key.parse().expect("default key")).collect()))
+                    .collect();
+                (context, bindings)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tables = BTreeMap::<Context, BTreeMap<String, KeyList>>::deserialize(deserializer)?;
+        let mut keymap = Keymap::default();
+        for (context, table) in tables {
+            let bindings = keymap.bindings.entry(context).or_default();
+            for (name, keys) in table {
+                let binding = Command::from_str(&name).ok()
+                    .and_then(|command| bindings.iter_mut().find(|(other, _)| *other == command));
+                let Some((_, binding_keys)) = binding else {
+                    let commands: Vec<String> = bindings.iter().map(|(command, _)| command.to_string()).collect();
+                    return Err(serde::de::Error::custom(format!(
+                        "keys.{}: unknown command '{}', expected one of {}", context, name, commands.join(", "))));
+                };
+                let keys = match keys {
+                    KeyList::One(key) => vec![key],
+                    KeyList::Many(keys) => keys,
+                };
+                *binding_keys = keys.iter()
+                    .map(|key| key.parse().map_err(|e| serde::de::Error::custom(format!("keys.{}.{}: {}", context, name, e))))
+                    .collect::<Result<_, _>>()?;
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+impl Keymap {
+    /// The command bound to `input` in `context`
+    pub fn command(&self, context: Context, input: &Input) -> Option<Command> {
+        self.bindings(context).iter()
+            .find(|(_, keys)| keys.iter().any(|key| key.matches(input)))
+            .map(|(command, _)| *command)
+    }
+
+    pub fn bindings(&self, context: Context) -> &[(Command, Vec<KeySpec>)] {
+        self.bindings.get(&context).map_or(&[], Vec::as_slice)
+    }
+
+    /// The keys of `command` as shown to the user, e.g. `l, Enter`
+    pub fn keys(&self, context: Context, command: Command) -> String {
+        self.bindings(context).iter()
+            .find(|(other, _)| *other == command)
+            .map(|(_, keys)| keys.iter().map(KeySpec::to_string).collect::<Vec<_>>().join(", "))
+            .unwrap_or_default()
+    }
+
+    /// Keys bound to two commands of the same context
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (context, bindings) in &self.bindings {
+            for (index, (command, keys)) in bindings.iter().enumerate() {
+                for key in keys {
+                    if let Some((other, _)) = bindings[..index].iter().find(|(_, other_keys)| other_keys.contains(key)) {
+                        errors.push(format!("keys.{}.{} and keys.{}.{} are both '{}'", context, other, context, command, key));
+                    }
+                }
+            }
+        }
+        if self.keys(Context::Main, Command::Quit).is_empty() {
+            errors.push(String::from("keys.main.quit has no key, lazycurl could not be left"));
+        }
+        errors
+    }
+}
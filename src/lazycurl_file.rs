@@ -1,16 +1,126 @@
-use std::{fs::{self, File}, io::{Read, Write}, path::{Path, PathBuf}};
+use std::{fs::{self, File}, io::{Read, Write}, path::{Path, PathBuf}, time::Duration};
 
-use chrono::Utc;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 
-use crate::{http_method::HTTPMethod, utils::directory::{init_collection_directory_if_not_exist, init_history_directory_if_not_exist, Directory}};
+use crate::{assertion::Assertion, config::Config, extraction::Extraction, http_file::HttpFile, http_method::HTTPMethod, variables::Variables, request_settings::RequestSettings, websocket::WebSocketFrame, grpc::GrpcRequest, utils::{binary, charset::{decode, detect_charset}, curl_service::{CurlRequest, CurlResponse}, directory::{init_history_directory_if_not_exist, Directory}, graphql::{add_json_content_type, GraphQLBody}}};
 use tui_tree_widget::TreeItem;
 
+/// Larger response bodies are not kept in history
+const MAX_SAVED_BODY_SIZE: usize = 1024 * 1024;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct LazyCurlFile {
     pub url: String,
     pub headers: Vec<String>,
     pub http_method: HTTPMethod,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    /// Set when the body is edited in GraphQL mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphql: Option<GraphQLBody>,
+    /// Set for gRPC and gRPC-Web requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<GrpcRequest>,
+    #[serde(default)]
+    pub settings: RequestSettings,
+    /// Checks of the response, evaluated after each send
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    /// Variables set from the response, evaluated after each send
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extractions: Vec<Extraction>,
+    /// Rhai script run before the request is sent
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pre_request_script: String,
+    /// Rhai script run once the response arrived
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub post_response_script: String,
+    /// Log of a WebSocket session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub websocket_frames: Vec<WebSocketFrame>,
+    /// Response received when the request was sent, kept in history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<SavedResponse>,
+}
+
+/// A response as kept in history, with the timings of its transfer
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedResponse {
+    pub started: DateTime<Utc>,
+    pub status: u32,
+    pub http_version: String,
+    /// Status line and headers
+    pub headers: Vec<String>,
+    /// The body as text, or as base64 when it is binary. Empty when the body
+    /// is larger than `MAX_SAVED_BODY_SIZE`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub body_base64: bool,
+    pub body_size: usize,
+    pub timings: SavedTimings,
+}
+
+/// Time spent in each phase of the transfer in milliseconds, as in HAR
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct SavedTimings {
+    pub dns: f64,
+    /// Includes the TLS handshake
+    pub connect: f64,
+    /// TLS handshake, zero without TLS
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+    pub total: f64,
+}
+
+impl SavedResponse {
+    pub fn new(response: &CurlResponse) -> Self {
+        let started = Utc::now() - chrono::Duration::from_std(response.total_time).unwrap_or_else(|_| chrono::Duration::zero());
+        let body_base64 = binary::is_binary(response.content_type(), &response.body);
+        let body = if response.body.len() > MAX_SAVED_BODY_SIZE {
+            String::new()
+        } else if body_base64 {
+            STANDARD.encode(&response.body)
+        } else {
+            let (encoding, _) = detect_charset(None, response.content_type(), &response.body);
+            decode(&response.body, encoding)
+        };
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let timings = &response.timings;
+        let connected = timings.connect.max(timings.app_connect);
+        Self {
+            started,
+            status: response.status,
+            http_version: response.http_version.clone(),
+            headers: response.headers.clone(),
+            body,
+            body_base64,
+            body_size: response.body.len(),
+            timings: SavedTimings {
+                dns: ms(timings.name_lookup),
+                connect: ms(connected.saturating_sub(timings.name_lookup)),
+                ssl: ms(timings.app_connect.saturating_sub(timings.connect)),
+                send: ms(timings.pre_transfer.saturating_sub(connected)),
+                wait: ms(timings.start_transfer.saturating_sub(timings.pre_transfer)),
+                receive: ms(response.total_time.saturating_sub(timings.start_transfer)),
+                total: ms(response.total_time),
+            },
+        }
+    }
+
+    /// The body as received
+    pub fn body_bytes(&self) -> Vec<u8> {
+        if self.body_base64 {
+            STANDARD.decode(&self.body).unwrap_or_default()
+        } else {
+            self.body.clone().into_bytes()
+        }
+    }
 }
 
 impl LazyCurlFile {
@@ -18,21 +128,109 @@ impl LazyCurlFile {
         Self {
             url,
             headers,
-            http_method
+            http_method,
+            body: String::new(),
+            graphql: None,
+            grpc: None,
+            settings: RequestSettings::default(),
+            assertions: Vec::new(),
+            extractions: Vec::new(),
+            pre_request_script: String::new(),
+            post_response_script: String::new(),
+            websocket_frames: Vec::new(),
+            response: None,
         }
     }
 
+    /// Load a request file, or a request of a `.http` file named by
+    /// `HttpFile::request_path`
+    pub fn from_path(path: &Path) -> Result<LazyCurlFile, Box<dyn std::error::Error>> {
+        if HttpFile::split_request_path(path).is_some() {
+            return HttpFile::load_request(path);
+        }
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// The request with the `{{name}}` placeholders of its URL, headers and
+    /// body replaced by the values of `variables`
+    pub fn with_variables(&self, variables: &Variables) -> LazyCurlFile {
+        let mut lazycurl_file = self.clone();
+        lazycurl_file.url = variables.substitute(&self.url);
+        lazycurl_file.headers = self.headers.iter().map(|header| variables.substitute(header)).collect();
+        lazycurl_file.body = variables.substitute(&self.body);
+        if let Some(graphql) = &mut lazycurl_file.graphql {
+            graphql.query = variables.substitute(&graphql.query);
+            graphql.variables = variables.substitute(&graphql.variables);
+        }
+        lazycurl_file
+    }
+
+    /// Placeholders of the URL, headers and body without a value in `variables`
+    pub fn missing_variables(&self, variables: &Variables) -> Vec<String> {
+        let mut text = format!("{}\n{}\n{}", self.url, self.headers.join("\n"), self.body);
+        if let Some(graphql) = &self.graphql {
+            text.push_str(&format!("\n{}\n{}", graphql.query, graphql.variables));
+        }
+        variables.missing(&text)
+    }
+
+    /// The HTTP request of the file, with unset settings taken from
+    /// `global_settings` and the default headers of the config added
+    pub fn curl_request(&self, global_settings: &RequestSettings) -> Result<CurlRequest, String> {
+        let mut headers = self.headers.clone();
+        for (name, value) in &Config::get().headers {
+            let is_set = self.headers.iter()
+                .filter_map(|header| header.split_once(':'))
+                .any(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name));
+            if !is_set {
+                headers.push(format!("{}: {}", name, value));
+            }
+        }
+        let body = match &self.graphql {
+            Some(graphql) => {
+                add_json_content_type(&mut headers);
+                graphql.envelope()?
+            },
+            None => self.body.clone(),
+        };
+
+        Ok(CurlRequest {
+            url: self.url.clone(),
+            method: self.http_method,
+            headers,
+            body: body.into_bytes(),
+            settings: self.settings.merge(global_settings),
+        })
+    }
+
     pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_as_of(Utc::now())?;
+        if let Some(max_entries) = Config::get().history.max_entries {
+            prune_history(max_entries)?;
+        }
+        Ok(())
+    }
+
+    /// Save to history as sent at `time`, requests sent within the same
+    /// second get a numbered file each
+    pub fn save_as_of(&self, time: DateTime<Utc>) -> Result<PathBuf, Box<dyn std::error::Error>> {
         init_history_directory_if_not_exist();
 
         let serialized = serde_json::to_string_pretty(&self)?;
-        let now = Utc::now();
-        let timestamp = now.format("%Y-%m-%dT%H-%M-%SZ").to_string();
-        let filename = format!("{}/lazy_curl_request_{}.json", Directory::History.path(), timestamp);
+        let timestamp = time.format("%Y-%m-%dT%H-%M-%SZ").to_string();
+        let mut filename = PathBuf::from(format!("{}/lazy_curl_request_{}.json", Directory::History.path(), timestamp));
+        let mut count = 1;
+        while filename.exists() {
+            count += 1;
+            filename = PathBuf::from(format!("{}/lazy_curl_request_{}_{}.json", Directory::History.path(), timestamp, count));
+        }
 
-        let mut file = File::create(filename)?;
+        let mut file = File::create(&filename)?;
         file.write_all(serialized.as_bytes())?;
-        Ok(())
+        Ok(filename)
     }
 
     pub fn get_history_lazycurlfiles(&mut self) -> Result<Vec<LazyCurlFile>, Box<dyn std::error::Error>> {
@@ -42,13 +240,7 @@ impl LazyCurlFile {
             let entry = entry?;
             let path = entry.path();
             if path.is_file() {
-                let mut file = File::open(path)?;
-
-                let mut contents = String::new();
-                file.read_to_string(&mut contents)?;
-
-                let lazy_curl_file: LazyCurlFile = serde_json::from_str(&contents)?;
-                lazy_curl_files.push(lazy_curl_file);
+                lazy_curl_files.push(LazyCurlFile::from_path(&path)?);
             }
         }
         Ok(lazy_curl_files)
@@ -72,7 +264,33 @@ impl LazyCurlFile {
         Ok(collection_tree)
     }
 
-    fn get_file_name(&mut self, path: &PathBuf) -> String {
+    fn get_file_name(&mut self, path: &Path) -> String {
         String::from(path.file_name().unwrap().to_str().unwrap())
     }
 }
+
+/// Delete the oldest history entries beyond `max_entries`, file names carry
+/// the time they were saved at
+fn prune_history(max_entries: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut paths = fs::read_dir(Directory::History.path())?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort_by_key(|path| history_order(path));
+    for path in paths.iter().take(paths.len().saturating_sub(max_entries)) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The time in the name of a history file, then the number `save_as_of`
+/// adds to the requests saved within the same second, `_10` after `_9`
+fn history_order(path: &Path) -> (String, u32) {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let timestamp = stem.trim_start_matches("lazy_curl_request_");
+    match timestamp.rsplit_once('_').and_then(|(timestamp, count)| Some((timestamp, count.parse().ok()?))) {
+        Some((timestamp, count)) => (timestamp.to_string(), count),
+        None => (timestamp.to_string(), 1),
+    }
+}
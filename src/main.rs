@@ -1,19 +1,68 @@
-use std::io;
+use std::{io, process::ExitCode};
+
+use clap::Parser;
 
 pub mod app;
 pub mod tui;
 pub mod components;
 pub mod action;
 pub mod lazycurl_file;
+pub mod http_file;
 pub mod utils;
 pub mod http_method;
+pub mod http_version;
+pub mod request_settings;
+pub mod websocket;
+pub mod grpc;
+pub mod assertion;
+pub mod extraction;
+pub mod variables;
+pub mod script;
+pub mod import;
+pub mod cli;
+pub mod config;
+pub mod keymap;
+pub mod crash_report;
+
+use crate::{app::App, cli::Cli, config::Config, http_file::HttpFile, utils::directory::init_directories};
+
+fn main() -> io::Result<ExitCode> {
+    let cli = Cli::parse();
+    init_directories(cli.data_dir, cli.config_dir);
+    match Config::load() {
+        Ok(config) => Config::init(config),
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::from(cli::EXIT_FAILURE));
+        },
+    }
+    if let (Some(_), Some(path)) = (&cli.command, &cli.file) {
+        eprintln!("{}: a file cannot be opened together with a command", path.display());
+        return Ok(ExitCode::from(cli::EXIT_FAILURE));
+    }
+    if let Some(command) = cli.command {
+        return Ok(cli::run(command));
+    }
 
-use crate::app::App;
+    let mut app = App::new();
+    if let Some(path) = cli.file {
+        match HttpFile::load(&path) {
+            Ok(http_file) => app.open_http_file(http_file),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return Ok(ExitCode::from(cli::EXIT_FAILURE));
+            },
+        }
+    }
 
-fn main() -> io::Result<()> {
     let mut terminal = tui::init()?;
-    let app_result = App::new().run(&mut terminal);
-    tui::restore()?;
-    app_result
+    let app_result = app.run(&mut terminal);
+    let restore_result = tui::restore();
+    // The terminal may be gone after SIGHUP, exit like the signal would have
+    if let Some(signal) = tui::received_signal() {
+        return Ok(ExitCode::from(128 + signal as u8));
+    }
+    restore_result?;
+    app_result.map(|()| ExitCode::SUCCESS)
 }
 
@@ -0,0 +1,286 @@
+use std::{fs, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, EnumString, FromRepr, IntoEnumIterator};
+
+use crate::{config::Config, http_version::HTTPVersion, utils::{charset::encoding_for_label, directory::Directory}};
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 500;
+const DEFAULT_RETRY_STATUS_CODES: [u32; 3] = [502, 503, 504];
+const DEFAULT_RETRY_ERRORS: [RetryableError; 2] = [RetryableError::Timeout, RetryableError::Connect];
+
+/// Transport failures that may be retried
+#[derive(Display, EnumString, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum RetryableError {
+    Timeout,
+    Connect,
+    Resolve,
+    Send,
+    Receive,
+}
+
+impl RetryableError {
+    pub fn matches(self, error: &curl::Error) -> bool {
+        match self {
+            RetryableError::Timeout => error.is_operation_timedout(),
+            RetryableError::Connect => error.is_couldnt_connect(),
+            RetryableError::Resolve => error.is_couldnt_resolve_host(),
+            RetryableError::Send => error.is_send_error(),
+            RetryableError::Receive => error.is_recv_error() || error.is_got_nothing(),
+        }
+    }
+}
+
+/// Transfer settings of a request. Unset values fall back to the global
+/// settings and then to the built in defaults.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct RequestSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<HTTPVersion>,
+    /// Let curl request and decode compressed responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decompress: Option<bool>,
+    /// Show the response while it arrives instead of once it completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Charset label used to decode the response instead of the detected one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Abort when the transfer is slower than this many bytes per second ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_speed_limit: Option<u32>,
+    /// ... for this many seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_speed_time_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_count: Option<u32>,
+    /// Delay before the first retry, doubled on every following retry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_status_codes: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_errors: Option<Vec<RetryableError>>,
+}
+
+impl RequestSettings {
+    /// Load the global settings from `settings.json` in the config directory,
+    /// over the `[request]` settings of `config.toml`
+    pub fn load_global() -> Result<RequestSettings, Box<dyn std::error::Error>> {
+        let path = format!("{}/settings.json", Directory::Config.path());
+        if !Path::new(&path).exists() {
+            return Ok(Config::get().request.clone());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        let settings: RequestSettings = serde_json::from_str(&contents)?;
+        Ok(settings.merge(&Config::get().request))
+    }
+
+    /// Fill every unset value of `self` with the value of `fallback`
+    pub fn merge(&self, fallback: &RequestSettings) -> RequestSettings {
+        RequestSettings {
+            http_version: self.http_version.or(fallback.http_version),
+            decompress: self.decompress.or(fallback.decompress),
+            stream: self.stream.or(fallback.stream),
+            charset: self.charset.clone().or(fallback.charset.clone()),
+            connect_timeout_ms: self.connect_timeout_ms.or(fallback.connect_timeout_ms),
+            timeout_ms: self.timeout_ms.or(fallback.timeout_ms),
+            low_speed_limit: self.low_speed_limit.or(fallback.low_speed_limit),
+            low_speed_time_secs: self.low_speed_time_secs.or(fallback.low_speed_time_secs),
+            retry_count: self.retry_count.or(fallback.retry_count),
+            retry_backoff_ms: self.retry_backoff_ms.or(fallback.retry_backoff_ms),
+            retry_status_codes: self.retry_status_codes.clone().or(fallback.retry_status_codes.clone()),
+            retry_errors: self.retry_errors.clone().or(fallback.retry_errors.clone()),
+        }
+    }
+
+    pub fn decompress(&self) -> bool {
+        self.decompress.unwrap_or(true)
+    }
+
+    pub fn stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS))
+    }
+
+    /// Total transfer timeout, zero disables it
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+
+    pub fn low_speed(&self) -> Option<(u32, Duration)> {
+        match (self.low_speed_limit, self.low_speed_time_secs) {
+            (Some(limit), Some(secs)) if limit > 0 && secs > 0 => Some((limit, Duration::from_secs(secs))),
+            _ => None,
+        }
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retry_count.unwrap_or(0)
+    }
+
+    /// Delay before the given retry (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self.retry_backoff_ms.unwrap_or(DEFAULT_RETRY_BACKOFF_MS);
+        Duration::from_millis(base.saturating_mul(1 << retry.saturating_sub(1).min(16)))
+    }
+
+    pub fn is_retryable_status(&self, status: u32) -> bool {
+        match &self.retry_status_codes {
+            Some(codes) => codes.contains(&status),
+            None => DEFAULT_RETRY_STATUS_CODES.contains(&status),
+        }
+    }
+
+    pub fn is_retryable_error(&self, error: &curl::Error) -> bool {
+        match &self.retry_errors {
+            Some(errors) => errors.iter().any(|e| e.matches(error)),
+            None => DEFAULT_RETRY_ERRORS.iter().any(|e| e.matches(error)),
+        }
+    }
+}
+
+/// The editable fields of `RequestSettings`, in display order
+#[derive(EnumIter, FromRepr, Clone, Copy)]
+pub enum SettingField {
+    HttpVersion,
+    Decompress,
+    Stream,
+    Charset,
+    ConnectTimeout,
+    Timeout,
+    LowSpeedLimit,
+    LowSpeedTime,
+    RetryCount,
+    RetryBackoff,
+    RetryStatusCodes,
+    RetryErrors,
+}
+
+impl SettingField {
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingField::HttpVersion => "HTTP version",
+            SettingField::Decompress => "Decompress response",
+            SettingField::Stream => "Stream response",
+            SettingField::Charset => "Response charset",
+            SettingField::ConnectTimeout => "Connect timeout (ms)",
+            SettingField::Timeout => "Total timeout (ms, 0 = none)",
+            SettingField::LowSpeedLimit => "Low speed limit (bytes/s)",
+            SettingField::LowSpeedTime => "Low speed time (s)",
+            SettingField::RetryCount => "Retries",
+            SettingField::RetryBackoff => "Retry backoff (ms)",
+            SettingField::RetryStatusCodes => "Retry on status",
+            SettingField::RetryErrors => "Retry on errors",
+        }
+    }
+
+    pub fn value(self, settings: &RequestSettings) -> Option<String> {
+        match self {
+            SettingField::HttpVersion => settings.http_version.map(|v| v.to_string()),
+            SettingField::Decompress => settings.decompress.map(|v| v.to_string()),
+            SettingField::Stream => settings.stream.map(|v| v.to_string()),
+            SettingField::Charset => settings.charset.clone(),
+            SettingField::ConnectTimeout => settings.connect_timeout_ms.map(|v| v.to_string()),
+            SettingField::Timeout => settings.timeout_ms.map(|v| v.to_string()),
+            SettingField::LowSpeedLimit => settings.low_speed_limit.map(|v| v.to_string()),
+            SettingField::LowSpeedTime => settings.low_speed_time_secs.map(|v| v.to_string()),
+            SettingField::RetryCount => settings.retry_count.map(|v| v.to_string()),
+            SettingField::RetryBackoff => settings.retry_backoff_ms.map(|v| v.to_string()),
+            SettingField::RetryStatusCodes => settings.retry_status_codes.as_ref().map(|v| join(v)),
+            SettingField::RetryErrors => settings.retry_errors.as_ref().map(|v| join(v)),
+        }
+    }
+
+    /// Value used when neither the request nor the global settings set one
+    pub fn default_value(self) -> String {
+        match self {
+            SettingField::HttpVersion => String::from("auto"),
+            SettingField::Decompress => String::from("true"),
+            SettingField::Stream => String::from("false"),
+            SettingField::Charset => String::from("auto"),
+            SettingField::ConnectTimeout => DEFAULT_CONNECT_TIMEOUT_MS.to_string(),
+            SettingField::Timeout => DEFAULT_TIMEOUT_MS.to_string(),
+            SettingField::LowSpeedLimit | SettingField::LowSpeedTime => String::from("off"),
+            SettingField::RetryCount => String::from("0"),
+            SettingField::RetryBackoff => DEFAULT_RETRY_BACKOFF_MS.to_string(),
+            SettingField::RetryStatusCodes => join(&DEFAULT_RETRY_STATUS_CODES),
+            SettingField::RetryErrors => join(&DEFAULT_RETRY_ERRORS),
+        }
+    }
+
+    /// The values a field can be cycled through, empty for free text fields
+    pub fn choices(self) -> Vec<String> {
+        match self {
+            SettingField::HttpVersion => HTTPVersion::iter().map(|v| v.to_string()).collect(),
+            SettingField::Decompress | SettingField::Stream => vec![String::from("true"), String::from("false")],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Parse `value` into the field, an empty value unsets it
+    pub fn set_value(self, settings: &mut RequestSettings, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match self {
+            SettingField::HttpVersion => settings.http_version = parse(value)?,
+            SettingField::Decompress => settings.decompress = parse(value)?,
+            SettingField::Stream => settings.stream = parse(value)?,
+            SettingField::Charset => settings.charset = parse_charset(value)?,
+            SettingField::ConnectTimeout => settings.connect_timeout_ms = parse(value)?,
+            SettingField::Timeout => settings.timeout_ms = parse(value)?,
+            SettingField::LowSpeedLimit => settings.low_speed_limit = parse(value)?,
+            SettingField::LowSpeedTime => settings.low_speed_time_secs = parse(value)?,
+            SettingField::RetryCount => settings.retry_count = parse(value)?,
+            SettingField::RetryBackoff => settings.retry_backoff_ms = parse(value)?,
+            SettingField::RetryStatusCodes => settings.retry_status_codes = parse_list(value)?,
+            SettingField::RetryErrors => settings.retry_errors = parse_list(value)?,
+        }
+        Ok(())
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse::<T>()
+        .map(Some)
+        .map_err(|_| format!("invalid value '{}'", value))
+}
+
+fn parse_charset(value: &str) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match encoding_for_label(value) {
+        Some(_) => Ok(Some(value.to_string())),
+        None => Err(format!("unknown charset '{}'", value)),
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Result<Option<Vec<T>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.split(',')
+        .map(|v| v.trim().parse::<T>().map_err(|_| format!("invalid value '{}'", v.trim())))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
@@ -0,0 +1,254 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, KeyInit, Mac};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{assertion::{Assertion, AssertionResult}, lazycurl_file::LazyCurlFile, utils::{bytes::to_hex, curl_service::CurlResponse}, variables::Variables};
+
+/// Scripts are stopped after this many operations, so a loop cannot hang the app
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 10 * 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScriptStage {
+    PreRequest,
+    PostResponse,
+}
+
+impl ScriptStage {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScriptStage::PreRequest => "pre-request",
+            ScriptStage::PostResponse => "post-response",
+        }
+    }
+}
+
+/// A line printed by a script, or the error that stopped it
+pub struct ConsoleEntry {
+    pub stage: ScriptStage,
+    pub text: String,
+    pub is_error: bool,
+}
+
+impl fmt::Display for ConsoleEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.stage.label(), self.text)
+    }
+}
+
+#[derive(Default)]
+pub struct ScriptOutput {
+    pub console: Vec<ConsoleEntry>,
+    /// Checks made with `test(name, passed)` in post-response scripts
+    pub test_results: Vec<AssertionResult>,
+    /// Why the script stopped, it is also in the console
+    pub error: Option<String>,
+}
+
+impl ScriptOutput {
+    pub fn extend(&mut self, other: ScriptOutput) {
+        self.console.extend(other.console);
+        self.test_results.extend(other.test_results);
+        self.error = self.error.take().or(other.error);
+    }
+}
+
+/// Number of lines of the script, or the first syntax error
+pub fn check(script: &str) -> Result<usize, String> {
+    if script.trim().is_empty() {
+        return Ok(0);
+    }
+    Engine::new().compile(script).map_err(|e| e.to_string())?;
+    Ok(script.lines().filter(|line| !line.trim().is_empty()).count())
+}
+
+/// Run the script before the request is sent. It may change `request.url`,
+/// `request.headers` and `request.body` and the variables in `vars`, the
+/// request is left as it was when the script fails.
+pub fn run_pre_request(script: &str, lazycurl_file: &mut LazyCurlFile, variables: &mut Variables) -> ScriptOutput {
+    if script.trim().is_empty() {
+        return ScriptOutput::default();
+    }
+
+    let mut request = Map::new();
+    request.insert("url".into(), lazycurl_file.url.clone().into());
+    request.insert("method".into(), lazycurl_file.http_method.to_string().into());
+    let headers = headers_map(&lazycurl_file.headers);
+    request.insert("headers".into(), headers.clone().into());
+    request.insert("body".into(), lazycurl_file.body.clone().into());
+
+    let mut scope = Scope::new();
+    scope.push("request", request);
+    let (mut output, scope) = run(ScriptStage::PreRequest, script, scope, variables);
+    if output.error.is_some() {
+        return output;
+    }
+
+    if let Some(request) = scope.get_value::<Map>("request") {
+        let text = |key: &str| request.get(key).map(|value| value.to_string());
+        if let Some(url) = text("url") {
+            lazycurl_file.url = url;
+        }
+        if let Some(body) = text("body") {
+            lazycurl_file.body = body;
+        }
+        match request.get("headers").and_then(|headers| headers.clone().try_cast::<Map>()) {
+            // The map lost the order and the repeated headers, only a change rewrites them
+            Some(changed) if header_lines(&changed) != header_lines(&headers) => lazycurl_file.headers = header_lines(&changed),
+            Some(_) => (),
+            None => output.console.push(ConsoleEntry {
+                stage: ScriptStage::PreRequest,
+                text: String::from("request.headers is not a map, the headers were left as they were"),
+                is_error: true,
+            }),
+        }
+    }
+    output
+}
+
+/// Run the script once the response arrived. It can read `response.status`,
+/// `response.headers`, `response.body`, `response.json` and `response.time_ms`,
+/// set variables in `vars` and make checks with `test(name, passed)`. Header
+/// names are lowercase and a repeated header has an array of its values.
+pub fn run_post_response(script: &str, response: &CurlResponse, json: Option<&Value>, variables: &mut Variables) -> ScriptOutput {
+    if script.trim().is_empty() {
+        return ScriptOutput::default();
+    }
+
+    let mut response_map = Map::new();
+    response_map.insert("status".into(), Dynamic::from_int(i64::from(response.status)));
+    response_map.insert("headers".into(), response_headers_map(&response.headers).into());
+    response_map.insert("body".into(), String::from_utf8_lossy(&response.body).to_string().into());
+    response_map.insert("json".into(), json.and_then(|json| rhai::serde::to_dynamic(json).ok()).unwrap_or(Dynamic::UNIT));
+    response_map.insert("time_ms".into(), Dynamic::from_int(response.total_time.as_millis() as i64));
+
+    let mut scope = Scope::new();
+    scope.push_constant("response", response_map);
+    run(ScriptStage::PostResponse, script, scope, variables).0
+}
+
+fn run<'a>(stage: ScriptStage, script: &str, mut scope: Scope<'a>, variables: &mut Variables) -> (ScriptOutput, Scope<'a>) {
+    let console = Rc::new(RefCell::new(Vec::new()));
+    let test_results = Rc::new(RefCell::new(Vec::new()));
+    let engine = engine(stage, &console, &test_results);
+
+    let vars = variables.merged().into_iter()
+        .map(|(name, value)| (name.into(), Dynamic::from(value)))
+        .collect::<Map>();
+    scope.push("vars", vars);
+
+    let result = engine.run_with_scope(&mut scope, script);
+    let error = result.err().map(|e| e.to_string());
+    if error.is_none() {
+        if let Some(vars) = scope.get_value::<Map>("vars") {
+            variables.set_merged(vars.iter()
+                .filter(|(_, value)| !value.is_unit())
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect());
+        }
+    }
+
+    let mut console = console.take();
+    if let Some(error) = &error {
+        console.push(ConsoleEntry { stage, text: error.clone(), is_error: true });
+    }
+    (ScriptOutput { console, test_results: test_results.take(), error }, scope)
+}
+
+/// A sandboxed engine, scripts cannot reach files or the network and are
+/// limited in time and memory
+fn engine(stage: ScriptStage, console: &Rc<RefCell<Vec<ConsoleEntry>>>, test_results: &Rc<RefCell<Vec<AssertionResult>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let print_console = Rc::clone(console);
+    engine.on_print(move |text| print_console.borrow_mut().push(ConsoleEntry { stage, text: text.to_string(), is_error: false }));
+    let debug_console = Rc::clone(console);
+    engine.on_debug(move |text, _, position| debug_console.borrow_mut().push(ConsoleEntry {
+        stage,
+        text: format!("{} ({})", text, position),
+        is_error: false,
+    }));
+
+    engine.register_fn("hmac_sha256", |key: &str, message: &str| to_hex(&hmac_sha256(key, message), ""));
+    engine.register_fn("hmac_sha256_base64", |key: &str, message: &str| STANDARD.encode(hmac_sha256(key, message)));
+    engine.register_fn("sha256", |text: &str| to_hex(&Sha256::digest(text.as_bytes()), ""));
+    engine.register_fn("base64_encode", |text: &str| STANDARD.encode(text));
+    engine.register_fn("base64_decode", |text: &str| -> Result<String, Box<EvalAltResult>> {
+        let bytes = STANDARD.decode(text).map_err(|e| e.to_string())?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    });
+    engine.register_fn("timestamp", || unix_time().as_secs() as i64);
+    engine.register_fn("timestamp_ms", || unix_time().as_millis() as i64);
+    engine.register_fn("uuid", || uuid::Uuid::new_v4().to_string());
+    engine.register_fn("json_encode", |value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+        let value: Value = rhai::serde::from_dynamic(&value)?;
+        Ok(value.to_string())
+    });
+    engine.register_fn("json_decode", |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+        let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        rhai::serde::to_dynamic(value)
+    });
+
+    if stage == ScriptStage::PostResponse {
+        let script_test_results = Rc::clone(test_results);
+        engine.register_fn("test", move |name: &str, passed: bool| script_test_results.borrow_mut().push(AssertionResult {
+            assertion: Assertion::Script { name: name.to_string() },
+            passed,
+            actual: String::from("false"),
+        }));
+    }
+    engine
+}
+
+/// Request headers by name, a repeated header keeps its last value
+fn headers_map(headers: &[String]) -> Map {
+    headers.iter()
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, value)| (name.trim().into(), value.trim().to_string().into()))
+        .collect()
+}
+
+fn header_lines(headers: &Map) -> Vec<String> {
+    headers.iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect()
+}
+
+/// Response headers by lowercase name, the values of a repeated header such
+/// as `set-cookie` in an array
+fn response_headers_map(headers: &[String]) -> Map {
+    let mut values = BTreeMap::<String, Vec<String>>::new();
+    for (name, value) in headers.iter().filter_map(|header| header.split_once(':')) {
+        values.entry(name.trim().to_ascii_lowercase()).or_default().push(value.trim().to_string());
+    }
+    values.into_iter()
+        .map(|(name, mut values)| {
+            let value = match values.len() {
+                1 => values.remove(0).into(),
+                _ => values.into_iter().map(Dynamic::from).collect::<Array>().into(),
+            };
+            (name.into(), value)
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &str, message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn unix_time() -> std::time::Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
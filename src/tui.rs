@@ -1,13 +1,21 @@
-use std::io::{self, stdout, Stdout};
+use std::{io::{self, stdout, Stdout}, panic, sync::{atomic::{AtomicUsize, Ordering}, Arc, OnceLock}, thread};
 
 use crossterm::{execute, terminal::*};
 use ratatui::prelude::*;
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::crash_report;
 
 /// A type alias for the terminal type used in this application
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+/// Number of the last termination signal received, 0 for none
+static SIGNAL: OnceLock<Arc<AtomicUsize>> = OnceLock::new();
+
 /// Initialize the terminal
 pub fn init() -> io::Result<Tui> {
+    set_panic_hook();
+    register_signals()?;
     execute!(stdout(), EnterAlternateScreen)?;
     enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
@@ -19,3 +27,44 @@ pub fn restore() -> io::Result<()> {
     disable_raw_mode()?;
     Ok(())
 }
+
+/// Restore the terminal before the panic is printed, it would be lost in
+/// the alternate screen otherwise, and write a crash report. A panic of a
+/// background thread only writes the report, the windows keep running and
+/// show the request as failed.
+fn set_panic_hook() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = crash_report::write(&info.to_string());
+        if thread::current().name() != Some("main") {
+            return;
+        }
+        let _ = restore();
+        hook(info);
+        match report {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(e) => eprintln!("Could not write a crash report: {}", e),
+        }
+    }));
+}
+
+/// Note SIGINT, SIGTERM and SIGHUP instead of ending the process, the event
+/// loop ends and the terminal is restored
+fn register_signals() -> io::Result<()> {
+    let signal = SIGNAL.get_or_init(|| Arc::new(AtomicUsize::new(0)));
+    #[cfg(unix)]
+    let signals = [SIGINT, SIGTERM, signal_hook::consts::SIGHUP];
+    #[cfg(not(unix))]
+    let signals = [SIGINT, SIGTERM];
+    for number in signals {
+        signal_hook::flag::register_usize(number, Arc::clone(signal), number as usize)?;
+    }
+    Ok(())
+}
+
+/// The termination signal received since `init`, if any
+pub fn received_signal() -> Option<i32> {
+    SIGNAL.get()
+        .map(|signal| signal.load(Ordering::Relaxed) as i32)
+        .filter(|&number| number != 0)
+}
@@ -0,0 +1,125 @@
+const TEXT_CONTENT_TYPES: [&str; 6] = ["json", "xml", "javascript", "html", "csv", "x-www-form-urlencoded"];
+
+/// Whether a response body should be shown as binary rather than text,
+/// decided by its content type and, when that is inconclusive, its bytes
+pub fn is_binary(content_type: Option<&str>, body: &[u8]) -> bool {
+    if let Some(content_type) = content_type {
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        if mime.starts_with("text/") || TEXT_CONTENT_TYPES.iter().any(|t| mime.contains(t)) {
+            return false;
+        }
+        if mime.starts_with("image/") || mime.starts_with("audio/") || mime.starts_with("video/")
+            || mime == "application/octet-stream" || mime == "application/pdf" || mime.contains("zip") {
+            return true;
+        }
+    }
+
+    file_type(body).is_some() || looks_binary(body)
+}
+
+/// Sniff the first bytes of a body for NUL bytes and control characters
+fn looks_binary(body: &[u8]) -> bool {
+    let sample = &body[..body.len().min(1024)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control_bytes = sample.iter()
+        .filter(|b| b.is_ascii_control() && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c))
+        .count();
+    control_bytes * 10 > sample.len()
+}
+
+/// Name of a well known file format recognised by its magic bytes
+pub fn file_type(body: &[u8]) -> Option<&'static str> {
+    let file_type = if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "PNG image"
+    } else if body.starts_with(b"GIF87a") || body.starts_with(b"GIF89a") {
+        "GIF image"
+    } else if body.starts_with(&[0xff, 0xd8, 0xff]) {
+        "JPEG image"
+    } else if body.starts_with(b"BM") && body.len() > 26 {
+        "BMP image"
+    } else if body.starts_with(b"RIFF") && body.get(8..12) == Some(b"WEBP") {
+        "WebP image"
+    } else if body.starts_with(b"%PDF-") {
+        "PDF document"
+    } else if body.starts_with(b"PK\x03\x04") || body.starts_with(b"PK\x05\x06") {
+        "ZIP archive"
+    } else if body.starts_with(&[0x1f, 0x8b]) {
+        "gzip archive"
+    } else if body.starts_with(b"7z\xbc\xaf\x27\x1c") {
+        "7z archive"
+    } else if body.starts_with(b"BZh") {
+        "bzip2 archive"
+    } else if body.get(257..262) == Some(b"ustar") {
+        "tar archive"
+    } else {
+        return None;
+    };
+    Some(file_type)
+}
+
+/// Width and height of an image when they can be read from its header
+pub fn image_dimensions(body: &[u8]) -> Option<(u32, u32)> {
+    match file_type(body)? {
+        "PNG image" => Some((read_u32_be(body, 16)?, read_u32_be(body, 20)?)),
+        "GIF image" => Some((read_u16_le(body, 6)? as u32, read_u16_le(body, 8)? as u32)),
+        // The height of a BMP is negative when its rows are stored top-down
+        "BMP image" => Some((read_u32_le(body, 18)?, (read_u32_le(body, 22)? as i32).unsigned_abs())),
+        "JPEG image" => jpeg_dimensions(body),
+        _ => None,
+    }
+}
+
+/// One line summary of a binary body, e.g. `PNG image, 640x480, 12.0 KB`
+pub fn summary(body: &[u8], size: &str) -> String {
+    let Some(file_type) = file_type(body) else {
+        return format!("Binary data, {}", size);
+    };
+
+    let mut parts = vec![file_type.to_string()];
+    if let Some((width, height)) = image_dimensions(body) {
+        parts.push(format!("{}x{}", width, height));
+    }
+    if file_type == "PDF document" {
+        let version = body.get(5..8).map(String::from_utf8_lossy).unwrap_or_default();
+        parts.push(format!("version {}", version));
+    }
+    parts.push(size.to_string());
+    parts.join(", ")
+}
+
+/// Walk the JPEG segments until a start of frame marker holding the dimensions
+fn jpeg_dimensions(body: &[u8]) -> Option<(u32, u32)> {
+    let mut position = 2;
+    while position + 9 < body.len() {
+        if body[position] != 0xff {
+            return None;
+        }
+        let marker = body[position + 1];
+        let is_start_of_frame = (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_start_of_frame {
+            let height = read_u16_be(body, position + 5)? as u32;
+            let width = read_u16_be(body, position + 7)? as u32;
+            return Some((width, height));
+        }
+        position += 2 + read_u16_be(body, position + 2)? as usize;
+    }
+    None
+}
+
+fn read_u16_be(body: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(body.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u16_le(body: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(body.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32_be(body: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(body.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u32_le(body: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(body.get(at..at + 4)?.try_into().ok()?))
+}
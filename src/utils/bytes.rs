@@ -0,0 +1,55 @@
+/// Human readable size, e.g. `1.5 KB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Render bytes as text, escaping everything but printable ASCII and newlines
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            b'\n' => escaped.push('\n'),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(*byte as char),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Hex bytes joined by `separator`, e.g. `de ad be ef` with a space
+pub fn to_hex(bytes: &[u8], separator: &str) -> String {
+    bytes.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Hex and ASCII dump of `bytes`, 16 bytes per line prefixed with the offset
+/// of the line. `offset` is the offset of the first byte.
+pub fn hex_dump(bytes: &[u8], offset: usize) -> String {
+    bytes.chunks(16)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex = to_hex(chunk, " ");
+            let ascii = chunk.iter()
+                .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|", offset + index * 16, hex, ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,94 @@
+use encoding_rs::{Encoding, UTF_8};
+
+/// Where the charset used to decode a body came from
+pub enum CharsetSource {
+    Override,
+    ContentType,
+    ByteOrderMark,
+    Meta,
+    Default,
+}
+
+impl CharsetSource {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CharsetSource::Override => "override",
+            CharsetSource::ContentType => "Content-Type",
+            CharsetSource::ByteOrderMark => "BOM",
+            CharsetSource::Meta => "meta",
+            CharsetSource::Default => "default",
+        }
+    }
+}
+
+/// Encoding for a charset label such as `ISO-8859-1` or `shift_jis`
+pub fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// Pick the charset of a body: an override wins, then the `charset`
+/// parameter of the content type, a byte order mark, a `<meta>` or XML
+/// declaration and finally UTF-8
+pub fn detect_charset(override_label: Option<&str>, content_type: Option<&str>, body: &[u8]) -> (&'static Encoding, CharsetSource) {
+    if let Some(encoding) = override_label.and_then(encoding_for_label) {
+        return (encoding, CharsetSource::Override);
+    }
+
+    let from_content_type = content_type.and_then(|content_type| {
+        content_type.split(';')
+            .skip(1)
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("charset"))
+            .and_then(|(_, value)| encoding_for_label(value.trim_matches('"')))
+    });
+    if let Some(encoding) = from_content_type {
+        return (encoding, CharsetSource::ContentType);
+    }
+
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return (encoding, CharsetSource::ByteOrderMark);
+    }
+
+    if let Some(encoding) = sniff_meta_charset(body) {
+        return (encoding, CharsetSource::Meta);
+    }
+
+    (UTF_8, CharsetSource::Default)
+}
+
+/// Decode `body`, replacing malformed sequences
+pub fn decode(body: &[u8], encoding: &'static Encoding) -> String {
+    let (decoded, _) = encoding.decode_with_bom_removal(body);
+    decoded.into_owned()
+}
+
+/// Look for `charset=` in an HTML `<meta>` tag or `encoding=` in an XML
+/// declaration within the first kilobyte
+fn sniff_meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_lowercase();
+
+    let value_after = |marker: &str, text: &str| -> Option<String> {
+        let start = text.find(marker)? + marker.len();
+        let value = text[start..]
+            .trim_start_matches(['"', '\'', ' '])
+            .split(['"', '\'', ' ', ';', '>', '/'])
+            .next()?
+            .to_string();
+        Some(value)
+    };
+
+    let from_meta = head.match_indices("<meta")
+        .filter_map(|(index, _)| {
+            let tag = &head[index..head[index..].find('>').map_or(head.len(), |end| index + end)];
+            value_after("charset=", tag)
+        })
+        .find_map(|label| encoding_for_label(&label));
+
+    from_meta.or_else(|| {
+        let declaration = &head[..head.find("?>")?];
+        head.starts_with("<?xml")
+            .then(|| value_after("encoding=", declaration))
+            .flatten()
+            .and_then(|label| encoding_for_label(&label))
+    })
+}
@@ -1,48 +1,331 @@
-use std::io::Read;
+use std::{fmt, io::Read, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver}, Arc}, thread, time::Duration};
 
-use curl::easy::Easy;
+use curl::easy::{Easy, List};
 
-use crate::http_method::HTTPMethod;
+use crate::{http_method::HTTPMethod, request_settings::RequestSettings};
 
-pub fn curl_call(url: &str, data: &mut Vec<u8>, headers: curl::easy::List, post_data: &str, method: HTTPMethod) {
-    match method {
-        HTTPMethod::GET => curl_get_call(url, data, headers),
-        HTTPMethod::POST => curl_post_call(url, data, headers, post_data)
+pub struct CurlRequest {
+    pub url: String,
+    pub method: HTTPMethod,
+    pub headers: Vec<String>,
+    pub body: Vec<u8>,
+    pub settings: RequestSettings,
+}
+
+pub struct CurlResponse {
+    /// URL the response was received from, after following redirects
+    pub url: String,
+    pub status: u32,
+    /// Protocol version of the response status line, e.g. `HTTP/2`
+    pub http_version: String,
+    pub headers: Vec<String>,
+    /// Header lines sent after the body, e.g. the status of a gRPC call
+    pub trailers: Vec<String>,
+    pub body: Vec<u8>,
+    /// Size of the body as transferred, before curl decoded it
+    pub download_size: u64,
+    pub total_time: Duration,
+    pub timings: Timings,
+    pub attempts: u32,
+}
+
+/// When the phases of the transfer ended, from its start
+#[derive(Clone, Copy, Default)]
+pub struct Timings {
+    pub name_lookup: Duration,
+    pub connect: Duration,
+    /// End of the TLS handshake, zero without TLS
+    pub app_connect: Duration,
+    /// The request is about to be sent
+    pub pre_transfer: Duration,
+    /// The first byte of the response arrived
+    pub start_transfer: Duration,
+}
+
+pub enum StreamError {
+    Stopped,
+    Curl(curl::Error),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Stopped => write!(f, "Stopped"),
+            StreamError::Curl(error) => write!(f, "{}", error),
+        }
     }
 }
 
-pub fn curl_get_call(url: &str, data: &mut Vec<u8>, headers: curl::easy::List) {
-        let mut easy = Easy::new();
-        easy.url(url).unwrap();
-        easy.http_headers(headers).unwrap();
-        let mut transfer = easy.transfer();
+/// Messages sent by `curl_stream` while the response arrives
+pub enum StreamMessage {
+    Headers(Vec<String>),
+    Chunk(Vec<u8>),
+    /// The transfer ended, the response carries no body
+    Finished(Box<Result<CurlResponse, StreamError>>),
+}
 
-        transfer.write_function(|d| {
-            data.extend_from_slice(d);
-            Ok(d.len())
-        }).unwrap();
+pub struct StreamHandle {
+    pub receiver: Receiver<StreamMessage>,
+    stop: Arc<AtomicBool>,
+}
+
+/// A request sent with `curl_in_background`, its result arrives once
+pub struct RequestHandle {
+    pub receiver: Receiver<Result<CurlResponse, curl::Error>>,
+}
+
+impl StreamHandle {
+    /// Ask the transfer to stop, it ends with `StreamError::Stopped`
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl CurlResponse {
+    /// Value of the first response header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.header_values(name).first().copied()
+    }
+
+    /// Values of every response header named `name`, ignoring case, e.g.
+    /// each `Set-Cookie`
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        header_values(&self.headers, name)
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("Content-Type")
+    }
+
+    /// File name to save the body as, taken from `Content-Disposition` or
+    /// the last segment of the URL path
+    pub fn suggested_filename(&self) -> String {
+        let from_content_disposition = self.header("Content-Disposition")
+            .and_then(|disposition| {
+                disposition.split(';')
+                    .filter_map(|param| param.trim().split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("filename"))
+                    .map(|(_, value)| value.trim().trim_matches('"').to_string())
+            });
+
+        let from_url = || {
+            self.url.split(['?', '#'])
+                .next()
+                .and_then(|path| path.split_once("://").map(|(_, rest)| rest))
+                .and_then(|path| path.split_once('/').map(|(_, path)| path))
+                .and_then(|path| path.rsplit('/').next())
+                .map(String::from)
+        };
+
+        from_content_disposition
+            .or_else(from_url)
+            .map(|name| name.replace(['/', '\\'], "_"))
+            .filter(|name| !name.is_empty() && name != "." && name != "..")
+            .unwrap_or_else(|| String::from("response.bin"))
+    }
+}
+
+/// Perform the request, retrying it as configured by its settings
+pub fn curl_call(request: &CurlRequest) -> Result<CurlResponse, curl::Error> {
+    let mut attempt = 1;
+    loop {
+        let result = curl_perform(request);
+        let retryable = match &result {
+            Ok(response) => request.settings.is_retryable_status(response.status),
+            Err(error) => request.settings.is_retryable_error(error),
+        };
+
+        if !retryable || attempt > request.settings.retries() {
+            return result.map(|response| CurlResponse { attempts: attempt, ..response });
+        }
 
-        transfer.perform().unwrap();
+        thread::sleep(request.settings.backoff(attempt));
+        attempt += 1;
+    }
+}
+
+fn curl_perform(request: &CurlRequest) -> Result<CurlResponse, curl::Error> {
+    let mut data = Vec::new();
+    let response = perform_transfer(request, &AtomicBool::new(false), |_| (), |d| {
+        data.extend_from_slice(d);
+        true
+    })?;
+    Ok(CurlResponse { body: data, ..response })
+}
+
+/// `curl_call` in a background thread, the response is sent to the
+/// returned handle when the request and its retries are done
+pub fn curl_in_background(request: CurlRequest) -> RequestHandle {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(curl_call(&request));
+    });
+    RequestHandle { receiver }
 }
 
-pub fn curl_post_call(url: &str, data: &mut Vec<u8>, headers: curl::easy::List, post_data: &str) {
-        let mut post_data_as_bytes = post_data.as_bytes();
-        let mut easy = Easy::new();
-        easy.post(true).unwrap();
-        easy.url(url).unwrap();
-        easy.http_headers(headers).unwrap();
-        easy.post_field_size(post_data_as_bytes.len() as u64).unwrap();
+/// Perform the request in a background thread, sending the response to the
+/// returned handle as it arrives. The total timeout and retries are not
+/// applied since a stream may never end.
+pub fn curl_stream(request: CurlRequest) -> StreamHandle {
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_requested = Arc::clone(&stop);
+
+    thread::spawn(move || {
+        let request = CurlRequest {
+            settings: RequestSettings { timeout_ms: Some(0), ..request.settings.clone() },
+            ..request
+        };
+        let result = perform_transfer(&request, &stop_requested,
+            |headers| {
+                let _ = sender.send(StreamMessage::Headers(headers.to_vec()));
+            },
+            |d| sender.send(StreamMessage::Chunk(d.to_vec())).is_ok());
+        let result = match result {
+            Err(_) if stop_requested.load(Ordering::Relaxed) => Err(StreamError::Stopped),
+            result => result.map_err(StreamError::Curl),
+        };
+        let _ = sender.send(StreamMessage::Finished(Box::new(result)));
+    });
+
+    StreamHandle { receiver, stop }
+}
+
+/// Run a single transfer, handing every completed block of response headers
+/// and every body chunk to the callbacks. The body is not collected and the
+/// transfer is aborted when `on_data` returns false or `stop` is set.
+fn perform_transfer(
+    request: &CurlRequest,
+    stop: &AtomicBool,
+    mut on_headers: impl FnMut(&[String]),
+    mut on_data: impl FnMut(&[u8]) -> bool,
+) -> Result<CurlResponse, curl::Error> {
+    let mut easy = Easy::new();
+    easy.url(&request.url)?;
+
+    let mut headers = List::new();
+    for header in &request.headers {
+        headers.append(header)?;
+    }
+    easy.http_headers(headers)?;
+    apply_settings(&mut easy, &request.settings)?;
+    easy.progress(true)?;
+
+    let mut post_data_as_bytes = request.body.as_slice();
+    match request.method {
+        HTTPMethod::GET => {},
+        HTTPMethod::HEAD => easy.nobody(true)?,
+        method => {
+            if method.has_body() || !post_data_as_bytes.is_empty() {
+                easy.post(true)?;
+                easy.post_field_size(post_data_as_bytes.len() as u64)?;
+            }
+            if method != HTTPMethod::POST {
+                easy.custom_request(&method.to_string())?;
+            }
+        },
+    }
+
+    let mut response_headers = Vec::new();
+    let mut trailers = Vec::new();
+    let mut is_header_block_complete = false;
+    {
         let mut transfer = easy.transfer();
 
         transfer.read_function(|buf| {
             Ok(post_data_as_bytes.read(buf).unwrap_or(0))
-        }).unwrap();
-
+        })?;
 
         transfer.write_function(|d| {
-            data.extend_from_slice(d);
-            Ok(d.len())
-        }).unwrap();
+            // Returning less than was given makes curl abort the transfer
+            Ok(if on_data(d) && !stop.load(Ordering::Relaxed) { d.len() } else { 0 })
+        })?;
 
-        transfer.perform().unwrap_or(());
+        // Called about once a second while idle, so a silent stream can be stopped too
+        transfer.progress_function(|_, _, _, _| !stop.load(Ordering::Relaxed))?;
+
+        transfer.header_function(|h| {
+            let line = String::from_utf8_lossy(h).trim_end().to_string();
+            // A new status line starts the headers of a redirect or 100-continue response
+            if line.starts_with("HTTP/") {
+                response_headers.clear();
+                is_header_block_complete = false;
+            }
+            if is_header_block_complete {
+                // Lines after the final header block are trailers
+                if !line.is_empty() {
+                    trailers.push(line);
+                }
+            } else if line.is_empty() {
+                is_header_block_complete = true;
+                on_headers(&response_headers);
+            } else {
+                response_headers.push(line);
+            }
+            true
+        })?;
+
+        transfer.perform()?;
+    }
+
+    Ok(CurlResponse {
+        url: easy.effective_url()?.unwrap_or(&request.url).to_string(),
+        status: easy.response_code()?,
+        http_version: http_version(&response_headers),
+        headers: response_headers,
+        trailers,
+        body: Vec::new(),
+        download_size: easy.download_size()? as u64,
+        total_time: easy.total_time()?,
+        timings: Timings {
+            name_lookup: easy.namelookup_time()?,
+            connect: easy.connect_time()?,
+            app_connect: easy.appconnect_time()?,
+            pre_transfer: easy.pretransfer_time()?,
+            start_transfer: easy.starttransfer_time()?,
+        },
+        attempts: 1,
+    })
+}
+
+/// Protocol version of the status line, e.g. `HTTP/2`
+pub fn http_version(headers: &[String]) -> String {
+    headers.first()
+        .and_then(|status_line| status_line.split_whitespace().next())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Status code of the status line
+pub fn status(headers: &[String]) -> u32 {
+    headers.first()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Values of the headers named `name`, ignoring case, in the order they came
+pub fn header_values<'a>(headers: &'a [String], name: &str) -> Vec<&'a str> {
+    headers.iter()
+        .filter_map(|header| header.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .collect()
+}
+
+fn apply_settings(easy: &mut Easy, settings: &RequestSettings) -> Result<(), curl::Error> {
+    if let Some(http_version) = settings.http_version {
+        easy.http_version(http_version.curl_version())?;
+    }
+    if settings.decompress() {
+        // An empty encoding offers every encoding curl was built with
+        easy.accept_encoding("")?;
+    }
+    easy.connect_timeout(settings.connect_timeout())?;
+    easy.timeout(settings.timeout())?;
+    if let Some((limit, time)) = settings.low_speed() {
+        easy.low_speed_limit(limit)?;
+        easy.low_speed_time(time)?;
+    }
+    Ok(())
 }
@@ -1,15 +1,37 @@
-use std::{fs, path::{Path, PathBuf}};
+use std::{env, fs, io, path::{Path, PathBuf}, sync::OnceLock};
+
+/// Name of the directory that makes a project a workspace, with its own
+/// collection, history, variables and environments
+pub const WORKSPACE_DIRECTORY: &str = ".lazycurl";
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+static CONFIG: OnceLock<PathBuf> = OnceLock::new();
 
 pub enum Directory {
+    /// Data directory: the workspace, or `$XDG_DATA_HOME/lazycurl`
     Root,
+    /// Settings of lazycurl: `$XDG_CONFIG_HOME/lazycurl`, the same in and out
+    /// of workspaces
+    Config,
     History,
     Collection,
+    /// Cached GraphQL schemas, one file per endpoint
+    Schemas,
+    /// JUnit XML and JSON reports of collection runs
+    Reports,
+    /// Named sets of variables, one file per environment
+    Environments,
+    /// Reports of panics, one file per crash
+    Crashes,
 }
 
 impl Directory {
     pub fn path(&self) -> String {
         match self {
-            Directory::Root => ".".to_string(),
+            Directory::Root => ROOT.get_or_init(|| resolve(None, "LAZYCURL_DATA_DIR", "XDG_DATA_HOME", ".local/share"))
+                .to_string_lossy().to_string(),
+            Directory::Config => CONFIG.get_or_init(|| resolve_config(None))
+                .to_string_lossy().to_string(),
             Directory::History => {
                 let root = Directory::Root.path();
                 format!("{}/history", root)
@@ -18,15 +40,93 @@ impl Directory {
                 let root = Directory::Root.path();
                 format!("{}/collection", root)
             },
+            Directory::Schemas => {
+                let root = Directory::Root.path();
+                format!("{}/schemas", root)
+            },
+            Directory::Reports => {
+                let root = Directory::Root.path();
+                format!("{}/reports", root)
+            },
+            Directory::Environments => {
+                let root = Directory::Root.path();
+                format!("{}/environments", root)
+            },
+            Directory::Crashes => {
+                let root = Directory::Root.path();
+                format!("{}/crashes", root)
+            },
+        }
+    }
+}
+
+/// Choose the data and config directories, before any path is used. Each
+/// is the directory of the flag, or else of its `LAZYCURL_*_DIR` variable,
+/// or else the XDG base directory. The workspace comes before the XDG data
+/// directory. The data directory is created.
+pub fn init_directories(data_directory: Option<PathBuf>, config_directory: Option<PathBuf>) {
+    let root = resolve(data_directory, "LAZYCURL_DATA_DIR", "XDG_DATA_HOME", ".local/share");
+    let _ = fs::create_dir_all(&root);
+    let _ = ROOT.set(root);
+    let _ = CONFIG.set(resolve_config(config_directory));
+}
+
+fn resolve(directory: Option<PathBuf>, variable: &str, xdg_variable: &str, home_default: &str) -> PathBuf {
+    directory
+        .or_else(|| env::var_os(variable).filter(|value| !value.is_empty()).map(PathBuf::from))
+        .or_else(find_workspace)
+        .unwrap_or_else(|| xdg_directory(xdg_variable, home_default))
+}
+
+fn resolve_config(directory: Option<PathBuf>) -> PathBuf {
+    directory
+        .or_else(|| env::var_os("LAZYCURL_CONFIG_DIR").filter(|value| !value.is_empty()).map(PathBuf::from))
+        .unwrap_or_else(|| xdg_directory("XDG_CONFIG_HOME", ".config"))
+}
+
+/// The XDG base directory, relative values are ignored as the spec says
+fn xdg_directory(variable: &str, home_default: &str) -> PathBuf {
+    let base = env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").filter(|home| !home.is_empty()).map(|home| Path::new(&home).join(home_default)));
+    match base {
+        Some(base) => base.join("lazycurl"),
+        None => PathBuf::from("."),
+    }
+}
+
+/// The `.lazycurl` directory of the current directory or one of its parents,
+/// up to the root of the git repository
+pub fn find_workspace() -> Option<PathBuf> {
+    let current = env::current_dir().ok()?;
+    for directory in current.ancestors() {
+        let workspace = directory.join(WORKSPACE_DIRECTORY);
+        if workspace.is_dir() {
+            return Some(workspace);
+        }
+        if directory.join(".git").exists() {
+            break;
         }
     }
+    None
+}
+
+/// Where `init` creates the workspace: the root of the git repository, or
+/// else the current directory
+pub fn workspace_location() -> io::Result<PathBuf> {
+    let current = env::current_dir()?;
+    let root = current.ancestors()
+        .find(|directory| directory.join(".git").exists())
+        .unwrap_or(&current);
+    Ok(root.join(WORKSPACE_DIRECTORY))
 }
 
 // Ensure the "history" directory exists
 pub fn init_history_directory_if_not_exist() -> PathBuf {
     let dir = Directory::History.path();
     let dir_path = Path::new(dir.as_str());
-    fs::create_dir_all(dir_path);
+    let _ = fs::create_dir_all(dir_path);
     dir_path.to_owned()
 }
 
@@ -34,6 +134,38 @@ pub fn init_history_directory_if_not_exist() -> PathBuf {
 pub fn init_collection_directory_if_not_exist() -> PathBuf {
     let dir = Directory::Collection.path();
     let dir_path = Path::new(dir.as_str());
-    fs::create_dir_all(dir_path);
+    let _ = fs::create_dir_all(dir_path);
+    dir_path.to_owned()
+}
+
+// Ensure the "schemas" directory exists
+pub fn init_schemas_directory_if_not_exist() -> PathBuf {
+    let dir = Directory::Schemas.path();
+    let dir_path = Path::new(dir.as_str());
+    let _ = fs::create_dir_all(dir_path);
+    dir_path.to_owned()
+}
+
+// Ensure the "reports" directory exists
+pub fn init_reports_directory_if_not_exist() -> PathBuf {
+    let dir = Directory::Reports.path();
+    let dir_path = Path::new(dir.as_str());
+    let _ = fs::create_dir_all(dir_path);
+    dir_path.to_owned()
+}
+
+// Ensure the "environments" directory exists
+pub fn init_environments_directory_if_not_exist() -> PathBuf {
+    let dir = Directory::Environments.path();
+    let dir_path = Path::new(dir.as_str());
+    let _ = fs::create_dir_all(dir_path);
+    dir_path.to_owned()
+}
+
+// Ensure the "crashes" directory exists
+pub fn init_crashes_directory_if_not_exist() -> PathBuf {
+    let dir = Directory::Crashes.path();
+    let dir_path = Path::new(dir.as_str());
+    let _ = fs::create_dir_all(dir_path);
     dir_path.to_owned()
 }
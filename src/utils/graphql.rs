@@ -0,0 +1,399 @@
+use std::{collections::HashMap, fs, sync::mpsc::{self, Receiver}, thread};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{curl_service::{curl_call, CurlRequest}, directory::{init_schemas_directory_if_not_exist, Directory}};
+
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery { __schema { \
+    queryType { name } mutationType { name } subscriptionType { name } \
+    types { name fields(includeDeprecated: true) { name type { ...TypeRef } } } } } \
+    fragment TypeRef on __Type { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } }";
+
+/// The part of a GraphQL schema needed to complete and validate selections:
+/// the fields of every object and interface type
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Schema {
+    pub query_type: String,
+    pub mutation_type: Option<String>,
+    pub subscription_type: Option<String>,
+    /// Field name to the name of its (unwrapped) type, per type
+    pub types: HashMap<String, HashMap<String, String>>,
+}
+
+impl Schema {
+    /// Read the schema from an introspection response
+    pub fn from_introspection(response: &Value) -> Result<Schema, String> {
+        if let Some(errors) = response.get("errors").filter(|errors| !errors.is_null()) {
+            return Err(format!("introspection failed: {}", errors));
+        }
+        let schema = response.pointer("/data/__schema")
+            .ok_or_else(|| String::from("response has no data.__schema"))?;
+        let type_name = |pointer: &str| schema.pointer(pointer).and_then(Value::as_str).map(String::from);
+
+        let types = schema.get("types")
+            .and_then(Value::as_array)
+            .ok_or_else(|| String::from("schema has no types"))?
+            .iter()
+            .filter_map(|t| {
+                let name = t.get("name")?.as_str()?.to_string();
+                let fields = t.get("fields")?.as_array()?
+                    .iter()
+                    .filter_map(|field| Some((field.get("name")?.as_str()?.to_string(), named_type(field.get("type")?)?)))
+                    .collect();
+                Some((name, fields))
+            })
+            .collect();
+
+        Ok(Schema {
+            query_type: type_name("/queryType/name").unwrap_or_else(|| String::from("Query")),
+            mutation_type: type_name("/mutationType/name"),
+            subscription_type: type_name("/subscriptionType/name"),
+            types,
+        })
+    }
+
+    pub fn load_cached(url: &str) -> Option<Schema> {
+        let contents = fs::read_to_string(cache_path(url)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save_cache(&self, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+        init_schemas_directory_if_not_exist();
+        fs::write(cache_path(url), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn fields(&self, type_name: &str) -> Option<&HashMap<String, String>> {
+        self.types.get(type_name)
+    }
+}
+
+fn cache_path(url: &str) -> String {
+    let file_name = url.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("{}/{}.json", Directory::Schemas.path(), file_name)
+}
+
+/// Name of the type behind `NON_NULL` and `LIST` wrappers
+fn named_type(type_ref: &Value) -> Option<String> {
+    match type_ref.get("name").and_then(Value::as_str) {
+        Some(name) => Some(name.to_string()),
+        None => named_type(type_ref.get("ofType")?),
+    }
+}
+
+/// A GraphQL request as edited in the body window, sent as a JSON envelope
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GraphQLBody {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub variables: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_name: Option<String>,
+}
+
+impl GraphQLBody {
+    pub fn introspection() -> Self {
+        Self {
+            query: INTROSPECTION_QUERY.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// The JSON body of the request, fails when the variables are not a JSON object
+    pub fn envelope(&self) -> Result<String, String> {
+        let mut body = json!({ "query": self.query });
+        if !self.variables.trim().is_empty() {
+            let variables: Value = serde_json::from_str(&self.variables)
+                .map_err(|e| format!("Invalid variables: {}", e))?;
+            if !variables.is_object() {
+                return Err(String::from("Invalid variables: expected a JSON object"));
+            }
+            body["variables"] = variables;
+        }
+        if let Some(operation_name) = &self.operation_name {
+            body["operationName"] = json!(operation_name);
+        }
+        Ok(body.to_string())
+    }
+}
+
+/// Send the introspection query of `request` in a background thread, the
+/// schema is sent to the returned receiver once it is read
+pub fn introspect_in_background(request: CurlRequest) -> Receiver<Result<Schema, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let schema = curl_call(&request)
+            .map_err(|e| e.to_string())
+            .and_then(|response| serde_json::from_slice(&response.body)
+                .map_err(|e| format!("introspection response is not JSON: {}", e)))
+            .and_then(|response| Schema::from_introspection(&response));
+        let _ = sender.send(schema);
+    });
+    receiver
+}
+
+/// GraphQL servers expect the envelope to be sent as JSON
+pub fn add_json_content_type(headers: &mut Vec<String>) {
+    let has_content_type = headers.iter()
+        .filter_map(|header| header.split_once(':'))
+        .any(|(key, _)| key.trim().eq_ignore_ascii_case("content-type"));
+    if !has_content_type {
+        headers.push(String::from("Content-Type: application/json"));
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Name(String),
+    Punctuator(char),
+    Spread,
+    /// Strings, numbers and anything else without meaning for selections
+    Value,
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let chars = query.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+            continue;
+        } else if c == '.' && chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') {
+            tokens.push(Token::Spread);
+            i += 2;
+        } else if c == '"' {
+            let is_block_string = chars.get(i + 1) == Some(&'"') && chars.get(i + 2) == Some(&'"');
+            i += if is_block_string { 3 } else { 1 };
+            while i < chars.len() {
+                if chars[i] == '\\' {
+                    i += 1;
+                } else if is_block_string && chars[i..].starts_with(&['"', '"', '"']) {
+                    i += 2;
+                    break;
+                } else if !is_block_string && chars[i] == '"' {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push(Token::Value);
+        } else if "{}()[]:@$!=|&".contains(c) {
+            tokens.push(Token::Punctuator(c));
+        } else if c.is_ascii_digit() || c == '-' {
+            while i + 1 < chars.len() && (chars[i + 1].is_ascii_alphanumeric() || chars[i + 1] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Value);
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// Names of the operations defined in a document
+pub fn operation_names(query: &str) -> Vec<String> {
+    let tokens = tokenize(query);
+    let mut depth = 0;
+    let mut names = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Punctuator('{') => depth += 1,
+            Token::Punctuator('}') => depth -= 1,
+            Token::Name(keyword) if depth == 0 && matches!(keyword.as_str(), "query" | "mutation" | "subscription") => {
+                if let Some(Token::Name(name)) = tokens.get(i + 1) {
+                    names.push(name.clone());
+                }
+            },
+            _ => (),
+        }
+    }
+    names
+}
+
+/// Result of walking the selections of a document against a schema
+pub struct Analysis {
+    pub errors: Vec<String>,
+    /// Type whose fields can be selected at the end of the document
+    pub current_type: Option<String>,
+}
+
+/// Walk the selection sets of `query`, reporting fields that do not exist
+/// on the type they are selected from
+pub fn analyze(query: &str, schema: &Schema) -> Analysis {
+    let tokens = tokenize(query);
+    let mut errors = Vec::new();
+    // Type of every open selection set, None when unknown
+    let mut stack: Vec<Option<String>> = Vec::new();
+    // Type of the next top level selection set, the query type by default
+    let mut next_root: Option<Option<String>> = None;
+    let mut next_selection: Option<String> = None;
+
+    let known_type = |type_name: &str, errors: &mut Vec<String>| {
+        if schema.fields(type_name).is_some() {
+            Some(type_name.to_string())
+        } else {
+            errors.push(format!("Unknown type '{}'", type_name));
+            None
+        }
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Punctuator('{') => {
+                let selection_type = if stack.is_empty() {
+                    next_root.take().unwrap_or_else(|| Some(schema.query_type.clone()))
+                } else {
+                    next_selection.take()
+                };
+                stack.push(selection_type);
+            },
+            Token::Punctuator('}') => {
+                stack.pop();
+                next_selection = None;
+            },
+            Token::Punctuator('(') => {
+                // Arguments and variable definitions hold no selections
+                let mut depth = 0;
+                while i < tokens.len() {
+                    match tokens[i] {
+                        Token::Punctuator('(') => depth += 1,
+                        Token::Punctuator(')') => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    i += 1;
+                }
+            },
+            Token::Punctuator('@') => i += 1,
+            Token::Name(name) if stack.is_empty() => match name.as_str() {
+                "query" => next_root = Some(Some(schema.query_type.clone())),
+                "mutation" => next_root = Some(schema.mutation_type.clone().or_else(|| {
+                    errors.push(String::from("Schema has no mutations"));
+                    None
+                })),
+                "subscription" => next_root = Some(schema.subscription_type.clone().or_else(|| {
+                    errors.push(String::from("Schema has no subscriptions"));
+                    None
+                })),
+                "on" => {
+                    if let Some(Token::Name(type_name)) = tokens.get(i + 1) {
+                        next_root = Some(known_type(type_name, &mut errors));
+                        i += 1;
+                    }
+                },
+                _ => (),
+            },
+            Token::Spread => {
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(Token::Name(on)), Some(Token::Name(type_name))) if on == "on" => {
+                        next_selection = known_type(type_name, &mut errors);
+                        i += 2;
+                    },
+                    (Some(Token::Name(_)), _) => i += 1,
+                    _ => next_selection = stack.last().cloned().flatten(),
+                }
+            },
+            Token::Name(name) => {
+                let field = match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(Token::Punctuator(':')), Some(Token::Name(field))) => {
+                        i += 2;
+                        field
+                    },
+                    _ => name,
+                };
+                next_selection = match stack.last().cloned().flatten() {
+                    Some(parent) if field != "__typename" => {
+                        let field_type = schema.fields(&parent).and_then(|fields| fields.get(field));
+                        if field_type.is_none() {
+                            errors.push(format!("Unknown field '{}' on type '{}'", field, parent));
+                        }
+                        field_type.cloned()
+                    },
+                    _ => None,
+                };
+            },
+            _ => (),
+        }
+        i += 1;
+    }
+
+    Analysis {
+        errors,
+        current_type: stack.last().cloned().flatten(),
+    }
+}
+
+/// Fields completing the name being typed at the end of `text`
+pub fn complete(text: &str, schema: &Schema) -> (String, Vec<String>) {
+    let partial_start = text
+        .char_indices()
+        .rfind(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let partial = &text[partial_start..];
+
+    let Some(fields) = analyze(&text[..partial_start], schema).current_type
+        .and_then(|current_type| schema.fields(&current_type)) else {
+        return (partial.to_string(), Vec::new());
+    };
+
+    let mut suggestions = fields.keys()
+        .filter(|field| field.starts_with(partial) && field.as_str() != partial)
+        .cloned()
+        .collect::<Vec<_>>();
+    suggestions.sort();
+    (partial.to_string(), suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        let fields = |fields: &[(&str, &str)]| fields.iter()
+            .map(|(name, field_type)| (name.to_string(), field_type.to_string()))
+            .collect();
+        Schema {
+            query_type: String::from("Query"),
+            mutation_type: None,
+            subscription_type: None,
+            types: HashMap::from([
+                (String::from("Query"), fields(&[("user", "User")])),
+                (String::from("User"), fields(&[("name", "String"), ("nickname", "String"), ("email", "String")])),
+            ]),
+        }
+    }
+
+    #[test]
+    fn complete_after_ascii_separator() {
+        let (partial, suggestions) = complete("{ user { n", &schema());
+        assert_eq!(partial, "n");
+        assert_eq!(suggestions, vec!["name", "nickname"]);
+    }
+
+    #[test]
+    fn complete_after_multibyte_separator() {
+        for separator in ["\u{201c}", "\u{2014}", "\u{1f600}"] {
+            let (partial, suggestions) = complete(&format!("{{ user {{ {}ni", separator), &schema());
+            assert_eq!(partial, "ni");
+            assert_eq!(suggestions, vec!["nickname"]);
+
+            let (partial, _) = complete(&format!("{{ user {{ {}", separator), &schema());
+            assert_eq!(partial, "");
+        }
+    }
+}
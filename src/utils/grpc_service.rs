@@ -0,0 +1,436 @@
+use std::{collections::HashSet, path::Path, sync::mpsc::{self, Receiver}, thread};
+
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor, SerializeOptions};
+use prost_types::FileDescriptorProto;
+use serde_json::Value;
+
+use crate::{grpc::GrpcRequest, http_method::HTTPMethod, http_version::HTTPVersion, request_settings::RequestSettings};
+
+use super::curl_service::{curl_call, header_values, CurlRequest, CurlResponse};
+
+/// Reflection services, newest first
+const REFLECTION_SERVICES: [&str; 2] = [
+    "grpc.reflection.v1.ServerReflection",
+    "grpc.reflection.v1alpha.ServerReflection",
+];
+
+/// Status code of a call to a method the server does not implement
+const UNIMPLEMENTED: u32 = 12;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GrpcProtocol {
+    Grpc,
+    GrpcWeb,
+}
+
+impl GrpcProtocol {
+    pub fn from_method(method: HTTPMethod) -> Option<Self> {
+        match method {
+            HTTPMethod::GRPC => Some(GrpcProtocol::Grpc),
+            HTTPMethod::GRPCWEB => Some(GrpcProtocol::GrpcWeb),
+            _ => None,
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            GrpcProtocol::Grpc => "application/grpc+proto",
+            GrpcProtocol::GrpcWeb => "application/grpc-web+proto",
+        }
+    }
+}
+
+/// The server calls are made to, with the headers and settings of the request
+pub struct GrpcEndpoint {
+    pub url: String,
+    pub headers: Vec<String>,
+    pub settings: RequestSettings,
+    pub protocol: GrpcProtocol,
+}
+
+pub struct GrpcResponse {
+    pub response: CurlResponse,
+    /// Response messages as JSON, more than one for server streaming methods
+    pub messages: Vec<Value>,
+    /// `grpc-status`, missing when the server did not answer with gRPC
+    pub status: Option<u32>,
+    pub status_message: String,
+    pub trailers: Vec<String>,
+}
+
+impl GrpcResponse {
+    /// The response message, or an array of them when there is not exactly one
+    pub fn messages_value(&self) -> Value {
+        match self.messages.as_slice() {
+            [message] => message.clone(),
+            messages => Value::Array(messages.to_vec()),
+        }
+    }
+
+    /// Status code with its name, e.g. `5 NOT_FOUND`
+    pub fn status_description(&self) -> String {
+        match self.status {
+            Some(status) => format!("{} {}", status, status_name(status)),
+            None => String::from("no grpc-status"),
+        }
+    }
+}
+
+impl GrpcEndpoint {
+    /// Send the framed messages to `path` and read the framed response
+    /// messages, the status and the trailers
+    fn call(&self, path: &str, messages: &[Vec<u8>]) -> Result<(GrpcResponse, Vec<Vec<u8>>), String> {
+        let mut headers = self.headers.clone();
+        headers.push(format!("Content-Type: {}", self.protocol.content_type()));
+        match self.protocol {
+            GrpcProtocol::Grpc => headers.push(String::from("TE: trailers")),
+            GrpcProtocol::GrpcWeb => headers.push(String::from("X-Grpc-Web: 1")),
+        }
+
+        let mut settings = self.settings.clone();
+        // Message frames are never compressed by curl
+        settings.decompress = Some(false);
+        if self.protocol == GrpcProtocol::Grpc && settings.http_version.is_none() {
+            settings.http_version = Some(if self.url.starts_with("https://") {
+                HTTPVersion::Http2Tls
+            } else {
+                HTTPVersion::Http2PriorKnowledge
+            });
+        }
+
+        let request = CurlRequest {
+            url: format!("{}/{}", self.url.trim_end_matches('/'), path),
+            method: HTTPMethod::POST,
+            headers,
+            body: encode_frames(messages),
+            settings,
+        };
+        let response = curl_call(&request).map_err(|e| e.to_string())?;
+
+        let (frames, web_trailers) = decode_frames(&response.body)?;
+        let trailers = response.trailers.iter().cloned().chain(web_trailers).collect::<Vec<_>>();
+        // A call failing before any message puts the status in the headers
+        let status_header = |name: &str| {
+            header_values(&trailers, name).into_iter()
+                .chain(response.header_values(name))
+                .next()
+                .map(str::to_string)
+        };
+        let status = status_header("grpc-status").and_then(|status| status.parse().ok());
+        let status_message = status_header("grpc-message").map(percent_decode).unwrap_or_default();
+
+        Ok((GrpcResponse { response, messages: Vec::new(), status, status_message, trailers }, frames))
+    }
+}
+
+/// Call `method` with the request message(s) in `json`. A client streaming
+/// method takes a JSON array with one element per message.
+pub fn grpc_call(endpoint: &GrpcEndpoint, method: &MethodDescriptor, json: &str) -> Result<GrpcResponse, String> {
+    let json = if json.trim().is_empty() { "{}" } else { json };
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid request message: {}", e))?;
+    let values = match value {
+        Value::Array(values) if method.is_client_streaming() => values,
+        value => vec![value],
+    };
+    let messages = values.into_iter()
+        .map(|value| DynamicMessage::deserialize(method.input(), value)
+            .map(|message| message.encode_to_vec())
+            .map_err(|e| format!("Invalid request message: {}", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let path = format!("{}/{}", method.parent_service().full_name(), method.name());
+    let (mut response, frames) = endpoint.call(&path, &messages)?;
+    response.messages = frames.iter()
+        .map(|frame| {
+            let message = DynamicMessage::decode(method.output(), frame.as_slice())
+                .map_err(|e| format!("Invalid response message: {}", e))?;
+            message.serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new().skip_default_fields(false))
+                .map_err(|e| e.to_string())
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(response)
+}
+
+/// `grpc_call` in a background thread, the response is sent to the
+/// returned receiver when the call is done
+pub fn grpc_in_background(endpoint: GrpcEndpoint, method: MethodDescriptor, json: String) -> Receiver<Result<GrpcResponse, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(grpc_call(&endpoint, &method, &json));
+    });
+    receiver
+}
+
+/// JSON of an input message with every field set to its default, a starting
+/// point for editing the request
+pub fn request_template(message: &MessageDescriptor) -> String {
+    DynamicMessage::new(message.clone())
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new().skip_default_fields(false))
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+        .unwrap_or_else(|| String::from("{}"))
+}
+
+/// Load the service definitions of a request, compiling its proto files or
+/// asking the server through reflection
+pub fn load_descriptors(grpc: &GrpcRequest, endpoint: &GrpcEndpoint) -> Result<DescriptorPool, String> {
+    if grpc.proto_files.is_empty() {
+        return reflect(endpoint);
+    }
+
+    let import_paths = if grpc.import_paths.is_empty() {
+        grpc.proto_files.iter()
+            .map(|file| Path::new(file).parent().map(Path::to_path_buf).unwrap_or_default())
+            .map(|dir| if dir.as_os_str().is_empty() { Path::new(".").to_path_buf() } else { dir })
+            .collect()
+    } else {
+        grpc.import_paths.iter().map(|path| Path::new(path).to_path_buf()).collect::<Vec<_>>()
+    };
+    let file_descriptor_set = protox::compile(&grpc.proto_files, import_paths).map_err(|e| e.to_string())?;
+    DescriptorPool::from_file_descriptor_set(file_descriptor_set).map_err(|e| e.to_string())
+}
+
+/// `load_descriptors` in a background thread, compiling proto files and
+/// reflection can both take a while
+pub fn load_descriptors_in_background(grpc: GrpcRequest, endpoint: GrpcEndpoint) -> Receiver<Result<DescriptorPool, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(load_descriptors(&grpc, &endpoint));
+    });
+    receiver
+}
+
+/// Services of the pool that can be called, i.e. without the reflection service
+pub fn service_names(pool: &DescriptorPool) -> Vec<String> {
+    pool.services()
+        .map(|service| service.full_name().to_string())
+        .filter(|name| !REFLECTION_SERVICES.contains(&name.as_str()))
+        .collect()
+}
+
+fn reflect(endpoint: &GrpcEndpoint) -> Result<DescriptorPool, String> {
+    let services = reflection_call(endpoint, vec![MessageRequest::ListServices(String::from("*"))])?
+        .into_iter()
+        .flat_map(|response| match response {
+            MessageResponse::ServiceList(list) => list.service,
+            _ => Vec::new(),
+        })
+        .map(|service| service.name)
+        .filter(|name| !REFLECTION_SERVICES.contains(&name.as_str()))
+        .collect::<Vec<_>>();
+    if services.is_empty() {
+        return Err(String::from("Server reflection lists no services"));
+    }
+
+    let mut pool = DescriptorPool::global();
+    let mut files: Vec<FileDescriptorProto> = Vec::new();
+    let mut requests = services.into_iter().map(MessageRequest::FileContainingSymbol).collect::<Vec<_>>();
+    // Files depend on files the server may not have sent yet
+    while !requests.is_empty() {
+        let known_count = files.len();
+        for response in reflection_call(endpoint, requests)? {
+            if let MessageResponse::FileDescriptors(response) = response {
+                for bytes in response.file_descriptor_proto {
+                    let file = FileDescriptorProto::decode(bytes.as_slice()).map_err(|e| e.to_string())?;
+                    if !files.iter().any(|known| known.name == file.name) {
+                        files.push(file);
+                    }
+                }
+            }
+        }
+        if files.len() == known_count {
+            break;
+        }
+
+        let known = files.iter().map(|file| file.name().to_string()).collect::<HashSet<_>>();
+        requests = files.iter()
+            .flat_map(|file| file.dependency.iter())
+            .filter(|dependency| !known.contains(*dependency) && pool.get_file_by_name(dependency).is_none())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|dependency| MessageRequest::FileByFilename(dependency.clone()))
+            .collect();
+    }
+
+    pool.add_file_descriptor_protos(files).map_err(|e| e.to_string())?;
+    Ok(pool)
+}
+
+/// Send reflection requests in a single stream, trying each version of the
+/// reflection service until one is implemented
+fn reflection_call(endpoint: &GrpcEndpoint, requests: Vec<MessageRequest>) -> Result<Vec<MessageResponse>, String> {
+    let messages = requests.into_iter()
+        .map(|request| ServerReflectionRequest { host: String::new(), message_request: Some(request) }.encode_to_vec())
+        .collect::<Vec<_>>();
+
+    for service in REFLECTION_SERVICES {
+        let (response, frames) = endpoint.call(&format!("{}/ServerReflectionInfo", service), &messages)?;
+        match response.status {
+            Some(0) => (),
+            Some(UNIMPLEMENTED) => continue,
+            _ => return Err(format!("Server reflection failed: {} {}", response.status_description(), response.status_message)),
+        }
+
+        return frames.iter()
+            .map(|frame| {
+                let response = ServerReflectionResponse::decode(frame.as_slice()).map_err(|e| e.to_string())?;
+                match response.message_response {
+                    Some(MessageResponse::Error(error)) => Err(format!("Server reflection failed: {}", error.error_message)),
+                    Some(response) => Ok(response),
+                    None => Err(String::from("Server reflection sent an empty response")),
+                }
+            })
+            .collect();
+    }
+    Err(String::from("Server does not implement reflection, load .proto files instead"))
+}
+
+/// Prefix every message with its compression flag and length
+fn encode_frames(messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for message in messages {
+        body.push(0);
+        body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        body.extend_from_slice(message);
+    }
+    body
+}
+
+/// Split a response body into messages. gRPC-Web sends the trailers as a
+/// last frame flagged with 0x80.
+fn decode_frames(mut body: &[u8]) -> Result<(Vec<Vec<u8>>, Vec<String>), String> {
+    let mut messages = Vec::new();
+    let mut trailers = Vec::new();
+    while !body.is_empty() {
+        if body.len() < 5 {
+            return Err(String::from("Truncated gRPC frame"));
+        }
+        let flags = body[0];
+        let length = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+        let Some(frame) = body.get(5..5 + length) else {
+            return Err(String::from("Truncated gRPC frame"));
+        };
+
+        if flags & 0x80 != 0 {
+            trailers.extend(String::from_utf8_lossy(frame)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty()));
+        } else if flags & 0x01 != 0 {
+            return Err(String::from("Compressed gRPC messages are not supported"));
+        } else {
+            messages.push(frame.to_vec());
+        }
+        body = &body[5 + length..];
+    }
+    Ok((messages, trailers))
+}
+
+/// `grpc-message` is percent encoded
+fn percent_decode(value: String) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn status_name(status: u32) -> &'static str {
+    match status {
+        0 => "OK",
+        1 => "CANCELLED",
+        2 => "UNKNOWN",
+        3 => "INVALID_ARGUMENT",
+        4 => "DEADLINE_EXCEEDED",
+        5 => "NOT_FOUND",
+        6 => "ALREADY_EXISTS",
+        7 => "PERMISSION_DENIED",
+        8 => "RESOURCE_EXHAUSTED",
+        9 => "FAILED_PRECONDITION",
+        10 => "ABORTED",
+        11 => "OUT_OF_RANGE",
+        12 => "UNIMPLEMENTED",
+        13 => "INTERNAL",
+        14 => "UNAVAILABLE",
+        15 => "DATA_LOSS",
+        16 => "UNAUTHENTICATED",
+        _ => "",
+    }
+}
+
+// Messages of grpc/reflection/v1/reflection.proto, the same in v1alpha
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionRequest {
+    #[prost(string, tag = "1")]
+    host: String,
+    #[prost(oneof = "MessageRequest", tags = "3, 4, 7")]
+    message_request: Option<MessageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageRequest {
+    #[prost(string, tag = "3")]
+    FileByFilename(String),
+    #[prost(string, tag = "4")]
+    FileContainingSymbol(String),
+    #[prost(string, tag = "7")]
+    ListServices(String),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionResponse {
+    #[prost(oneof = "MessageResponse", tags = "4, 6, 7")]
+    message_response: Option<MessageResponse>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageResponse {
+    #[prost(message, tag = "4")]
+    FileDescriptors(FileDescriptorResponse),
+    #[prost(message, tag = "6")]
+    ServiceList(ListServiceResponse),
+    #[prost(message, tag = "7")]
+    Error(ErrorResponse),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorResponse {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    file_descriptor_proto: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ListServiceResponse {
+    #[prost(message, repeated, tag = "1")]
+    service: Vec<ServiceResponse>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceResponse {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorResponse {
+    #[prost(int32, tag = "1")]
+    error_code: i32,
+    #[prost(string, tag = "2")]
+    error_message: String,
+}
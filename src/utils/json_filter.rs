@@ -0,0 +1,417 @@
+use std::{cmp::Ordering, collections::BTreeMap, fs};
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use super::{directory::Directory, jsonpath};
+
+/// Filters kept per request
+const RECENT_FILTER_COUNT: usize = 10;
+
+/// Filters used on the responses of each request, newest first, kept in
+/// `filters.json` in the root directory
+#[derive(Serialize, Deserialize, Default)]
+pub struct RecentFilters {
+    #[serde(flatten)]
+    requests: BTreeMap<String, Vec<String>>,
+}
+
+impl RecentFilters {
+    fn path() -> String {
+        format!("{}/filters.json", Directory::Root.path())
+    }
+
+    pub fn load() -> RecentFilters {
+        fs::read_to_string(Self::path()).ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(Self::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, request: &str) -> &[String] {
+        self.requests.get(request).map_or(&[], Vec::as_slice)
+    }
+
+    /// Move `filter` to the front of the filters of `request`
+    pub fn add(&mut self, request: &str, filter: &str) {
+        let filters = self.requests.entry(request.to_string()).or_default();
+        filters.retain(|recent| recent != filter);
+        filters.insert(0, filter.to_string());
+        filters.truncate(RECENT_FILTER_COUNT);
+    }
+}
+
+/// Filter a JSON value with a JSONPath expression starting with `$`, or a jq
+/// style expression starting with `.`. The result is pretty printed JSON,
+/// jq expressions print each output on its own like jq does.
+pub fn apply(expression: &str, value: &Value) -> Result<String, String> {
+    let expression = expression.trim();
+    if expression.starts_with('$') {
+        return match jsonpath::query(expression, value)? {
+            Some(selected) => Ok(pretty(&selected)),
+            None => Err(String::from("Nothing matches the path")),
+        };
+    }
+    if !expression.starts_with('.') && !expression.starts_with(|c: char| c.is_alphabetic()) {
+        return Err(String::from("Start with $ for JSONPath or . for jq"));
+    }
+
+    let outputs = run(expression, value)?;
+    Ok(outputs.iter().map(pretty).collect::<Vec<_>>().join("\n"))
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Outputs of a jq style pipeline, the supported subset is paths (`.`,
+/// `.key`, `."key"`, `.[0]`, `.[-1]`, `.[]`, `..`), `|` and the functions
+/// `keys`, `length`, `type`, `first`, `last`, `map(f)` and `select(f)`
+fn run(expression: &str, value: &Value) -> Result<Vec<Value>, String> {
+    let mut outputs = vec![value.clone()];
+    for stage in split_top_level(expression, '|') {
+        let stage = stage.trim();
+        if stage.is_empty() {
+            return Err(String::from("Empty filter around |"));
+        }
+        outputs = outputs.iter()
+            .map(|output| apply_stage(stage, output))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    Ok(outputs)
+}
+
+fn apply_stage(stage: &str, value: &Value) -> Result<Vec<Value>, String> {
+    if stage.starts_with('.') {
+        return apply_path(stage, value);
+    }
+    if let Some(argument) = function_argument(stage, "map") {
+        let Value::Array(items) = value else {
+            return Err(format!("Cannot map over {}", type_name(value)));
+        };
+        let mapped = items.iter()
+            .map(|item| run(argument, item))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(vec![Value::Array(mapped.into_iter().flatten().collect())]);
+    }
+    if let Some(argument) = function_argument(stage, "select") {
+        return Ok(if condition(argument, value)? { vec![value.clone()] } else { Vec::new() });
+    }
+
+    let output = match stage {
+        "keys" => match value {
+            Value::Object(map) => {
+                let mut keys = map.keys().cloned().collect::<Vec<_>>();
+                keys.sort();
+                Value::from(keys)
+            },
+            Value::Array(items) => Value::from((0..items.len()).collect::<Vec<_>>()),
+            value => return Err(format!("{} has no keys", type_name(value))),
+        },
+        "length" => match value {
+            Value::Object(map) => Value::from(map.len()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::String(text) => Value::from(text.chars().count()),
+            Value::Null => Value::from(0),
+            Value::Number(number) => Value::from(number.as_f64().unwrap_or_default().abs()),
+            value => return Err(format!("{} has no length", type_name(value))),
+        },
+        "type" => Value::from(type_name(value)),
+        "first" => return apply_path(".[0]", value),
+        "last" => return apply_path(".[-1]", value),
+        _ => return Err(format!("Unknown filter {}", stage)),
+    };
+    Ok(vec![output])
+}
+
+/// `select` conditions, a path compared to a JSON literal with `==`, `!=`,
+/// `<`, `<=`, `>` or `>=`, or a path that must not be false or null
+fn condition(argument: &str, value: &Value) -> Result<bool, String> {
+    for operator in ["==", "!=", "<=", ">=", "<", ">"] {
+        if let Some((left, right)) = split_operator(argument, operator) {
+            let literal = serde_json::from_str::<Value>(right.trim())
+                .map_err(|_| format!("Expected a JSON value after {}, e.g. \"text\" or 42", operator))?;
+            let outputs = run(left.trim(), value)?;
+            return Ok(outputs.iter().any(|output| {
+                let ordering = compare(output, &literal);
+                match operator {
+                    "==" => ordering == Some(Ordering::Equal),
+                    "!=" => ordering != Some(Ordering::Equal),
+                    "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                    "<" => ordering == Some(Ordering::Less),
+                    _ => ordering == Some(Ordering::Greater),
+                }
+            }));
+        }
+    }
+    Ok(run(argument, value)?.iter().any(|output| !matches!(output, Value::Null | Value::Bool(false))))
+}
+
+/// Numbers by value, strings alphabetically, other values only by equality
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => jsonpath::compare_numbers(left, right),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (left, right) if left == right => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn apply_path(path: &str, value: &Value) -> Result<Vec<Value>, String> {
+    let chars = path.chars().collect::<Vec<_>>();
+    let mut outputs = vec![value.clone()];
+    let mut position = 0;
+
+    while position < chars.len() {
+        let step;
+        (step, position) = parse_step(&chars, position)?;
+        outputs = outputs.iter()
+            .map(|output| step.apply(output))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    Ok(outputs)
+}
+
+enum Step {
+    Identity,
+    Key(String),
+    Index(i64),
+    Iterate,
+    Recurse,
+}
+
+impl Step {
+    fn apply(&self, value: &Value) -> Result<Vec<Value>, String> {
+        Ok(match (self, value) {
+            (Step::Identity, value) => vec![value.clone()],
+            (Step::Key(key), Value::Object(map)) => vec![map.get(key).cloned().unwrap_or(Value::Null)],
+            (Step::Key(_) | Step::Index(_), Value::Null) => vec![Value::Null],
+            (Step::Key(key), value) => return Err(format!("Cannot index {} with \"{}\"", type_name(value), key)),
+            (Step::Index(index), Value::Array(items)) => {
+                let index = if *index < 0 { items.len() as i64 + index } else { *index };
+                vec![usize::try_from(index).ok().and_then(|index| items.get(index)).cloned().unwrap_or(Value::Null)]
+            },
+            (Step::Index(_), value) => return Err(format!("Cannot index {} with a number", type_name(value))),
+            (Step::Iterate, Value::Array(items)) => items.clone(),
+            (Step::Iterate, Value::Object(map)) => map.values().cloned().collect(),
+            (Step::Iterate, value) => return Err(format!("Cannot iterate over {}", type_name(value))),
+            (Step::Recurse, value) => {
+                let mut found = vec![value.clone()];
+                recurse(value, &mut found);
+                found
+            },
+        })
+    }
+}
+
+fn recurse(value: &Value, found: &mut Vec<Value>) {
+    let children: Vec<&Value> = match value {
+        Value::Object(map) => map.values().collect(),
+        Value::Array(items) => items.iter().collect(),
+        _ => return,
+    };
+    for child in children {
+        found.push(child.clone());
+        recurse(child, found);
+    }
+}
+
+/// One step of a path starting at `position`, and where the next one starts
+fn parse_step(chars: &[char], position: usize) -> Result<(Step, usize), String> {
+    match (chars[position], chars.get(position + 1)) {
+        ('.', Some('.')) => Ok((Step::Recurse, position + 2)),
+        ('.', Some('[')) => parse_step(chars, position + 1),
+        ('.', Some('"')) => {
+            let (key, next) = parse_quoted(chars, position + 1)?;
+            Ok((Step::Key(key), next))
+        },
+        ('.', Some(c)) if c.is_alphanumeric() || *c == '_' => {
+            let end = chars[position + 1..].iter()
+                .position(|c| !(c.is_alphanumeric() || *c == '_' || *c == '-'))
+                .map_or(chars.len(), |offset| position + 1 + offset);
+            Ok((Step::Key(chars[position + 1..end].iter().collect()), end))
+        },
+        ('.', None) => Ok((Step::Identity, position + 1)),
+        ('[', _) => {
+            let close = chars[position..].iter()
+                .position(|c| *c == ']')
+                .map(|offset| position + offset)
+                .ok_or_else(|| String::from("Missing ]"))?;
+            let inner = chars[position + 1..close].iter().collect::<String>();
+            let inner = inner.trim();
+            let step = if inner.is_empty() {
+                Step::Iterate
+            } else if inner.starts_with('"') {
+                Step::Key(parse_quoted(&inner.chars().collect::<Vec<_>>(), 0)?.0)
+            } else {
+                Step::Index(inner.parse().map_err(|_| format!("Invalid index [{}]", inner))?)
+            };
+            Ok((step, close + 1))
+        },
+        (c, _) => Err(format!("Unexpected '{}' in path", c)),
+    }
+}
+
+fn parse_quoted(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let end = chars[start + 1..].iter()
+        .position(|c| *c == '"')
+        .map(|offset| start + 1 + offset)
+        .ok_or_else(|| String::from("Missing closing \""))?;
+    Ok((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+/// The argument of `name(argument)`
+fn function_argument<'a>(stage: &'a str, name: &str) -> Option<&'a str> {
+    stage.strip_prefix(name)?.trim().strip_prefix('(')?.strip_suffix(')')
+}
+
+/// Split at `separator` outside of quotes, brackets and parentheses
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' | '[' if !in_quotes => depth += 1,
+            ')' | ']' if !in_quotes => depth -= 1,
+            c if c == separator && depth == 0 && !in_quotes => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            },
+            _ => (),
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// `text` split at the first `operator` outside of quotes and brackets
+fn split_operator<'a>(text: &'a str, operator: &str) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0;
+    let mut in_quotes = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' | '[' if !in_quotes => depth += 1,
+            ')' | ']' if !in_quotes => depth -= 1,
+            _ if depth == 0 && !in_quotes && text[index..].starts_with(operator) => {
+                return Some((&text[..index], &text[index + operator.len()..]));
+            },
+            _ => (),
+        }
+    }
+    None
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn document() -> Value {
+        json!({
+            "id": 9007199254740992_i64,
+            "first name": "Ada",
+            "items": [
+                { "n": 1, "name": "a|b" },
+                { "n": 2, "name": "c" },
+                { "n": 3, "name": "d", "sub": { "n": 4 } },
+            ],
+        })
+    }
+
+    #[test]
+    fn negative_indexes_count_from_the_end() {
+        assert_eq!(run(".items[-1].n", &document()), Ok(vec![json!(3)]));
+        assert_eq!(run(".items | .[-2].name", &document()), Ok(vec![json!("c")]));
+        assert_eq!(run(".items[-4]", &document()), Ok(vec![Value::Null]));
+        assert_eq!(run(".items | last | .n", &document()), Ok(vec![json!(3)]));
+    }
+
+    #[test]
+    fn recurse_into_every_value() {
+        let numbers = run(".items[2] | .. | select(type == \"number\")", &document());
+        assert_eq!(numbers, Ok(vec![json!(3), json!(4)]));
+    }
+
+    #[test]
+    fn quoted_keys() {
+        assert_eq!(run(".\"first name\"", &document()), Ok(vec![json!("Ada")]));
+        assert_eq!(run(".[\"first name\"]", &document()), Ok(vec![json!("Ada")]));
+        assert_eq!(run(".items[0][\"name\"]", &document()), Ok(vec![json!("a|b")]));
+    }
+
+    #[test]
+    fn map_and_select_with_each_operator() {
+        for (operator, expected) in [
+            ("==", json!([2])),
+            ("!=", json!([1, 3])),
+            ("<", json!([1])),
+            ("<=", json!([1, 2])),
+            (">", json!([3])),
+            (">=", json!([2, 3])),
+        ] {
+            let filter = format!(".items | map(select(.n {} 2) | .n)", operator);
+            assert_eq!(run(&filter, &document()), Ok(vec![expected]), "{}", filter);
+        }
+        assert_eq!(run(".items | map(select(.sub) | .name)", &document()), Ok(vec![json!(["d"])]));
+        assert_eq!(run(".items[] | select(.name > \"b\") | .n", &document()), Ok(vec![json!(2), json!(3)]));
+    }
+
+    #[test]
+    fn pipes_inside_quotes_are_not_stages() {
+        assert_eq!(run(".items[] | select(.name == \"a|b\") | .n", &document()), Ok(vec![json!(1)]));
+        assert_eq!(run(".items[] | select(.name != \"a|b\") | .n", &document()), Ok(vec![json!(2), json!(3)]));
+    }
+
+    #[test]
+    fn integers_compare_exactly() {
+        assert_eq!(run("select(.id == 9007199254740992)", &document()).map(|outputs| outputs.len()), Ok(1));
+        assert_eq!(run("select(.id == 9007199254740993)", &document()).map(|outputs| outputs.len()), Ok(0));
+        assert_eq!(run("select(.id < 9007199254740993)", &document()).map(|outputs| outputs.len()), Ok(1));
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        let error = |expression: &str| apply(expression, &document()).unwrap_err();
+        assert_eq!(error("items"), "Unknown filter items");
+        assert_eq!(error("[0]"), "Start with $ for JSONPath or . for jq");
+        assert_eq!(error("$.missing"), "Nothing matches the path");
+        assert_eq!(error(".items | | .n"), "Empty filter around |");
+        assert_eq!(error(".items[0"), "Missing ]");
+        assert_eq!(error(".items[first]"), "Invalid index [first]");
+        assert_eq!(error(".\"first name"), "Missing closing \"");
+        assert_eq!(error(".id | .[]"), "Cannot iterate over number");
+        assert_eq!(error(".items[0].name.first"), "Cannot index string with \"first\"");
+        assert_eq!(error(".\"first name\"[0]"), "Cannot index string with a number");
+        assert_eq!(error("map(.n)"), "Cannot map over object");
+        assert_eq!(error(".items[] | select(.n == two)"), "Expected a JSON value after ==, e.g. \"text\" or 42");
+        assert_eq!(error(".id | keys"), "number has no keys");
+        assert_eq!(error(".id?"), "Unexpected '?' in path");
+    }
+}
@@ -0,0 +1,251 @@
+use std::cmp::Ordering;
+
+use serde_json::{Number, Value};
+
+/// One step of a JSONPath expression
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    /// Negative indexes count from the end
+    Index(i64),
+    Wildcard,
+    /// `..key`, every value named key at any depth
+    Descendant(String),
+    /// `..*` or `..[*]`, every value at any depth
+    AllDescendants,
+}
+
+/// Values selected by a JSONPath expression, the supported subset is
+/// `$`, `.key`, `['key']`, `[0]`, `[-1]`, `[*]`, `.*` and `..key`
+pub fn select<'a>(path: &str, value: &'a Value) -> Result<Vec<&'a Value>, String> {
+    let mut selected = vec![value];
+    for segment in parse(path)? {
+        selected = selected.into_iter()
+            .flat_map(|value| apply(&segment, value))
+            .collect();
+    }
+    Ok(selected)
+}
+
+/// The value selected by the expression, an array when it selects more than
+/// one value and None when it selects nothing
+pub fn query(path: &str, value: &Value) -> Result<Option<Value>, String> {
+    let selected = select(path, value)?;
+    Ok(match selected.as_slice() {
+        [] => None,
+        [value] => Some((*value).clone()),
+        values => Some(Value::Array(values.iter().map(|value| (*value).clone()).collect())),
+    })
+}
+
+/// Text of a selected value, strings without their quotes
+pub fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Numbers by value, integers exactly and floats as f64, so `1` equals
+/// `1.0` but large integers that round to the same f64 differ
+pub fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    let integer = |number: &Number| number.as_i64().map(i128::from).or_else(|| number.as_u64().map(i128::from));
+    match (integer(left), integer(right)) {
+        (Some(left), Some(right)) => Some(left.cmp(&right)),
+        _ => left.as_f64()?.partial_cmp(&right.as_f64()?),
+    }
+}
+
+/// The JSONPath at the start of `text` and the trimmed rest, keys in
+/// brackets may contain spaces, e.g. `$['first name']`
+pub fn split_path(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, c) if c.is_whitespace() => return (&text[..index], text[index..].trim()),
+            _ => (),
+        }
+    }
+    (text, "")
+}
+
+fn apply<'a>(segment: &Segment, value: &'a Value) -> Vec<&'a Value> {
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+        (Segment::Index(index), Value::Array(values)) => {
+            let index = if *index < 0 { values.len() as i64 + index } else { *index };
+            usize::try_from(index).ok().and_then(|index| values.get(index)).into_iter().collect()
+        },
+        (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+        (Segment::Wildcard, Value::Array(values)) => values.iter().collect(),
+        (Segment::Descendant(_) | Segment::AllDescendants, _) => {
+            let mut found = Vec::new();
+            descendants(segment, value, &mut found);
+            found
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn descendants<'a>(segment: &Segment, value: &'a Value, found: &mut Vec<&'a Value>) {
+    let children: Vec<(Option<&String>, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(key, value)| (Some(key), value)).collect(),
+        Value::Array(values) => values.iter().map(|value| (None, value)).collect(),
+        _ => return,
+    };
+    for (key, child) in children {
+        match segment {
+            Segment::Descendant(name) if key == Some(name) => found.push(child),
+            Segment::AllDescendants => found.push(child),
+            _ => (),
+        }
+        descendants(segment, child, found);
+    }
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let path = path.trim();
+    let rest = path.strip_prefix('$')
+        .ok_or_else(|| format!("JSONPath must start with $: {}", path))?;
+    let chars = rest.chars().collect::<Vec<_>>();
+    let mut segments = Vec::new();
+    let mut position = 0;
+
+    while position < chars.len() {
+        match chars[position] {
+            '.' if chars.get(position + 1) == Some(&'.') => {
+                position += 2;
+                if chars.get(position) == Some(&'*') {
+                    position += 1;
+                    segments.push(Segment::AllDescendants);
+                } else if chars.get(position) == Some(&'[') {
+                    let (segment, next) = parse_bracket(&chars, position)?;
+                    position = next;
+                    segments.push(match segment {
+                        Segment::Key(key) => Segment::Descendant(key),
+                        Segment::Wildcard => Segment::AllDescendants,
+                        _ => return Err(format!("Unsupported recursive index in {}", path)),
+                    });
+                } else {
+                    let (name, next) = parse_name(&chars, position);
+                    if name.is_empty() {
+                        return Err(format!("Missing name after .. in {}", path));
+                    }
+                    position = next;
+                    segments.push(Segment::Descendant(name));
+                }
+            },
+            '.' => {
+                position += 1;
+                if chars.get(position) == Some(&'*') {
+                    position += 1;
+                    segments.push(Segment::Wildcard);
+                } else {
+                    let (name, next) = parse_name(&chars, position);
+                    if name.is_empty() {
+                        return Err(format!("Missing name after . in {}", path));
+                    }
+                    position = next;
+                    segments.push(Segment::Key(name));
+                }
+            },
+            '[' => {
+                let (segment, next) = parse_bracket(&chars, position)?;
+                position = next;
+                segments.push(segment);
+            },
+            c => return Err(format!("Unexpected '{}' in {}", c, path)),
+        }
+    }
+    Ok(segments)
+}
+
+fn parse_name(chars: &[char], start: usize) -> (String, usize) {
+    let end = chars[start..].iter()
+        .position(|c| matches!(c, '.' | '['))
+        .map_or(chars.len(), |offset| start + offset);
+    (chars[start..end].iter().collect(), end)
+}
+
+/// `[0]`, `[-1]`, `[*]`, `['key']` or `["key"]` starting at the `[`
+fn parse_bracket(chars: &[char], start: usize) -> Result<(Segment, usize), String> {
+    let close = chars[start..].iter()
+        .position(|c| *c == ']')
+        .map(|offset| start + offset)
+        .ok_or_else(|| String::from("Missing ] in JSONPath"))?;
+    let inner = chars[start + 1..close].iter().collect::<String>();
+    let inner = inner.trim();
+
+    let segment = if inner == "*" {
+        Segment::Wildcard
+    } else if let Some(key) = inner.strip_prefix('\'').and_then(|key| key.strip_suffix('\''))
+        .or_else(|| inner.strip_prefix('"').and_then(|key| key.strip_suffix('"'))) {
+        Segment::Key(key.to_string())
+    } else {
+        Segment::Index(inner.parse().map_err(|_| format!("Invalid index [{}] in JSONPath", inner))?)
+    };
+    Ok((segment, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn document() -> Value {
+        json!({
+            "id": 1,
+            "first name": "Ada",
+            "a.b": true,
+            "items": [
+                { "id": 2, "tags": ["x", "y"] },
+                { "id": 3, "owner": { "id": 4 } },
+            ],
+        })
+    }
+
+    #[test]
+    fn negative_indexes_count_from_the_end() {
+        assert_eq!(query("$.items[-1].id", &document()), Ok(Some(json!(3))));
+        assert_eq!(query("$.items[0].tags[-2]", &document()), Ok(Some(json!("x"))));
+        assert_eq!(query("$.items[-3]", &document()), Ok(None));
+    }
+
+    #[test]
+    fn descendant_keys_at_any_depth() {
+        assert_eq!(query("$..id", &document()), Ok(Some(json!([1, 2, 3, 4]))));
+        assert_eq!(query("$.items..id", &document()), Ok(Some(json!([2, 3, 4]))));
+        assert_eq!(query("$..['id']", &document()), Ok(Some(json!([1, 2, 3, 4]))));
+        assert_eq!(query("$..missing", &document()), Ok(None));
+    }
+
+    #[test]
+    fn bracketed_quoted_keys() {
+        assert_eq!(query("$['first name']", &document()), Ok(Some(json!("Ada"))));
+        assert_eq!(query("$[\"a.b\"]", &document()), Ok(Some(json!(true))));
+        assert_eq!(query("$['items'][1]['owner'].id", &document()), Ok(Some(json!(4))));
+        assert_eq!(split_path("$['first name'] == \"Ada\""), ("$['first name']", "== \"Ada\""));
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        assert_eq!(query("items", &document()), Err(String::from("JSONPath must start with $: items")));
+        assert_eq!(query("$.items[0", &document()), Err(String::from("Missing ] in JSONPath")));
+        assert_eq!(query("$.items[first]", &document()), Err(String::from("Invalid index [first] in JSONPath")));
+        assert_eq!(query("$.", &document()), Err(String::from("Missing name after . in $.")));
+        assert_eq!(query("$..", &document()), Err(String::from("Missing name after .. in $..")));
+        assert_eq!(query("$id", &document()), Err(String::from("Unexpected 'i' in $id")));
+    }
+
+    #[test]
+    fn integers_compare_exactly() {
+        let number = |value: Value| value.as_number().cloned().unwrap();
+        assert_eq!(compare_numbers(&number(json!(1)), &number(json!(1.0))), Some(Ordering::Equal));
+        assert_eq!(compare_numbers(&number(json!(9007199254740992_i64)), &number(json!(9007199254740993_i64))), Some(Ordering::Less));
+        assert_eq!(compare_numbers(&number(json!(-1)), &number(json!(u64::MAX))), Some(Ordering::Less));
+    }
+}
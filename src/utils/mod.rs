@@ -2,3 +2,13 @@ pub mod tui_frame_util;
 pub mod curl_service;
 pub mod directory;
 pub mod tui_block;
+pub mod bytes;
+pub mod binary;
+pub mod charset;
+pub mod sse;
+pub mod websocket_service;
+pub mod graphql;
+pub mod grpc_service;
+pub mod jsonpath;
+pub mod json_filter;
+pub mod runner;
@@ -0,0 +1,471 @@
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc::{self, Receiver}, Arc, Mutex, PoisonError}, thread, time::{Duration, Instant}};
+
+use chrono::Local;
+use serde_json::json;
+
+use crate::{assertion::{evaluate, response_json, AssertionResult}, extraction::{extract, ExtractionResult}, http_file::HttpFile, http_method::HTTPMethod, lazycurl_file::LazyCurlFile, request_settings::RequestSettings, script::{run_post_response, run_pre_request, ConsoleEntry}, variables::Variables};
+
+use super::{directory::init_reports_directory_if_not_exist, curl_service::{curl_call, CurlResponse}, grpc_service::{grpc_call, load_descriptors, GrpcEndpoint, GrpcProtocol, GrpcResponse}};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// A status other than the expected ones was received or a test failed
+    Failed,
+    /// The request could not be loaded or sent
+    Error,
+    /// Not run, WebSocket sessions and requests after a stop
+    Skipped,
+}
+
+impl Outcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Passed => "PASS",
+            Outcome::Failed => "FAIL",
+            Outcome::Error => "ERROR",
+            Outcome::Skipped => "SKIP",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RunOptions {
+    /// Number of requests run at the same time, 1 runs them in order
+    pub parallel: usize,
+    pub stop_on_failure: bool,
+    /// Expected status codes. When empty any 2xx or 3xx, unless the request
+    /// tests the status itself
+    pub expect_status: Vec<u32>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            parallel: 1,
+            stop_on_failure: false,
+            expect_status: Vec::new(),
+        }
+    }
+}
+
+pub struct RequestResult {
+    pub path: PathBuf,
+    pub method: Option<HTTPMethod>,
+    pub url: String,
+    pub outcome: Outcome,
+    /// Why the request did not pass
+    pub message: Option<String>,
+    pub response: Option<CurlResponse>,
+    pub grpc_response: Option<GrpcResponse>,
+    /// Outcome of the tests of the request
+    pub assertion_results: Vec<AssertionResult>,
+    /// Variables set from the response for the requests after it
+    pub extraction_results: Vec<ExtractionResult>,
+    /// Output of the scripts of the request
+    pub console: Vec<ConsoleEntry>,
+    pub time: Duration,
+}
+
+impl RequestResult {
+    fn new(path: &Path, outcome: Outcome, message: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            method: None,
+            url: String::new(),
+            outcome,
+            message: Some(message),
+            response: None,
+            grpc_response: None,
+            assertion_results: Vec::new(),
+            extraction_results: Vec::new(),
+            console: Vec::new(),
+            time: Duration::ZERO,
+        }
+    }
+
+    /// The HTTP response, also of gRPC calls
+    pub fn http_response(&self) -> Option<&CurlResponse> {
+        self.response.as_ref().or(self.grpc_response.as_ref().map(|grpc_response| &grpc_response.response))
+    }
+
+    pub fn status(&self) -> Option<u32> {
+        self.http_response().map(|response| response.status)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.http_response().map_or(0, |response| response.body.len() as u64)
+    }
+}
+
+pub enum RunnerMessage {
+    Finished(usize, Box<RequestResult>),
+    /// Every request has finished or was skipped, with the variables the
+    /// run set, None for the ones its scripts removed
+    Done(BTreeMap<String, Option<String>>),
+}
+
+pub struct RunnerHandle {
+    pub receiver: Receiver<RunnerMessage>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RunnerHandle {
+    /// Skip the requests that have not started yet
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The file itself, or every JSON file below the directory in path order.
+/// `.http` and `.rest` files give a path per request, see `HttpFile::request_path`.
+pub fn request_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if HttpFile::is_http_file(path) && path.is_file() {
+        return HttpFile::request_paths(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()));
+    }
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() || HttpFile::is_http_file(&entry_path) {
+            files.extend(request_files(&entry_path)?);
+        } else if entry_path.extension().is_some_and(|extension| extension == "json") {
+            files.push(entry_path);
+        }
+    }
+    // Requests of a .http file stay in file order
+    files.sort_by_cached_key(|file| HttpFile::split_request_path(file).unwrap_or_else(|| (file.clone(), 0)));
+    Ok(files)
+}
+
+/// Run the requests, handing every result to `on_result` with the index of
+/// its file as soon as it is finished. Results arrive in file order unless
+/// requests are run in parallel. Once `stop` is set the remaining requests
+/// are skipped. Requests use and set the shared `variables`, so a request
+/// can use a value extracted by an earlier one.
+pub fn run_files(
+    files: &[PathBuf],
+    options: &RunOptions,
+    global_settings: &RequestSettings,
+    variables: &Mutex<Variables>,
+    stop: &AtomicBool,
+    on_result: impl Fn(usize, RequestResult) + Sync,
+) {
+    let next_index = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..options.parallel.clamp(1, files.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(index) else {
+                    break;
+                };
+
+                let result = if stop.load(Ordering::Relaxed) {
+                    RequestResult::new(path, Outcome::Skipped, String::from("Not run, the run was stopped"))
+                } else {
+                    run_request(path, &options.expect_status, global_settings, variables)
+                };
+                if options.stop_on_failure && matches!(result.outcome, Outcome::Failed | Outcome::Error) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                on_result(index, result);
+            });
+        }
+    });
+}
+
+/// Run the requests in a background thread, sending each result to the returned handle
+pub fn run_in_background(files: Vec<PathBuf>, options: RunOptions, global_settings: RequestSettings, variables: Variables) -> RunnerHandle {
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_requested = Arc::clone(&stop);
+
+    thread::spawn(move || {
+        let before = variables.clone();
+        let variables = Mutex::new(variables);
+        run_files(&files, &options, &global_settings, &variables, &stop_requested, |index, result| {
+            let _ = sender.send(RunnerMessage::Finished(index, Box::new(result)));
+        });
+        let after = variables.into_inner().unwrap_or_else(PoisonError::into_inner);
+        let _ = sender.send(RunnerMessage::Done(after.changes_since(&before)));
+    });
+
+    RunnerHandle { receiver, stop }
+}
+
+pub fn run_request(path: &Path, expect_status: &[u32], global_settings: &RequestSettings, variables: &Mutex<Variables>) -> RequestResult {
+    let mut lazycurl_file = match LazyCurlFile::from_path(path) {
+        Ok(lazycurl_file) => lazycurl_file,
+        Err(e) => return RequestResult::new(path, Outcome::Error, e.to_string()),
+    };
+    let lock_variables = || variables.lock().unwrap_or_else(PoisonError::into_inner);
+    let pre_request_script = lazycurl_file.pre_request_script.clone();
+    let script_output = run_pre_request(&pre_request_script, &mut lazycurl_file, &mut lock_variables());
+    let lazycurl_file = lazycurl_file.with_variables(&lock_variables());
+    let mut result = RequestResult {
+        method: Some(lazycurl_file.http_method),
+        url: lazycurl_file.url.clone(),
+        console: script_output.console,
+        ..RequestResult::new(path, Outcome::Passed, String::new())
+    };
+    result.message = None;
+    if let Some(error) = script_output.error {
+        result.outcome = Outcome::Error;
+        result.message = Some(format!("Pre-request script: {}", error));
+        return result;
+    }
+
+    // Tests, extractions and the post-response script, in that order
+    let check_response = |result: &mut RequestResult, response: &CurlResponse, json: Option<&serde_json::Value>| {
+        let mut variables = lock_variables();
+        result.assertion_results = evaluate(&lazycurl_file.assertions, response, json);
+        result.extraction_results = extract(&lazycurl_file.extractions, response, json, &mut variables);
+        let mut script_output = run_post_response(&lazycurl_file.post_response_script, response, json, &mut variables);
+        result.assertion_results.append(&mut script_output.test_results);
+        result.console.append(&mut script_output.console);
+        script_output.error
+    };
+
+    let started = Instant::now();
+    let mut script_error = None;
+    if let Some(protocol) = GrpcProtocol::from_method(lazycurl_file.http_method) {
+        match run_grpc(&lazycurl_file, protocol, global_settings) {
+            Ok(response) => {
+                script_error = check_response(&mut result, &response.response, Some(&response.messages_value()));
+                if response.status != Some(0) {
+                    result.message = Some(format!("gRPC status {} {}", response.status_description(), response.status_message));
+                }
+                result.grpc_response = Some(response);
+            },
+            Err(e) => {
+                result.outcome = Outcome::Error;
+                result.message = Some(e);
+            },
+        }
+    } else if lazycurl_file.http_method == HTTPMethod::WS {
+        result.outcome = Outcome::Skipped;
+        result.message = Some(String::from("WebSocket sessions can only be run in the TUI"));
+    } else {
+        match lazycurl_file.curl_request(global_settings).and_then(|request| curl_call(&request).map_err(|e| e.to_string())) {
+            Ok(response) => {
+                script_error = check_response(&mut result, &response, response_json(&response).as_ref());
+                result.response = Some(response);
+            },
+            Err(e) => {
+                result.outcome = Outcome::Error;
+                result.message = Some(e);
+            },
+        }
+    }
+    result.time = started.elapsed();
+
+    if result.outcome == Outcome::Passed {
+        let tests_status = lazycurl_file.assertions.iter().any(|assertion| assertion.is_status());
+        let grpc_status = result.grpc_response.as_ref().and_then(|response| response.status);
+        let status = result.status().unwrap_or_default();
+        let is_expected_status = if !expect_status.is_empty() {
+            expect_status.contains(&status)
+        } else {
+            tests_status || (200..400).contains(&status)
+        };
+        let failed_tests = result.assertion_results.iter()
+            .filter(|assertion_result| !assertion_result.passed)
+            .map(AssertionResult::to_string)
+            .collect::<Vec<_>>();
+
+        // gRPC calls must also end with status OK
+        if !is_expected_status || grpc_status.unwrap_or(0) != 0 || !failed_tests.is_empty() || script_error.is_some() {
+            result.outcome = Outcome::Failed;
+        }
+        if let Some(error) = script_error {
+            result.message = Some(format!("Post-response script: {}", error));
+        } else if !failed_tests.is_empty() {
+            result.message = Some(failed_tests.join("; "));
+        } else if !is_expected_status {
+            result.message = Some(format!("Unexpected status {}", status));
+        }
+    }
+    result
+}
+
+fn run_grpc(lazycurl_file: &LazyCurlFile, protocol: GrpcProtocol, global_settings: &RequestSettings) -> Result<GrpcResponse, String> {
+    let grpc = lazycurl_file.grpc.clone().unwrap_or_default();
+    let endpoint = GrpcEndpoint {
+        url: lazycurl_file.url.clone(),
+        headers: lazycurl_file.headers.clone(),
+        settings: lazycurl_file.settings.merge(global_settings),
+        protocol,
+    };
+    let pool = load_descriptors(&grpc, &endpoint)?;
+    let method = pool.get_service_by_name(&grpc.service)
+        .and_then(|service| service.methods().find(|method| method.name() == grpc.method))
+        .ok_or_else(|| format!("Unknown gRPC method {}", grpc.path()))?;
+    grpc_call(&endpoint, &method, &lazycurl_file.body)
+}
+
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    /// Sum of the request times
+    pub time: Duration,
+}
+
+impl Summary {
+    pub fn new<'a>(results: impl IntoIterator<Item = &'a RequestResult>) -> Self {
+        let mut summary = Self { passed: 0, failed: 0, errors: 0, skipped: 0, time: Duration::ZERO };
+        for result in results {
+            match result.outcome {
+                Outcome::Passed => summary.passed += 1,
+                Outcome::Failed => summary.failed += 1,
+                Outcome::Error => summary.errors += 1,
+                Outcome::Skipped => summary.skipped += 1,
+            }
+            summary.time += result.time;
+        }
+        summary
+    }
+
+    pub fn total(&self) -> usize {
+        self.passed + self.failed + self.errors + self.skipped
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} requests: {} passed, {} failed, {} errors, {} skipped, {} ms request time",
+            self.total(), self.passed, self.failed, self.errors, self.skipped, self.time.as_millis())
+    }
+}
+
+/// Name of the request in reports, its path below the collection root
+pub fn result_name(result: &RequestResult, root: &Path) -> String {
+    result.path.strip_prefix(root).unwrap_or(&result.path).display().to_string()
+}
+
+pub fn json_report(results: &[&RequestResult], root: &Path) -> String {
+    let summary = Summary::new(results.iter().copied());
+    let requests = results.iter()
+        .map(|result| json!({
+            "name": result_name(result, root),
+            "method": result.method.map(|method| method.to_string()),
+            "url": result.url,
+            "outcome": result.outcome.label(),
+            "status": result.status(),
+            "time_ms": result.time.as_millis() as u64,
+            "size": result.size(),
+            "message": result.message,
+            "assertions": assertions_json(&result.assertion_results),
+            "extractions": extractions_json(&result.extraction_results),
+            "console": result.console.iter().map(ConsoleEntry::to_string).collect::<Vec<_>>(),
+        }))
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&json!({
+        "collection": root.display().to_string(),
+        "timestamp": Local::now().to_rfc3339(),
+        "summary": {
+            "total": summary.total(),
+            "passed": summary.passed,
+            "failed": summary.failed,
+            "errors": summary.errors,
+            "skipped": summary.skipped,
+            "time_ms": summary.time.as_millis() as u64,
+        },
+        "requests": requests,
+    })).unwrap_or_default()
+}
+
+pub fn assertions_json(assertion_results: &[AssertionResult]) -> Vec<serde_json::Value> {
+    assertion_results.iter()
+        .map(|assertion_result| json!({
+            "assertion": assertion_result.assertion.to_string(),
+            "passed": assertion_result.passed,
+            "actual": assertion_result.actual,
+        }))
+        .collect()
+}
+
+pub fn extractions_json(extraction_results: &[ExtractionResult]) -> Vec<serde_json::Value> {
+    extraction_results.iter()
+        .map(|extraction_result| match &extraction_result.value {
+            Ok(value) => json!({ "variable": extraction_result.variable, "value": value }),
+            Err(reason) => json!({ "variable": extraction_result.variable, "error": reason }),
+        })
+        .collect()
+}
+
+pub fn junit_report(results: &[&RequestResult], root: &Path) -> String {
+    let summary = Summary::new(results.iter().copied());
+    let suite_name = escape_xml(&root.display().to_string());
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        suite_name, summary.total(), summary.failed, summary.errors, summary.skipped, summary.time.as_secs_f64()));
+    xml.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+        suite_name, summary.total(), summary.failed, summary.errors, summary.skipped, summary.time.as_secs_f64(),
+        Local::now().format("%Y-%m-%dT%H:%M:%S")));
+
+    for result in results {
+        xml.push_str(&format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape_xml(&result_name(result, root)), suite_name, result.time.as_secs_f64()));
+        let message = escape_xml(result.message.as_deref().unwrap_or_default());
+        match result.outcome {
+            Outcome::Passed => xml.push_str(">\n"),
+            Outcome::Failed => {
+                let failure_type = if result.assertion_results.iter().any(|assertion_result| !assertion_result.passed) { "assertion" } else { "status" };
+                xml.push_str(&format!(">\n      <failure message=\"{}\" type=\"{}\"/>\n", message, failure_type));
+            },
+            Outcome::Error => xml.push_str(&format!(">\n      <error message=\"{}\" type=\"transport\"/>\n", message)),
+            Outcome::Skipped => xml.push_str(&format!(">\n      <skipped message=\"{}\"/>\n", message)),
+        }
+        if let Some(method) = result.method {
+            let mut output = format!("{} {} {}",
+                method,
+                result.url,
+                result.status().map(|status| status.to_string()).unwrap_or_default());
+            for assertion_result in &result.assertion_results {
+                output.push_str(&format!("\n{}", assertion_result));
+            }
+            for extraction_result in &result.extraction_results {
+                output.push_str(&format!("\n{}", extraction_result));
+            }
+            for entry in &result.console {
+                output.push_str(&format!("\n{}", entry));
+            }
+            xml.push_str(&format!("      <system-out>{}</system-out>\n", escape_xml(&output)));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Write the JUnit XML and JSON reports of a run to the reports directory,
+/// returns the path of the JUnit report
+pub fn save_reports(results: &[&RequestResult], root: &Path) -> io::Result<PathBuf> {
+    let dir_path = init_reports_directory_if_not_exist();
+    let timestamp = Local::now().format("%Y-%m-%dT%H-%M-%S").to_string();
+    let junit_path = dir_path.join(format!("run_{}.xml", timestamp));
+    fs::write(&junit_path, junit_report(results, root))?;
+    fs::write(dir_path.join(format!("run_{}.json", timestamp)), json_report(results, root))?;
+    Ok(junit_path)
+}
+
+fn escape_xml(value: &str) -> String {
+    value.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .map(|c| match c {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            '\'' => String::from("&apos;"),
+            c => c.to_string(),
+        })
+        .collect()
+}
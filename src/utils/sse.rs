@@ -0,0 +1,85 @@
+use chrono::{DateTime, Local};
+
+/// A dispatched `text/event-stream` event
+pub struct SseEvent {
+    pub received_at: DateTime<Local>,
+    pub id: Option<String>,
+    pub event: String,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+/// Incremental `text/event-stream` parser, fed with body chunks as they arrive
+#[derive(Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    last_event_id: Option<String>,
+    event: Option<String>,
+    data: Vec<String>,
+    retry: Option<u64>,
+    skip_line_feed: bool,
+}
+
+impl SseParser {
+    /// Parse a chunk, returning the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for byte in chunk {
+            // "\r\n" ends a single line
+            if self.skip_line_feed && *byte == b'\n' {
+                self.skip_line_feed = false;
+                continue;
+            }
+            self.skip_line_feed = *byte == b'\r';
+
+            if *byte == b'\n' || *byte == b'\r' {
+                let line = String::from_utf8_lossy(&self.buffer).to_string();
+                self.buffer.clear();
+                if let Some(event) = self.process_line(&line) {
+                    events.push(event);
+                }
+            } else {
+                self.buffer.push(*byte);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" => self.retry = value.parse().ok().or(self.retry),
+            _ => (),
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let retry = self.retry.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(SseEvent {
+            received_at: Local::now(),
+            id: self.last_event_id.clone(),
+            event: event.unwrap_or_else(|| String::from("message")),
+            data: self.data.drain(..).collect::<Vec<_>>().join("\n"),
+            retry,
+        })
+    }
+}
@@ -1,11 +1,11 @@
 use ratatui::widgets::{ Borders, Block };
 use ratatui::prelude::{ Style, Modifier };
-use ratatui::style::Color;
+use crate::config::Config;
 
 pub fn main_block<'a>(selected: &bool, title: &'a str) -> Block<'a> {
     // Highlight color
     let border_style = if *selected {
-        Style::default().fg(Color::Green)
+        Style::default().fg(Config::get().theme.selected_border)
     } else {
         Style::default()
     };
@@ -0,0 +1,139 @@
+use std::{io::ErrorKind, net::TcpStream, sync::mpsc::{self, Receiver, Sender, TryRecvError}, thread, time::Duration};
+
+use tungstenite::{client::IntoClientRequest, http::{HeaderName, HeaderValue}, stream::MaybeTlsStream, Message, WebSocket};
+
+use crate::websocket::{WebSocketDirection, WebSocketFrame, WebSocketFrameKind};
+
+use super::bytes::to_hex;
+
+/// How long a read waits for a frame before pending commands are sent
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+pub enum WebSocketCommand {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping,
+    Close,
+}
+
+pub enum WebSocketEvent {
+    Frame(WebSocketFrame),
+    /// The connection is gone, no further events follow
+    Closed,
+}
+
+pub struct WebSocketHandle {
+    commands: Sender<WebSocketCommand>,
+    pub events: Receiver<WebSocketEvent>,
+}
+
+impl WebSocketHandle {
+    pub fn send(&self, command: WebSocketCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+/// Connect to a ws:// or wss:// URL in a background thread. Commands sent
+/// to the handle are written to the socket, received frames and connection
+/// changes come back as events.
+pub fn websocket_connect(url: String, headers: Vec<String>) -> WebSocketHandle {
+    let (command_sender, command_receiver) = mpsc::channel();
+    let (event_sender, event_receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let info = |message: String| WebSocketEvent::Frame(WebSocketFrame::info(message));
+        let _ = event_sender.send(info(format!("Connecting to {}", url)));
+
+        match connect(&url, &headers) {
+            Ok(mut socket) => {
+                let _ = event_sender.send(info(String::from("Connected")));
+                run_session(&mut socket, &command_receiver, &event_sender);
+            },
+            Err(e) => {
+                let _ = event_sender.send(info(format!("Connection failed: {}", e)));
+            },
+        }
+        let _ = event_sender.send(WebSocketEvent::Closed);
+    });
+
+    WebSocketHandle { commands: command_sender, events: event_receiver }
+}
+
+fn connect(url: &str, headers: &[String]) -> Result<WebSocket<MaybeTlsStream<TcpStream>>, Box<dyn std::error::Error>> {
+    let mut request = url.into_client_request()?;
+    for header in headers {
+        let Some((key, value)) = header.split_once(':') else {
+            continue;
+        };
+        if key.trim().is_empty() {
+            continue;
+        }
+        request.headers_mut().append(
+            HeaderName::from_bytes(key.trim().as_bytes())?,
+            HeaderValue::from_str(value.trim())?);
+    }
+
+    let (mut socket, _) = tungstenite::connect(request)?;
+    match socket.get_mut() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(READ_TIMEOUT))?,
+        MaybeTlsStream::NativeTls(stream) => stream.get_mut().set_read_timeout(Some(READ_TIMEOUT))?,
+        _ => (),
+    }
+    Ok(socket)
+}
+
+fn run_session(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>, commands: &Receiver<WebSocketCommand>, events: &Sender<WebSocketEvent>) {
+    let frame = |direction, kind, payload| WebSocketEvent::Frame(WebSocketFrame::new(direction, kind, payload));
+
+    loop {
+        loop {
+            let (message, sent) = match commands.try_recv() {
+                Ok(WebSocketCommand::Text(text)) => (Message::Text(text.clone()), frame(WebSocketDirection::Sent, WebSocketFrameKind::Text, text)),
+                Ok(WebSocketCommand::Binary(bytes)) => (Message::Binary(bytes.clone()), frame(WebSocketDirection::Sent, WebSocketFrameKind::Binary, to_hex(&bytes, " "))),
+                Ok(WebSocketCommand::Ping) => (Message::Ping(Vec::new()), frame(WebSocketDirection::Sent, WebSocketFrameKind::Ping, String::new())),
+                Ok(WebSocketCommand::Close) => (Message::Close(None), frame(WebSocketDirection::Sent, WebSocketFrameKind::Close, String::new())),
+                Err(TryRecvError::Empty) => break,
+                // The session was dropped by the UI
+                Err(TryRecvError::Disconnected) => return,
+            };
+            match socket.send(message) {
+                Ok(()) => (),
+                // The frame is queued and written by a later read or send
+                Err(tungstenite::Error::Io(e)) if is_timeout(e.kind()) => (),
+                Err(e) => {
+                    let _ = events.send(WebSocketEvent::Frame(WebSocketFrame::info(format!("Send failed: {}", e))));
+                    continue;
+                },
+            }
+            let _ = events.send(sent);
+        }
+
+        let received = match socket.read() {
+            Ok(Message::Text(text)) => frame(WebSocketDirection::Received, WebSocketFrameKind::Text, text),
+            Ok(Message::Binary(bytes)) => frame(WebSocketDirection::Received, WebSocketFrameKind::Binary, to_hex(&bytes, " ")),
+            Ok(Message::Ping(bytes)) => frame(WebSocketDirection::Received, WebSocketFrameKind::Ping, to_hex(&bytes, " ")),
+            Ok(Message::Pong(bytes)) => frame(WebSocketDirection::Received, WebSocketFrameKind::Pong, to_hex(&bytes, " ")),
+            Ok(Message::Close(close_frame)) => {
+                let reason = close_frame
+                    .map(|close_frame| format!("{} {}", u16::from(close_frame.code), close_frame.reason))
+                    .unwrap_or_default();
+                frame(WebSocketDirection::Received, WebSocketFrameKind::Close, reason)
+            },
+            Ok(Message::Frame(_)) => continue,
+            Err(tungstenite::Error::Io(e)) if is_timeout(e.kind()) => continue,
+            Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                let _ = events.send(WebSocketEvent::Frame(WebSocketFrame::info(String::from("Connection closed"))));
+                return;
+            },
+            Err(e) => {
+                let _ = events.send(WebSocketEvent::Frame(WebSocketFrame::info(format!("Connection lost: {}", e))));
+                return;
+            },
+        };
+        let _ = events.send(received);
+    }
+}
+
+fn is_timeout(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
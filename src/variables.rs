@@ -0,0 +1,227 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use serde::{Serialize, Deserialize};
+
+use crate::utils::directory::{init_environments_directory_if_not_exist, Directory};
+
+/// Values referenced as `{{name}}` in the URL, headers and body of requests.
+/// They are set by the extraction rules of earlier responses and kept in
+/// `variables.json` in the root directory, so they outlive the session.
+///
+/// The values of the active environment, a file in the `environments`
+/// directory, fill in the names without a value of their own. The
+/// `@name = value` definitions of an open `.http` file take precedence over
+/// both. Neither is written to `variables.json`.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Variables {
+    #[serde(flatten)]
+    pub values: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub environment: Option<String>,
+    #[serde(skip)]
+    pub environment_values: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub file_values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    fn path() -> String {
+        format!("{}/variables.json", Directory::Root.path())
+    }
+
+    /// Load `variables.json` and the active environment, an environment that
+    /// cannot be read is left out rather than failing the variables
+    pub fn load() -> Result<Variables, Box<dyn std::error::Error>> {
+        let path = Self::path();
+        let mut variables: Variables = if Path::new(&path).exists() {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            Variables::default()
+        };
+        let _ = variables.use_environment(Self::active_environment());
+        Ok(variables)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(Self::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn environment_path(name: &str) -> PathBuf {
+        Path::new(&Directory::Environments.path()).join(format!("{}.json", name))
+    }
+
+    fn active_environment_path() -> PathBuf {
+        Path::new(&Directory::Environments.path()).join(".active")
+    }
+
+    /// Names of the environments, sorted
+    pub fn environments() -> Vec<String> {
+        let Ok(entries) = fs::read_dir(Directory::Environments.path()) else {
+            return Vec::new();
+        };
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn active_environment() -> Option<String> {
+        let name = fs::read_to_string(Self::active_environment_path()).ok()?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    pub fn load_environment(name: &str) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(Self::environment_path(name))
+            .map_err(|e| format!("environment {}: {}", name, e))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save_environment(name: &str, values: &BTreeMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+        init_environments_directory_if_not_exist();
+        fs::write(Self::environment_path(name), serde_json::to_string_pretty(values)?)?;
+        Ok(())
+    }
+
+    /// Layer the values of the environment under the variables, None removes
+    /// the environment. The choice is not saved, see `save_active_environment`.
+    pub fn use_environment(&mut self, name: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.environment_values = match &name {
+            Some(name) => Self::load_environment(name)?,
+            None => BTreeMap::new(),
+        };
+        self.environment = name;
+        Ok(())
+    }
+
+    /// Make the current environment the one loaded by the next `load`
+    pub fn save_active_environment(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.environment == Self::active_environment() {
+            return Ok(());
+        }
+        init_environments_directory_if_not_exist();
+        fs::write(Self::active_environment_path(), self.environment.clone().unwrap_or_default())?;
+        Ok(())
+    }
+
+    /// Layer the definitions of a `.http` file over the variables, in file
+    /// order so a value can use the ones defined before it
+    pub fn use_file_variables(&mut self, definitions: &[(String, String)]) {
+        self.file_values.clear();
+        for (name, value) in definitions {
+            let value = self.substitute(value);
+            self.file_values.insert(name.clone(), value);
+        }
+    }
+
+    /// The value of the file variable, or else of the variable, or else of
+    /// the active environment
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.file_values.get(name)
+            .or_else(|| self.values.get(name))
+            .or_else(|| self.environment_values.get(name))
+    }
+
+    /// Every value, the file variables over the variables over the active environment
+    pub fn merged(&self) -> BTreeMap<String, String> {
+        let mut merged = self.environment_values.clone();
+        merged.extend(self.values.clone());
+        merged.extend(self.file_values.clone());
+        merged
+    }
+
+    /// Keep the values of `merged` that were variables already or differ
+    /// from the active environment. File variables left as they were keep
+    /// the variable of the same name.
+    pub fn set_merged(&mut self, merged: BTreeMap<String, String>) {
+        let mut values = BTreeMap::new();
+        for (name, value) in merged {
+            if self.file_values.get(&name) == Some(&value) {
+                if let Some(previous) = self.values.get(&name) {
+                    values.insert(name, previous.clone());
+                }
+            } else if self.values.contains_key(&name) || self.environment_values.get(&name) != Some(&value) {
+                values.insert(name, value);
+            }
+        }
+        self.values = values;
+    }
+
+    /// The variables set since `before`, None for the ones removed
+    pub fn changes_since(&self, before: &Variables) -> BTreeMap<String, Option<String>> {
+        let mut changes = BTreeMap::new();
+        for (name, value) in &self.values {
+            if before.values.get(name) != Some(value) {
+                changes.insert(name.clone(), Some(value.clone()));
+            }
+        }
+        for name in before.values.keys() {
+            if !self.values.contains_key(name) {
+                changes.insert(name.clone(), None);
+            }
+        }
+        changes
+    }
+
+    /// Set and remove the variables of `changes_since`, leaving the others
+    pub fn apply_changes(&mut self, changes: BTreeMap<String, Option<String>>) {
+        for (name, value) in changes {
+            match value {
+                Some(value) => self.values.insert(name, value),
+                None => self.values.remove(&name),
+            };
+        }
+    }
+
+    /// Names are made of letters, digits, `_`, `-` and `.`
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    }
+
+    pub fn set(&mut self, name: &str, value: String) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Replace every `{{name}}` with the value of the variable, placeholders
+    /// of unknown variables are left as they are
+    pub fn substitute(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + length].trim();
+            result.push_str(&rest[..start]);
+            match self.get(name) {
+                Some(value) => result.push_str(value),
+                None => result.push_str(&rest[start..start + length + 4]),
+            }
+            rest = &rest[start + length + 4..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Names of the placeholders in `text` without a value
+    pub fn missing(&self, text: &str) -> Vec<String> {
+        let mut missing = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + length].trim().to_string();
+            if self.get(&name).is_none() && !missing.contains(&name) {
+                missing.push(name);
+            }
+            rest = &rest[start + length + 4..];
+        }
+        missing
+    }
+}
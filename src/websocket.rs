@@ -0,0 +1,63 @@
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use strum::Display;
+
+#[derive(Display, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum WebSocketDirection {
+    #[strum(serialize = "->")]
+    Sent,
+    #[strum(serialize = "<-")]
+    Received,
+    /// Connection events such as connecting, closing and errors
+    #[strum(serialize = "--")]
+    Info,
+}
+
+#[derive(Display, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum WebSocketFrameKind {
+    Text,
+    Binary,
+    Ping,
+    Pong,
+    Close,
+    Info,
+}
+
+/// An entry of the log of a WebSocket session. Binary payloads are stored
+/// as space separated hex bytes.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct WebSocketFrame {
+    pub timestamp: DateTime<Local>,
+    pub direction: WebSocketDirection,
+    pub kind: WebSocketFrameKind,
+    pub payload: String,
+}
+
+impl WebSocketFrame {
+    pub fn new(direction: WebSocketDirection, kind: WebSocketFrameKind, payload: String) -> Self {
+        Self {
+            timestamp: Local::now(),
+            direction,
+            kind,
+            payload,
+        }
+    }
+
+    pub fn info(message: String) -> Self {
+        Self::new(WebSocketDirection::Info, WebSocketFrameKind::Info, message)
+    }
+}
+
+/// Parse hex bytes, whitespace between bytes is optional
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let digits = hex.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+    if digits.len() % 2 != 0 {
+        return Err(String::from("odd number of hex digits"));
+    }
+    digits.chunks(2)
+        .map(|pair| {
+            let byte = pair.iter().collect::<String>();
+            u8::from_str_radix(&byte, 16).map_err(|_| format!("invalid hex byte '{}'", byte))
+        })
+        .collect()
+}